@@ -0,0 +1,695 @@
+//! `fundr` command-line tool for creating and operating Fundr funds.
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use fundr_client::instructions::{self, InitializeFundArgs};
+use fundr_client::{
+    legacy, pda, preview, Discriminator, Fund, FundMode, ManagerAction, ProposalAction, UserStake, MAX_FEE_PERIOD,
+    MIN_FEE_PERIOD, PROGRAM_ID, USER_STAKE_FUND_OFFSET,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::{lamports_to_sol, sol_to_lamports};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+#[derive(Parser)]
+#[command(name = "fundr", version, about = "Manage Fundr funds from the command line")]
+struct Cli {
+    /// JSON RPC endpoint
+    #[arg(long, short = 'u', global = true, env = "FUNDR_RPC_URL", default_value = "https://api.devnet.solana.com")]
+    url: String,
+
+    /// Keypair file used to sign (and pay for) transactions
+    #[arg(long, short = 'k', global = true, env = "FUNDR_KEYPAIR", default_value = "~/.config/solana/id.json")]
+    keypair: String,
+
+    /// Simulate the transaction and print its logs instead of sending it
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a fund managed by the signer
+    InitFund {
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        /// Performance fee in basis points (max 2000)
+        #[arg(long, default_value_t = 2000)]
+        performance_fee_bps: u16,
+        /// Minimum investor deposit in SOL
+        #[arg(long)]
+        min_deposit: f64,
+        #[arg(long, value_enum, default_value_t = ModeArg::Manual)]
+        mode: ModeArg,
+        /// Manager seed deposit in SOL (0 for none)
+        #[arg(long, default_value_t = 0.0)]
+        initial_deposit: f64,
+        /// Days between performance fee crystallizations (30 to 365)
+        #[arg(
+            long,
+            default_value_t = MIN_FEE_PERIOD / 86_400,
+            value_parser = clap::value_parser!(i64).range(MIN_FEE_PERIOD / 86_400..=MAX_FEE_PERIOD / 86_400)
+        )]
+        fee_period_days: i64,
+    },
+    /// Deposit SOL into a fund
+    Deposit {
+        #[command(flatten)]
+        fund: FundArg,
+        /// Amount in SOL
+        #[arg(long)]
+        amount: f64,
+        /// Fail if fewer shares than this would be minted (defaults to the previewed
+        /// shares less the slippage tolerance)
+        #[arg(long, conflicts_with = "no_minimum")]
+        min_shares_out: Option<u64>,
+        #[command(flatten)]
+        slippage: SlippageArg,
+    },
+    /// Redeem shares for SOL
+    Withdraw {
+        #[command(flatten)]
+        fund: FundArg,
+        /// Shares to redeem (defaults to the whole position)
+        #[arg(long)]
+        shares: Option<u64>,
+        /// Fail if less than this much SOL (after fees) would be paid out (defaults to
+        /// the previewed payout less the slippage tolerance)
+        #[arg(long, conflicts_with = "no_minimum")]
+        min_out: Option<f64>,
+        #[command(flatten)]
+        slippage: SlippageArg,
+    },
+    /// Crystallize performance fees for the signer's fund into escrow (proposed as an
+    /// action if the fund has a signer set)
+    CollectFees {
+        #[command(flatten)]
+        fund: FundArg,
+    },
+    /// Crystallize performance fees for any fund whose fee period has elapsed
+    CrankFees {
+        #[command(flatten)]
+        fund: FundArg,
+    },
+    /// Claim escrowed performance fees once their delay has passed (a fund with a signer
+    /// set proposes the claim as an action, then redeems it with `--action`)
+    ClaimFees {
+        #[command(flatten)]
+        fund: FundArg,
+        /// Approved claim-fees action redeemed by a fund with a signer set
+        #[arg(long)]
+        action: Option<u64>,
+    },
+    /// Switch the fund between manual and auto allocation (proposed as an action if the
+    /// fund has a signer set)
+    SetMode {
+        #[command(flatten)]
+        fund: FundArg,
+        #[arg(value_enum)]
+        mode: ModeArg,
+    },
+    /// Print a fund's on-chain state
+    ShowFund {
+        #[command(flatten)]
+        fund: FundArg,
+    },
+    /// List every investor stake in a fund
+    ListStakes {
+        #[command(flatten)]
+        fund: FundArg,
+    },
+    /// Upgrade a fund and the signer's stake in it to the current account layout
+    Migrate {
+        #[command(flatten)]
+        fund: FundArg,
+    },
+    /// Open an investor proposal on a fund the signer holds shares in
+    Propose {
+        #[command(flatten)]
+        fund: FundArg,
+        #[arg(value_enum)]
+        action: ProposalArg,
+        /// Manager to hand the fund to (replace-authority only)
+        #[arg(long, required_if_eq("action", "replace-authority"))]
+        new_authority: Option<Pubkey>,
+    },
+    /// Vote with the signer's shares on an open proposal
+    Vote {
+        #[command(flatten)]
+        fund: FundArg,
+        /// Proposal number
+        #[arg(long)]
+        proposal: u64,
+        /// Vote against instead of for
+        #[arg(long)]
+        against: bool,
+    },
+    /// Apply a proposal whose vote has ended and passed
+    ExecuteProposal {
+        #[command(flatten)]
+        fund: FundArg,
+        /// Proposal number
+        #[arg(long)]
+        proposal: u64,
+    },
+    /// Delegate rebalancing to a trader key (omit --trader to revoke)
+    SetTrader {
+        #[command(flatten)]
+        fund: FundArg,
+        #[arg(long)]
+        trader: Option<Pubkey>,
+        /// Largest input amount per trade, in the input token's base units (0 for no cap)
+        #[arg(long, default_value_t = 0)]
+        max_trade_amount: u64,
+    },
+    /// Delegate account cleanup to an operator key (omit --operator to revoke)
+    SetOperator {
+        #[command(flatten)]
+        fund: FundArg,
+        #[arg(long)]
+        operator: Option<Pubkey>,
+    },
+    /// Limit trading, each as basis points of NAV (0 for no limit)
+    SetRiskLimits {
+        #[command(flatten)]
+        fund: FundArg,
+        /// Largest single trade
+        #[arg(long, default_value_t = 0)]
+        max_trade_bps: u16,
+        /// Most traded over a rolling 24 hours
+        #[arg(long, default_value_t = 0)]
+        max_daily_turnover_bps: u16,
+        /// Largest cost basis of any one position
+        #[arg(long, default_value_t = 0)]
+        max_position_bps: u16,
+    },
+    /// Require M-of-N approval for the fund's sensitive actions
+    CreateSignerSet {
+        #[command(flatten)]
+        fund: FundArg,
+        /// Signer public key (repeat for each signer)
+        #[arg(long = "signer", required = true)]
+        signers: Vec<Pubkey>,
+        /// Approvals required to execute an action
+        #[arg(long)]
+        threshold: u8,
+    },
+    /// Approve a pending action as one of the fund's signers
+    ApproveAction {
+        #[command(flatten)]
+        fund: FundArg,
+        /// Action number
+        #[arg(long)]
+        action: u64,
+    },
+    /// Apply a pending action that has enough approvals
+    ExecuteAction {
+        #[command(flatten)]
+        fund: FundArg,
+        /// Action number
+        #[arg(long)]
+        action: u64,
+    },
+}
+
+#[derive(Args)]
+struct FundArg {
+    /// Fund address (defaults to the latest fund opened by the signer)
+    #[arg(long)]
+    fund: Option<Pubkey>,
+}
+
+impl FundArg {
+    fn resolve(&self, rpc: &RpcClient, signer: &Pubkey) -> Result<Pubkey> {
+        match self.fund {
+            Some(fund) => Ok(fund),
+            None => Ok(pda::fund_at(signer, fund_count(rpc, signer)?.saturating_sub(1)).0),
+        }
+    }
+}
+
+#[derive(Args)]
+struct SlippageArg {
+    /// How far below the previewed amount to accept, in basis points
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u16).range(0..=10_000))]
+    slippage_bps: u16,
+    /// Accept whatever the fund pays out instead of a previewed minimum
+    #[arg(long, conflicts_with = "slippage_bps")]
+    no_minimum: bool,
+}
+
+impl SlippageArg {
+    /// The least to accept of the amount `preview` expects, or 0 if the user opted out
+    fn minimum(&self, preview: impl FnOnce() -> Result<u64>) -> Result<u64> {
+        if self.no_minimum {
+            return Ok(0);
+        }
+        let tolerated = preview()? as u128 * (10_000 - self.slippage_bps) as u128 / 10_000;
+        Ok(tolerated as u64)
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ModeArg {
+    Manual,
+    Auto,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ProposalArg {
+    ReplaceAuthority,
+    Pause,
+    Resume,
+    WindDown,
+}
+
+impl From<ModeArg> for FundMode {
+    fn from(mode: ModeArg) -> Self {
+        match mode {
+            ModeArg::Manual => FundMode::Manual,
+            ModeArg::Auto => FundMode::Auto,
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let rpc = RpcClient::new_with_commitment(cli.url.clone(), CommitmentConfig::confirmed());
+
+    // Read-only commands only need a keypair to locate the signer's own fund
+    match &cli.command {
+        Command::ShowFund { fund: FundArg { fund: Some(fund) } } => return show_fund(&rpc, fund),
+        Command::ListStakes { fund: FundArg { fund: Some(fund) } } => return list_stakes(&rpc, fund),
+        _ => {}
+    }
+
+    let signer = load_keypair(&cli.keypair)?;
+    let me = signer.pubkey();
+
+    match &cli.command {
+        Command::InitFund {
+            name,
+            description,
+            performance_fee_bps,
+            min_deposit,
+            mode,
+            initial_deposit,
+            fee_period_days,
+        } => {
+            let fund_index = fund_count(&rpc, &me)?;
+            let ix = instructions::initialize_fund(
+                &me,
+                fund_index,
+                InitializeFundArgs {
+                    name: name.clone(),
+                    description: description.clone(),
+                    performance_fee: *performance_fee_bps,
+                    min_deposit: sol_to_lamports(*min_deposit),
+                    fund_mode: (*mode).into(),
+                    initial_deposit: sol_to_lamports(*initial_deposit),
+                    fee_period: fee_period_days * 86_400,
+                },
+            );
+            println!("Fund: {}", pda::fund_at(&me, fund_index).0);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::Deposit { fund, amount, min_shares_out, slippage } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let amount = sol_to_lamports(*amount);
+            let min_shares_out = match min_shares_out {
+                Some(min_shares_out) => *min_shares_out,
+                None => slippage.minimum(|| {
+                    let expected = preview::deposit(&fetch_fund(&rpc, &fund)?, amount)
+                        .map_err(|_| anyhow!("deposit preview overflows"))?;
+                    Ok(expected.shares)
+                })?,
+            };
+            let ix = instructions::deposit(&fund, &me, amount, min_shares_out);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::Withdraw { fund, shares, min_out, slippage } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let shares = match shares {
+                Some(shares) => *shares,
+                None => fetch_stake(&rpc, &pda::stake(&fund, &me).0)?.shares,
+            };
+            let min_lamports_out = match min_out {
+                Some(min_out) => sol_to_lamports(*min_out),
+                None => slippage.minimum(|| {
+                    let expected = preview::withdraw(&fetch_fund(&rpc, &fund)?, shares)
+                        .map_err(|_| anyhow!("withdrawal preview overflows"))?;
+                    Ok(expected.net)
+                })?,
+            };
+            let ix = instructions::withdraw(&fund, &me, shares, min_lamports_out);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::CollectFees { fund } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let ix = match propose_if_multisig(&rpc, &fund, &me, ManagerAction::CollectFees)? {
+                Some(ix) => ix,
+                None => instructions::collect_fees(&fund, &me),
+            };
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::CrankFees { fund } => {
+            let ix = instructions::crank_fees(&fund.resolve(&rpc, &me)?, &me);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::ClaimFees { fund, action } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let proposal = match action {
+                Some(_) => None,
+                None => propose_if_multisig(&rpc, &fund, &me, ManagerAction::ClaimFees)?,
+            };
+            let ix = proposal.unwrap_or_else(|| instructions::claim_fees(&fund, &me, *action));
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::SetMode { fund, mode } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let action = ManagerAction::SetFundMode { mode: (*mode).into() };
+            let ix = match propose_if_multisig(&rpc, &fund, &me, action)? {
+                Some(ix) => ix,
+                None => instructions::update_fund_mode(&fund, &me, (*mode).into()),
+            };
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::ShowFund { fund } => show_fund(&rpc, &fund.resolve(&rpc, &me)?),
+        Command::ListStakes { fund } => list_stakes(&rpc, &fund.resolve(&rpc, &me)?),
+        Command::Migrate { fund } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let mut ixs = Vec::new();
+            if legacy::upgrade_fund(&rpc.get_account_data(&fund)?).is_ok() {
+                ixs.push(instructions::migrate_fund(&fund, &me));
+            }
+            // The signer may not hold a stake in the fund at all
+            if let Ok(data) = rpc.get_account_data(&pda::stake(&fund, &me).0) {
+                if legacy::upgrade_user_stake(&data).is_ok() {
+                    ixs.push(instructions::migrate_stake(&fund, &me, &me));
+                }
+            }
+            if ixs.is_empty() {
+                println!("Accounts already use the current layout");
+                return Ok(());
+            }
+            submit(&rpc, &signer, ixs, cli.dry_run)
+        }
+        Command::Propose { fund, action, new_authority } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let action = match action {
+                ProposalArg::ReplaceAuthority => ProposalAction::ReplaceAuthority {
+                    new_authority: new_authority.context("--new-authority is required")?,
+                },
+                ProposalArg::Pause => ProposalAction::Pause,
+                ProposalArg::Resume => ProposalAction::Resume,
+                ProposalArg::WindDown => ProposalAction::WindDown,
+            };
+            let id = fetch_fund(&rpc, &fund)?.proposal_count;
+            let ix = instructions::create_proposal(&fund, &me, id, action);
+            println!("Proposal {id}: {}", pda::proposal(&fund, id).0);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::Vote { fund, proposal, against } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let ix = instructions::cast_vote(&fund, &pda::proposal(&fund, *proposal).0, &me, !against);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::ExecuteProposal { fund, proposal } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let multisig = fetch_fund(&rpc, &fund)?.multisig;
+            let ix = instructions::execute_proposal(&fund, &pda::proposal(&fund, *proposal).0, &me, multisig);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::SetTrader { fund, trader, max_trade_amount } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let trader = trader.unwrap_or_default();
+            let action = ManagerAction::SetTrader { trader, max_trade_amount: *max_trade_amount };
+            let ix = match propose_if_multisig(&rpc, &fund, &me, action)? {
+                Some(ix) => ix,
+                None => instructions::set_trader(&fund, &me, &trader, *max_trade_amount),
+            };
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::SetOperator { fund, operator } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let operator = operator.unwrap_or_default();
+            let ix = match propose_if_multisig(&rpc, &fund, &me, ManagerAction::SetOperator { operator })? {
+                Some(ix) => ix,
+                None => instructions::set_operator(&fund, &me, &operator),
+            };
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::SetRiskLimits { fund, max_trade_bps, max_daily_turnover_bps, max_position_bps } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let action = ManagerAction::SetRiskLimits {
+                max_trade_bps: *max_trade_bps,
+                max_daily_turnover_bps: *max_daily_turnover_bps,
+                max_position_bps: *max_position_bps,
+            };
+            let ix = match propose_if_multisig(&rpc, &fund, &me, action)? {
+                Some(ix) => ix,
+                None => instructions::set_risk_limits(&fund, &me, *max_trade_bps, *max_daily_turnover_bps, *max_position_bps),
+            };
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::CreateSignerSet { fund, signers, threshold } => {
+            let ix = instructions::create_signer_set(&fund.resolve(&rpc, &me)?, &me, signers.clone(), *threshold);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::ApproveAction { fund, action } => {
+            let ix = instructions::approve_action(&fund.resolve(&rpc, &me)?, *action, &me);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+        Command::ExecuteAction { fund, action } => {
+            let fund = fund.resolve(&rpc, &me)?;
+            let data = rpc
+                .get_account_data(&pda::pending_action(&fund, *action).0)
+                .with_context(|| format!("no pending action {action}"))?;
+            let pending =
+                fundr_client::deserialize_pending_action(&data).map_err(|err| anyhow!("invalid pending action: {err}"))?;
+            let ix = instructions::execute_action(&fund, *action, &pending.action, &me);
+            submit(&rpc, &signer, vec![ix], cli.dry_run)
+        }
+    }
+}
+
+/// For a fund run by a signer set, an instruction proposing `action` instead of
+/// performing it directly
+fn propose_if_multisig(rpc: &RpcClient, fund: &Pubkey, me: &Pubkey, action: ManagerAction) -> Result<Option<Instruction>> {
+    if !fetch_fund(rpc, fund)?.multisig {
+        return Ok(None);
+    }
+    let data = rpc.get_account_data(&pda::signer_set(fund).0)?;
+    let signer_set = fundr_client::deserialize_signer_set(&data).map_err(|err| anyhow!("invalid signer set: {err}"))?;
+    println!("Proposing action {}", signer_set.action_count);
+    Ok(Some(instructions::propose_action(fund, me, signer_set.action_count, action)))
+}
+
+fn load_keypair(path: &str) -> Result<Keypair> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => format!("{}/{}", std::env::var("HOME").context("HOME is not set")?, rest),
+        None => path.to_string(),
+    };
+    read_keypair_file(&path).map_err(|err| anyhow!("failed to read keypair {path}: {err}"))
+}
+
+/// Sign and send `instructions`, or simulate them and print the program logs on `--dry-run`
+fn submit(rpc: &RpcClient, signer: &Keypair, instructions: Vec<Instruction>, dry_run: bool) -> Result<()> {
+    let blockhash = rpc.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(&instructions, Some(&signer.pubkey()), &[signer], blockhash);
+
+    if dry_run {
+        let result = rpc.simulate_transaction(&tx)?.value;
+        println!("Simulation {}", if result.err.is_none() { "succeeded" } else { "failed" });
+        if let Some(units) = result.units_consumed {
+            println!("Compute units: {units}");
+        }
+        for line in result.logs.unwrap_or_default() {
+            println!("  {line}");
+        }
+        if let Some(err) = result.err {
+            bail!("simulated transaction failed: {err}");
+        }
+        return Ok(());
+    }
+
+    let signature = rpc.send_and_confirm_transaction(&tx)?;
+    println!("Signature: {signature}");
+    Ok(())
+}
+
+fn fetch_stake(rpc: &RpcClient, address: &Pubkey) -> Result<UserStake> {
+    let account = rpc
+        .get_account(address)
+        .with_context(|| format!("no stake account at {address}"))?;
+    fundr_client::deserialize_user_stake(&account.data).map_err(|err| anyhow!("invalid stake account: {err}"))
+}
+
+/// Funds `manager` has opened so far, which is also the index of their next one
+fn fund_count(rpc: &RpcClient, manager: &Pubkey) -> Result<u64> {
+    let address = pda::manager_profile(manager).0;
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(fundr_client::deserialize_manager_profile(&account.data)
+            .map_err(|err| anyhow!("invalid manager profile: {err}"))?
+            .fund_count),
+        None => Ok(0),
+    }
+}
+
+fn fetch_fund(rpc: &RpcClient, address: &Pubkey) -> Result<Fund> {
+    let account = rpc
+        .get_account(address)
+        .with_context(|| format!("no fund account at {address}"))?;
+    fundr_client::deserialize_fund(&account.data).map_err(|err| anyhow!("invalid fund account: {err}"))
+}
+
+fn show_fund(rpc: &RpcClient, address: &Pubkey) -> Result<()> {
+    let fund = fetch_fund(rpc, address)?;
+    let vault_lamports = rpc.get_balance(&pda::vault(address).0)?;
+    let nav = preview::nav_per_share(&fund).map_err(|_| anyhow!("NAV overflows"))?;
+    let accrued_fee = preview::performance_fee(&fund).map_err(|_| anyhow!("performance fee overflows"))?;
+
+    println!("Fund:            {address}");
+    println!("Name:            {}", fund.name);
+    println!("Description:     {}", fund.description);
+    if !fund.uri.is_empty() {
+        println!("URI:             {}", fund.uri);
+    }
+    println!("Authority:       {}", fund.authority);
+    if fund.trader != Pubkey::default() {
+        println!("Trader:          {} (max trade {})", fund.trader, fund.max_trade_amount);
+    }
+    if fund.operator != Pubkey::default() {
+        println!("Operator:        {}", fund.operator);
+    }
+    if fund.max_trade_bps > 0 || fund.max_daily_turnover_bps > 0 || fund.max_position_bps > 0 {
+        println!(
+            "Risk limits:     trade {} bps, daily turnover {} bps, position {} bps",
+            fund.max_trade_bps, fund.max_daily_turnover_bps, fund.max_position_bps
+        );
+    }
+    println!("Status:          {:?}", fund.status);
+    println!("Mode:            {:?}", fund.fund_mode);
+    println!("Performance fee: {}.{:02}%", fund.performance_fee / 100, fund.performance_fee % 100);
+    println!("Min deposit:     {} SOL", lamports_to_sol(fund.min_deposit));
+    println!("Total assets:    {} SOL", lamports_to_sol(fund.total_assets));
+    println!("Vault balance:   {} SOL", lamports_to_sol(vault_lamports));
+    println!("Total shares:    {}", fund.total_shares);
+    println!("NAV per share:   {:.6}", nav as f64 / fundr_client::math::NAV_SCALE as f64);
+    println!("Accrued fee:     {} SOL", lamports_to_sol(accrued_fee));
+    println!("Next fee run:    unix time {}", fund.next_fee_collection());
+    if fund.pending_fees > 0 {
+        println!(
+            "Pending fees:    {} SOL (claimable at unix time {})",
+            lamports_to_sol(fund.pending_fees),
+            fund.fees_claimable_at
+        );
+    }
+    if fund.flagged {
+        println!("Flagged:         yes, fees cannot be claimed");
+    }
+    println!("Investors:       {}", fund.investor_count);
+    println!("Proposals:       {}", fund.proposal_count);
+    if fund.multisig {
+        println!("Signer set:      {}", pda::signer_set(address).0);
+    }
+
+    let portfolio = rpc
+        .get_account_with_commitment(&pda::portfolio(address).0, rpc.commitment())?
+        .value;
+    if let Some(account) = portfolio {
+        let portfolio = fundr_client::deserialize_portfolio(&account.data)
+            .map_err(|err| anyhow!("invalid portfolio account: {err}"))?;
+        println!("Positions:       {}", portfolio.position_count);
+        for position in portfolio.positions() {
+            println!(
+                "  {}  target {}.{:02}%",
+                position.mint,
+                position.target_weight_bps / 100,
+                position.target_weight_bps % 100
+            );
+        }
+    }
+    Ok(())
+}
+
+fn list_stakes(rpc: &RpcClient, fund: &Pubkey) -> Result<()> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, UserStake::DISCRIMINATOR.to_vec())),
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(USER_STAKE_FUND_OFFSET, fund.to_bytes().to_vec())),
+        ]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(solana_account_decoder::UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+
+    let mut stakes = rpc
+        .get_program_accounts_with_config(&PROGRAM_ID, config)?
+        .into_iter()
+        .filter_map(|(address, account)| {
+            fundr_client::deserialize_user_stake(&account.data).ok().map(|stake| (address, stake))
+        })
+        .collect::<Vec<_>>();
+    stakes.sort_by_key(|(_, stake)| std::cmp::Reverse(stake.shares));
+
+    println!("{:<44}  {:>24}  {:>16}", "Investor", "Shares", "Deposited (SOL)");
+    for (_, stake) in &stakes {
+        println!(
+            "{:<44}  {:>24}  {:>16}",
+            stake.user.to_string(),
+            stake.shares,
+            lamports_to_sol(stake.total_deposited)
+        );
+    }
+    println!("{} stake account(s)", stakes.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn minimums_come_from_the_preview_unless_opted_out() {
+        let parse = |args: &[&str]| match Cli::try_parse_from([&["fundr", "deposit", "--amount", "1"], args].concat()) {
+            Ok(Cli { command: Command::Deposit { slippage, .. }, .. }) => slippage,
+            _ => panic!("expected a deposit"),
+        };
+
+        assert_eq!(parse(&[]).minimum(|| Ok(10_000)).unwrap(), 9_900);
+        assert_eq!(parse(&["--slippage-bps", "250"]).minimum(|| Ok(10_000)).unwrap(), 9_750);
+        assert_eq!(parse(&["--no-minimum"]).minimum(|| unreachable!()).unwrap(), 0);
+        assert!(Cli::try_parse_from(["fundr", "deposit", "--amount", "1", "--slippage-bps", "10001"]).is_err());
+        assert!(Cli::try_parse_from(["fundr", "deposit", "--amount", "1", "--min-shares-out", "5", "--no-minimum"]).is_err());
+    }
+
+    #[test]
+    fn fee_period_stays_within_the_program_limits() {
+        let parse = |days: &str| Cli::try_parse_from(["fundr", "init-fund", "--name", "f", "--min-deposit", "1", "--fee-period-days", days]);
+
+        assert!(matches!(parse("365"), Ok(Cli { command: Command::InitFund { fee_period_days: 365, .. }, .. })));
+        assert!(parse("29").is_err());
+        assert!(parse("366").is_err());
+        assert!(parse(&i64::MAX.to_string()).is_err());
+    }
+}
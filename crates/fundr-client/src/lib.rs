@@ -0,0 +1,925 @@
+//! Rust client SDK for the Fundr program.
+//!
+//! Provides PDA derivation for the `fund`/`vault`/`stake`/`portfolio`/`config`/`proposal`/
+//! `vote`/`signers`/`action`/`price` seeds, typed instruction builders for every program
+//! instruction, deserializers for `Fund`, `UserStake`, `Portfolio`, `Proposal`,
+//! `SignerSet`, `PendingAction`, `ProtocolConfig` and `PriceFeed`, and previews of
+//! deposits, withdrawals, fees and rebalance bounds computed with the program's own math.
+
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{system_program, AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{get_associated_token_address, get_associated_token_address_with_program_id};
+use anchor_spl::token::spl_token::native_mint;
+
+pub use anchor_lang::Discriminator;
+pub use fundr::{
+    Fund, FundMode, FundStatus, ManagerAction, ManagerProfile, PendingAction, Portfolio, Position, PriceFeed, Proposal, ProposalAction,
+    ProtocolConfig, SignerSet, UserStake, VoteRecord, ID as PROGRAM_ID, MAX_FEE_PERIOD, MIN_FEE_PERIOD,
+};
+pub use fundr::legacy;
+pub use fundr_math as math;
+
+/// Byte offset of `UserStake.fund` (after the discriminator and `user`), for
+/// `getProgramAccounts` memcmp filters
+pub const USER_STAKE_FUND_OFFSET: usize = 8 + 32;
+
+/// PDA derivation helpers matching the program's account seeds
+pub mod pda {
+    use super::*;
+
+    /// A manager's first fund: `["fund", manager]`
+    pub fn fund(manager: &Pubkey) -> (Pubkey, u8) {
+        fund_at(manager, 0)
+    }
+
+    /// A manager's `index`th fund: `["fund", manager, index]`, with the index left out of
+    /// the first fund's seeds
+    pub fn fund_at(manager: &Pubkey, index: u64) -> (Pubkey, u8) {
+        let index_seed = fundr::fund_index_seed(index);
+        Pubkey::find_program_address(&[b"fund", manager.as_ref(), &index_seed], &PROGRAM_ID)
+    }
+
+    /// Per-manager fund counter PDA: `["manager", manager]`
+    pub fn manager_profile(manager: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"manager", manager.as_ref()], &PROGRAM_ID)
+    }
+
+    /// SOL vault PDA: `["vault", fund]`
+    pub fn vault(fund: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"vault", fund.as_ref()], &PROGRAM_ID)
+    }
+
+    /// Investor stake PDA: `["stake", fund, user]`
+    pub fn stake(fund: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"stake", fund.as_ref(), user.as_ref()], &PROGRAM_ID)
+    }
+
+    /// Protocol config PDA: `["config"]`
+    pub fn config() -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"config"], &PROGRAM_ID)
+    }
+
+    /// The program's upgradeable-loader data account, which records its upgrade authority
+    pub fn program_data() -> Pubkey {
+        Pubkey::find_program_address(&[PROGRAM_ID.as_ref()], &bpf_loader_upgradeable::ID).0
+    }
+
+    /// Zero-copy portfolio PDA: `["portfolio", fund]`
+    pub fn portfolio(fund: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"portfolio", fund.as_ref()], &PROGRAM_ID)
+    }
+
+    /// Governance proposal PDA: `["proposal", fund, id]`, with `id` little-endian
+    pub fn proposal(fund: &Pubkey, id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"proposal", fund.as_ref(), &id.to_le_bytes()], &PROGRAM_ID)
+    }
+
+    /// Vote record PDA: `["vote", proposal, voter]`
+    pub fn vote(proposal: &Pubkey, voter: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"vote", proposal.as_ref(), voter.as_ref()], &PROGRAM_ID)
+    }
+
+    /// Manager signer set PDA: `["signers", fund]`
+    pub fn signer_set(fund: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"signers", fund.as_ref()], &PROGRAM_ID)
+    }
+
+    /// Pending manager action PDA: `["action", fund, id]`, with `id` little-endian
+    pub fn pending_action(fund: &Pubkey, id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"action", fund.as_ref(), &id.to_le_bytes()], &PROGRAM_ID)
+    }
+
+    /// Oracle price PDA: `["price", mint]`
+    pub fn price_feed(mint: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"price", mint.as_ref()], &PROGRAM_ID)
+    }
+
+    /// The fund's wrapped SOL associated token account, counted by `sync_assets`
+    pub fn wsol_account(fund: &Pubkey) -> Pubkey {
+        get_associated_token_address(fund, &native_mint::ID)
+    }
+}
+
+/// Deserialize a `Fund` account, checking its discriminator
+pub fn deserialize_fund(data: &[u8]) -> anchor_lang::Result<Fund> {
+    Fund::try_deserialize(&mut &data[..])
+}
+
+/// Deserialize a `ManagerProfile` account, checking its discriminator
+pub fn deserialize_manager_profile(data: &[u8]) -> anchor_lang::Result<ManagerProfile> {
+    ManagerProfile::try_deserialize(&mut &data[..])
+}
+
+/// Deserialize a `UserStake` account, checking its discriminator
+pub fn deserialize_user_stake(data: &[u8]) -> anchor_lang::Result<UserStake> {
+    UserStake::try_deserialize(&mut &data[..])
+}
+
+/// Copy a `Portfolio` account out of its zero-copy layout, checking its discriminator
+pub fn deserialize_portfolio(data: &[u8]) -> anchor_lang::Result<Portfolio> {
+    Portfolio::try_deserialize(&mut &data[..])
+}
+
+/// Deserialize a `Proposal` account, checking its discriminator
+pub fn deserialize_proposal(data: &[u8]) -> anchor_lang::Result<Proposal> {
+    Proposal::try_deserialize(&mut &data[..])
+}
+
+/// Deserialize a `SignerSet` account, checking its discriminator
+pub fn deserialize_signer_set(data: &[u8]) -> anchor_lang::Result<SignerSet> {
+    SignerSet::try_deserialize(&mut &data[..])
+}
+
+/// Deserialize a `PendingAction` account, checking its discriminator
+pub fn deserialize_pending_action(data: &[u8]) -> anchor_lang::Result<PendingAction> {
+    PendingAction::try_deserialize(&mut &data[..])
+}
+
+/// Deserialize the `ProtocolConfig` account, checking its discriminator
+pub fn deserialize_config(data: &[u8]) -> anchor_lang::Result<ProtocolConfig> {
+    ProtocolConfig::try_deserialize(&mut &data[..])
+}
+
+/// Deserialize a `PriceFeed` account, checking its discriminator
+pub fn deserialize_price_feed(data: &[u8]) -> anchor_lang::Result<PriceFeed> {
+    PriceFeed::try_deserialize(&mut &data[..])
+}
+
+/// The non-SOL side of a rebalance, whose price feed bounds the trade
+fn priced_mint(token_in_mint: &Pubkey, token_out_mint: &Pubkey) -> Pubkey {
+    if *token_in_mint == native_mint::ID { *token_out_mint } else { *token_in_mint }
+}
+
+/// Previews of instruction outcomes for a fetched `Fund`. They run the same
+/// `fundr_math` code as the program, so they match on-chain results for that state.
+pub mod preview {
+    use super::*;
+    use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFee;
+    use fundr_math::{DepositPreview, MathError, MathResult, WithdrawPreview};
+
+    fn fee_waived(fund: &Fund) -> bool {
+        fund.status == FundStatus::WindingDown
+    }
+
+    /// Platform fee and shares minted by `deposit(amount)`
+    pub fn deposit(fund: &Fund, amount: u64) -> MathResult<DepositPreview> {
+        fundr_math::preview_deposit(amount, fund.total_assets, fund.total_shares)
+    }
+
+    /// Payout of `withdraw(shares)`
+    pub fn withdraw(fund: &Fund, shares: u64) -> MathResult<WithdrawPreview> {
+        fundr_math::preview_withdraw(shares, fund.total_assets, fund.total_shares, fee_waived(fund))
+    }
+
+    /// Shares burned by `withdraw_exact(lamports_out)`
+    pub fn withdraw_exact(fund: &Fund, lamports_out: u64) -> MathResult<WithdrawPreview> {
+        fundr_math::preview_withdraw_exact(lamports_out, fund.total_assets, fund.total_shares, fee_waived(fund))
+    }
+
+    /// Current NAV per share in `NAV_SCALE` fixed point
+    pub fn nav_per_share(fund: &Fund) -> MathResult<u64> {
+        fundr_math::nav_per_share(fund.total_assets, fund.total_shares)
+    }
+
+    /// Lamports `collect_fees` would move into the fee escrow right now; the manager
+    /// claims them with `claim_fees` once the protocol's claim delay has passed
+    pub fn performance_fee(fund: &Fund) -> MathResult<u64> {
+        fundr_math::performance_fee(
+            fund.total_shares,
+            nav_per_share(fund)?,
+            fund.high_water_mark,
+            fund.performance_fee,
+        )
+    }
+
+    /// Smallest `minimum_amount_out` a rebalance of `token_in_amount` may ask for at the
+    /// feed's current price under the protocol's slippage tolerance. The program checks
+    /// it after the output mint's transfer fee, so pass `output_fee`, the Token-2022
+    /// output mint's fee for the current epoch (`TransferFeeConfig::get_epoch_fee`), to
+    /// ask for enough more to cover it.
+    pub fn minimum_amount_out(
+        config: &ProtocolConfig,
+        feed: &PriceFeed,
+        token_in_mint: &Pubkey,
+        token_in_amount: u64,
+        output_fee: Option<&TransferFee>,
+    ) -> MathResult<u64> {
+        let buying = *token_in_mint == native_mint::ID;
+        let floor = fundr_math::minimum_swap_output(token_in_amount, feed.price, buying, config.slippage_bps)?;
+        match output_fee {
+            // A fee that takes everything has no amount that clears the floor
+            Some(fee) => fee
+                .calculate_pre_fee_amount(floor)
+                .filter(|gross| *gross > 0 || floor == 0)
+                .ok_or(MathError::Overflow),
+            None => Ok(floor),
+        }
+    }
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn with_remaining(mut ix: Instruction, remaining: &[Pubkey]) -> Instruction {
+    ix.accounts
+        .extend(remaining.iter().map(|key| AccountMeta::new(*key, false)));
+    ix
+}
+
+/// Typed builders for every `fundr` instruction
+pub mod instructions {
+    use super::*;
+    use fundr::{accounts, instruction};
+
+    /// Arguments for [`initialize_fund`]
+    #[derive(Clone, Debug)]
+    pub struct InitializeFundArgs {
+        pub name: String,
+        pub description: String,
+        pub performance_fee: u16,
+        pub min_deposit: u64,
+        pub fund_mode: FundMode,
+        pub initial_deposit: u64,
+        pub fee_period: i64,
+    }
+
+    /// Open the manager's `fund_index`th fund; the index is the manager profile's
+    /// `fund_count`, or 0 if the manager has none yet
+    pub fn initialize_fund(manager: &Pubkey, fund_index: u64, args: InitializeFundArgs) -> Instruction {
+        let fund = pda::fund_at(manager, fund_index).0;
+        build(
+            accounts::InitializeFund {
+                manager_profile: pda::manager_profile(manager).0,
+                fund,
+                fund_vault: pda::vault(&fund).0,
+                manager_stake: (args.initial_deposit > 0).then(|| pda::stake(&fund, manager).0),
+                manager: *manager,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeFund {
+                name: args.name,
+                description: args.description,
+                performance_fee: args.performance_fee,
+                min_deposit: args.min_deposit,
+                fund_mode: args.fund_mode,
+                initial_deposit: args.initial_deposit,
+                fee_period: args.fee_period,
+            },
+        )
+    }
+
+    pub fn deposit(fund: &Pubkey, depositor: &Pubkey, amount: u64, min_shares_out: u64) -> Instruction {
+        build(
+            accounts::Deposit {
+                fund: *fund,
+                user_stake: pda::stake(fund, depositor).0,
+                fund_vault: pda::vault(fund).0,
+                depositor: *depositor,
+                system_program: system_program::ID,
+            },
+            instruction::Deposit { amount, min_shares_out },
+        )
+    }
+
+    fn withdraw_accounts(fund: &Pubkey, withdrawer: &Pubkey) -> accounts::Withdraw {
+        accounts::Withdraw {
+            fund: *fund,
+            user_stake: pda::stake(fund, withdrawer).0,
+            fund_vault: pda::vault(fund).0,
+            withdrawer: *withdrawer,
+            system_program: system_program::ID,
+        }
+    }
+
+    pub fn withdraw(
+        fund: &Pubkey,
+        withdrawer: &Pubkey,
+        shares_to_redeem: u64,
+        min_lamports_out: u64,
+    ) -> Instruction {
+        build(
+            withdraw_accounts(fund, withdrawer),
+            instruction::Withdraw { shares_to_redeem, min_lamports_out },
+        )
+    }
+
+    pub fn withdraw_exact(
+        fund: &Pubkey,
+        withdrawer: &Pubkey,
+        lamports_out: u64,
+        max_shares_in: u64,
+    ) -> Instruction {
+        build(
+            withdraw_accounts(fund, withdrawer),
+            instruction::WithdrawExact { lamports_out, max_shares_in },
+        )
+    }
+
+    pub fn rebalance(
+        fund: &Pubkey,
+        manager: &Pubkey,
+        token_program: &Pubkey,
+        token_in_mint: &Pubkey,
+        token_in_amount: u64,
+        token_out_mint: &Pubkey,
+        minimum_amount_out: u64,
+    ) -> Instruction {
+        build(
+            accounts::Rebalance {
+                fund: *fund,
+                manager: *manager,
+                output_mint: *token_out_mint,
+                portfolio: pda::portfolio(fund).0,
+                token_program: *token_program,
+                config: pda::config().0,
+                price_feed: pda::price_feed(&priced_mint(token_in_mint, token_out_mint)).0,
+            },
+            instruction::Rebalance {
+                token_in_mint: *token_in_mint,
+                token_in_amount,
+                token_out_mint: *token_out_mint,
+                minimum_amount_out,
+            },
+        )
+    }
+
+    pub fn collect_fees(fund: &Pubkey, manager: &Pubkey) -> Instruction {
+        build(
+            accounts::CollectFees {
+                fund: *fund,
+                config: pda::config().0,
+                manager: *manager,
+            },
+            instruction::CollectFees {},
+        )
+    }
+
+    pub fn crank_fees(fund: &Pubkey, cranker: &Pubkey) -> Instruction {
+        build(
+            accounts::CrankFees {
+                fund: *fund,
+                config: pda::config().0,
+                cranker: *cranker,
+            },
+            instruction::CrankFees {},
+        )
+    }
+
+    /// A fund with a signer set redeems the approved `ManagerAction::ClaimFees` numbered `approval`
+    pub fn claim_fees(fund: &Pubkey, manager: &Pubkey, approval: Option<u64>) -> Instruction {
+        build(
+            accounts::ClaimFees {
+                fund: *fund,
+                fund_vault: pda::vault(fund).0,
+                manager: *manager,
+                signer_set: approval.map(|_| pda::signer_set(fund).0),
+                pending_action: approval.map(|id| pda::pending_action(fund, id).0),
+                system_program: system_program::ID,
+            },
+            instruction::ClaimFees {},
+        )
+    }
+
+    pub fn update_fund_mode(fund: &Pubkey, manager: &Pubkey, new_mode: FundMode) -> Instruction {
+        build(
+            accounts::UpdateFundMode { fund: *fund, manager: *manager },
+            instruction::UpdateFundMode { new_mode },
+        )
+    }
+
+    pub fn reclaim_rent(fund: &Pubkey, manager: &Pubkey, stake_owner: &Pubkey) -> Instruction {
+        build(
+            accounts::ReclaimRent {
+                fund: *fund,
+                manager: *manager,
+                closed_stake: pda::stake(fund, stake_owner).0,
+                stake_owner: *stake_owner,
+            },
+            instruction::ReclaimRent {},
+        )
+    }
+
+    pub fn close_stake(fund: &Pubkey, user: &Pubkey) -> Instruction {
+        build(
+            accounts::CloseStake { user_stake: pda::stake(fund, user).0, user: *user },
+            instruction::CloseStake {},
+        )
+    }
+
+    pub fn close_token_account(
+        fund: &Pubkey,
+        manager: &Pubkey,
+        token_account: &Pubkey,
+        token_program: &Pubkey,
+    ) -> Instruction {
+        build(
+            accounts::CloseTokenAccount {
+                fund: *fund,
+                fund_vault: pda::vault(fund).0,
+                manager: *manager,
+                token_account: *token_account,
+                portfolio: pda::portfolio(fund).0,
+                token_program: *token_program,
+            },
+            instruction::CloseTokenAccount {},
+        )
+    }
+
+    pub fn close_token_accounts(
+        fund: &Pubkey,
+        manager: &Pubkey,
+        token_program: &Pubkey,
+        token_accounts: &[Pubkey],
+    ) -> Instruction {
+        let ix = build(
+            accounts::CloseTokenAccounts {
+                fund: *fund,
+                fund_vault: pda::vault(fund).0,
+                manager: *manager,
+                portfolio: pda::portfolio(fund).0,
+                token_program: *token_program,
+            },
+            instruction::CloseTokenAccounts {},
+        );
+        with_remaining(ix, token_accounts)
+    }
+
+    pub fn update_fund_metadata(
+        fund: &Pubkey,
+        manager: &Pubkey,
+        name: Option<String>,
+        description: Option<String>,
+        uri: Option<String>,
+    ) -> Instruction {
+        build(
+            accounts::UpdateFundMetadata {
+                fund: *fund,
+                manager: *manager,
+            },
+            instruction::UpdateFundMetadata { name, description, uri },
+        )
+    }
+
+    /// `positions` lists the (mint, token program) of every position in the fund's
+    /// portfolio, in order, so the program can check the fund holds none of them
+    pub fn sync_assets(fund: &Pubkey, positions: &[(Pubkey, Pubkey)]) -> Instruction {
+        let mut ix = build(
+            accounts::SyncAssets {
+                fund: *fund,
+                fund_vault: pda::vault(fund).0,
+                wsol_account: pda::wsol_account(fund),
+                portfolio: pda::portfolio(fund).0,
+            },
+            instruction::SyncAssets {},
+        );
+        for (mint, token_program) in positions {
+            ix.accounts.push(AccountMeta::new_readonly(*mint, false));
+            let token_account = get_associated_token_address_with_program_id(fund, mint, token_program);
+            ix.accounts.push(AccountMeta::new_readonly(token_account, false));
+        }
+        ix
+    }
+
+    pub fn initiate_wind_down(fund: &Pubkey, manager: &Pubkey) -> Instruction {
+        build(
+            accounts::InitiateWindDown { fund: *fund, manager: *manager },
+            instruction::InitiateWindDown {},
+        )
+    }
+
+    pub fn force_distribute(fund: &Pubkey, investor: &Pubkey, cranker: &Pubkey) -> Instruction {
+        build(
+            accounts::ForceDistribute {
+                fund: *fund,
+                user_stake: pda::stake(fund, investor).0,
+                fund_vault: pda::vault(fund).0,
+                investor: *investor,
+                cranker: *cranker,
+                system_program: system_program::ID,
+            },
+            instruction::ForceDistribute {},
+        )
+    }
+
+    /// Close a wound-down fund. `creator` is the manager the fund was opened by
+    /// (`Fund::seed_key`), and `platform` the protocol admin, who receives the retained
+    /// platform fees. Set `has_portfolio` if the fund's portfolio was initialized so its
+    /// rent is returned as well. A fund with a signer set redeems the approved
+    /// `ManagerAction::CloseFund` numbered `approval`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn close_fund(
+        fund: &Pubkey,
+        creator: &Pubkey,
+        manager: &Pubkey,
+        platform: &Pubkey,
+        has_portfolio: bool,
+        approval: Option<u64>,
+        token_program: &Pubkey,
+        token_accounts: &[Pubkey],
+    ) -> Instruction {
+        let ix = build(
+            accounts::CloseFund {
+                fund: *fund,
+                manager_profile: pda::manager_profile(creator).0,
+                config: pda::config().0,
+                platform: *platform,
+                fund_vault: pda::vault(fund).0,
+                manager: *manager,
+                portfolio: has_portfolio.then(|| pda::portfolio(fund).0),
+                signer_set: approval.map(|_| pda::signer_set(fund).0),
+                pending_action: approval.map(|id| pda::pending_action(fund, id).0),
+                token_program: *token_program,
+                system_program: system_program::ID,
+            },
+            instruction::CloseFund {},
+        );
+        with_remaining(ix, token_accounts)
+    }
+
+    pub fn initialize_portfolio(fund: &Pubkey, manager: &Pubkey) -> Instruction {
+        build(
+            accounts::InitializePortfolio {
+                fund: *fund,
+                portfolio: pda::portfolio(fund).0,
+                manager: *manager,
+                system_program: system_program::ID,
+            },
+            instruction::InitializePortfolio {},
+        )
+    }
+
+    pub fn set_position(fund: &Pubkey, manager: &Pubkey, mint: &Pubkey, target_weight_bps: u16) -> Instruction {
+        build(
+            accounts::SetPosition {
+                fund: *fund,
+                portfolio: pda::portfolio(fund).0,
+                mint: *mint,
+                manager: *manager,
+            },
+            instruction::SetPosition { target_weight_bps },
+        )
+    }
+
+    pub fn remove_position(fund: &Pubkey, manager: &Pubkey, mint: &Pubkey) -> Instruction {
+        build(
+            accounts::RemovePosition {
+                fund: *fund,
+                portfolio: pda::portfolio(fund).0,
+                manager: *manager,
+            },
+            instruction::RemovePosition { mint: *mint },
+        )
+    }
+
+    /// Create the protocol config; `upgrade_authority` must be the program's upgrade authority
+    pub fn initialize_config(upgrade_authority: &Pubkey, admin: &Pubkey, fee_claim_delay: i64) -> Instruction {
+        build(
+            accounts::InitializeConfig {
+                config: pda::config().0,
+                program: PROGRAM_ID,
+                program_data: pda::program_data(),
+                upgrade_authority: *upgrade_authority,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeConfig { admin: *admin, fee_claim_delay },
+        )
+    }
+
+    pub fn update_config(admin: &Pubkey, new_admin: Option<Pubkey>, fee_claim_delay: Option<i64>) -> Instruction {
+        build(
+            accounts::UpdateConfig { config: pda::config().0, admin: *admin },
+            instruction::UpdateConfig { admin: new_admin, fee_claim_delay },
+        )
+    }
+
+    fn admin_fund_action(fund: &Pubkey, admin: &Pubkey) -> accounts::AdminFundAction {
+        accounts::AdminFundAction {
+            config: pda::config().0,
+            fund: *fund,
+            admin: *admin,
+        }
+    }
+
+    pub fn set_fund_flag(fund: &Pubkey, admin: &Pubkey, flagged: bool) -> Instruction {
+        build(admin_fund_action(fund, admin), instruction::SetFundFlag { flagged })
+    }
+
+    pub fn void_fees(fund: &Pubkey, admin: &Pubkey) -> Instruction {
+        build(admin_fund_action(fund, admin), instruction::VoidFees {})
+    }
+
+    pub fn set_governance_params(
+        admin: &Pubkey,
+        quorum_bps: u16,
+        approval_threshold_bps: u16,
+        voting_period: i64,
+    ) -> Instruction {
+        build(
+            accounts::UpdateConfig { config: pda::config().0, admin: *admin },
+            instruction::SetGovernanceParams { quorum_bps, approval_threshold_bps, voting_period },
+        )
+    }
+
+    pub fn set_oracle_params(admin: &Pubkey, slippage_bps: u16, max_price_age: i64) -> Instruction {
+        build(
+            accounts::UpdateConfig { config: pda::config().0, admin: *admin },
+            instruction::SetOracleParams { slippage_bps, max_price_age },
+        )
+    }
+
+    /// Publish `mint`'s price in lamports per base unit, scaled by `PRICE_SCALE`
+    pub fn publish_price(admin: &Pubkey, mint: &Pubkey, price: u64) -> Instruction {
+        build(
+            accounts::PublishPrice {
+                config: pda::config().0,
+                mint: *mint,
+                price_feed: pda::price_feed(mint).0,
+                admin: *admin,
+                system_program: system_program::ID,
+            },
+            instruction::PublishPrice { price },
+        )
+    }
+
+    /// Open proposal number `id`, which must be the fund's current `proposal_count`
+    pub fn create_proposal(fund: &Pubkey, proposer: &Pubkey, id: u64, action: ProposalAction) -> Instruction {
+        build(
+            accounts::CreateProposal {
+                fund: *fund,
+                config: pda::config().0,
+                proposal: pda::proposal(fund, id).0,
+                proposer_stake: pda::stake(fund, proposer).0,
+                proposer: *proposer,
+                system_program: system_program::ID,
+            },
+            instruction::CreateProposal { action },
+        )
+    }
+
+    pub fn cast_vote(fund: &Pubkey, proposal: &Pubkey, voter: &Pubkey, support: bool) -> Instruction {
+        build(
+            accounts::CastVote {
+                proposal: *proposal,
+                fund: *fund,
+                voter_stake: pda::stake(fund, voter).0,
+                vote_record: pda::vote(proposal, voter).0,
+                voter: *voter,
+                system_program: system_program::ID,
+            },
+            instruction::CastVote { support },
+        )
+    }
+
+    /// Execute a passed proposal. `multisig` is the fund's flag of the same name: replacing
+    /// the authority of a multisig fund also voids its signer set.
+    pub fn execute_proposal(fund: &Pubkey, proposal: &Pubkey, executor: &Pubkey, multisig: bool) -> Instruction {
+        build(
+            accounts::ExecuteProposal {
+                proposal: *proposal,
+                fund: *fund,
+                signer_set: multisig.then(|| pda::signer_set(fund).0),
+                executor: *executor,
+            },
+            instruction::ExecuteProposal {},
+        )
+    }
+
+    pub fn create_signer_set(fund: &Pubkey, manager: &Pubkey, signers: Vec<Pubkey>, threshold: u8) -> Instruction {
+        build(
+            accounts::CreateSignerSet {
+                fund: *fund,
+                signer_set: pda::signer_set(fund).0,
+                manager: *manager,
+                system_program: system_program::ID,
+            },
+            instruction::CreateSignerSet { signers, threshold },
+        )
+    }
+
+    /// Propose action number `id`, which must be the signer set's current `action_count`
+    pub fn propose_action(fund: &Pubkey, proposer: &Pubkey, id: u64, action: ManagerAction) -> Instruction {
+        build(
+            accounts::ProposeAction {
+                signer_set: pda::signer_set(fund).0,
+                pending_action: pda::pending_action(fund, id).0,
+                proposer: *proposer,
+                system_program: system_program::ID,
+            },
+            instruction::ProposeAction { action },
+        )
+    }
+
+    pub fn approve_action(fund: &Pubkey, id: u64, signer: &Pubkey) -> Instruction {
+        build(
+            accounts::ApproveAction {
+                signer_set: pda::signer_set(fund).0,
+                pending_action: pda::pending_action(fund, id).0,
+                signer: *signer,
+            },
+            instruction::ApproveAction {},
+        )
+    }
+
+    /// Execute pending action `id`, passing only the accounts `action` touches
+    pub fn execute_action(fund: &Pubkey, id: u64, action: &ManagerAction, executor: &Pubkey) -> Instruction {
+        let (config, portfolio, mint) = match action {
+            ManagerAction::CollectFees => (Some(pda::config().0), None, None),
+            ManagerAction::SetPosition { mint, .. } => (None, Some(pda::portfolio(fund).0), Some(*mint)),
+            ManagerAction::RemovePosition { .. } => (None, Some(pda::portfolio(fund).0), None),
+            _ => (None, None, None),
+        };
+        build(
+            accounts::ExecuteAction {
+                fund: *fund,
+                signer_set: pda::signer_set(fund).0,
+                pending_action: pda::pending_action(fund, id).0,
+                config,
+                portfolio,
+                mint,
+                executor: *executor,
+            },
+            instruction::ExecuteAction {},
+        )
+    }
+
+    /// Delegate rebalancing to `trader` (the default pubkey revokes it)
+    pub fn set_trader(fund: &Pubkey, owner: &Pubkey, trader: &Pubkey, max_trade_amount: u64) -> Instruction {
+        build(
+            accounts::SetRole { fund: *fund, owner: *owner },
+            instruction::SetTrader { trader: *trader, max_trade_amount },
+        )
+    }
+
+    /// Delegate account cleanup to `operator` (the default pubkey revokes it)
+    pub fn set_operator(fund: &Pubkey, owner: &Pubkey, operator: &Pubkey) -> Instruction {
+        build(
+            accounts::SetRole { fund: *fund, owner: *owner },
+            instruction::SetOperator { operator: *operator },
+        )
+    }
+
+    /// Cap single trades, rolling daily turnover and position cost basis, each in
+    /// basis points of NAV (0 = no limit)
+    pub fn set_risk_limits(
+        fund: &Pubkey,
+        owner: &Pubkey,
+        max_trade_bps: u16,
+        max_daily_turnover_bps: u16,
+        max_position_bps: u16,
+    ) -> Instruction {
+        build(
+            accounts::SetRole { fund: *fund, owner: *owner },
+            instruction::SetRiskLimits { max_trade_bps, max_daily_turnover_bps, max_position_bps },
+        )
+    }
+
+    pub fn migrate_fund(fund: &Pubkey, payer: &Pubkey) -> Instruction {
+        build(
+            accounts::MigrateFund {
+                fund: *fund,
+                payer: *payer,
+                system_program: system_program::ID,
+            },
+            instruction::MigrateFund {},
+        )
+    }
+
+    pub fn migrate_stake(fund: &Pubkey, user: &Pubkey, payer: &Pubkey) -> Instruction {
+        build(
+            accounts::MigrateStake {
+                user_stake: pda::stake(fund, user).0,
+                payer: *payer,
+                system_program: system_program::ID,
+            },
+            instruction::MigrateStake {},
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AccountSerialize;
+
+    #[test]
+    fn deposit_targets_derived_accounts() {
+        let manager = Pubkey::new_unique();
+        let depositor = Pubkey::new_unique();
+        let fund = pda::fund(&manager).0;
+
+        let ix = instructions::deposit(&fund, &depositor, 1_000_000_000, 1);
+
+        assert_eq!(ix.program_id, PROGRAM_ID);
+        assert_eq!(ix.accounts[0].pubkey, fund);
+        assert_eq!(ix.accounts[1].pubkey, pda::stake(&fund, &depositor).0);
+        assert_eq!(ix.accounts[2].pubkey, pda::vault(&fund).0);
+        assert!(ix.accounts[3].is_signer);
+        assert_eq!(&ix.data[..8], &fundr::instruction::Deposit::DISCRIMINATOR);
+    }
+
+    #[test]
+    fn batch_close_appends_token_accounts() {
+        let fund = Pubkey::new_unique();
+        let manager = Pubkey::new_unique();
+        let token_accounts = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        let ix = instructions::close_token_accounts(
+            &fund,
+            &manager,
+            &anchor_spl::token::ID,
+            &token_accounts,
+        );
+
+        let tail: Vec<Pubkey> = ix.accounts[5..].iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(tail, token_accounts);
+        assert!(ix.accounts[5..].iter().all(|meta| meta.is_writable && !meta.is_signer));
+    }
+
+    #[test]
+    fn withdraw_preview_waives_fee_during_wind_down() {
+        let mut fund = Fund {
+            performance_fee: 2000,
+            total_shares: 2_000_000_000 * math::VIRTUAL_SHARES,
+            total_assets: 2_000_000_000,
+            investor_count: 1,
+            ..Fund::default()
+        };
+        let shares = 1_000_000_000 * math::VIRTUAL_SHARES;
+
+        let active = preview::withdraw(&fund, shares).unwrap();
+        assert_eq!(active.fee, active.gross / 100);
+        assert_eq!(preview::performance_fee(&fund).unwrap(), 0);
+
+        fund.status = FundStatus::WindingDown;
+        let winding_down = preview::withdraw(&fund, shares).unwrap();
+        assert_eq!(winding_down.fee, 0);
+        assert_eq!(winding_down.net, active.gross);
+    }
+
+    #[test]
+    fn minimum_amount_out_covers_the_output_transfer_fee() {
+        use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFee;
+
+        let config = ProtocolConfig {
+            admin: Pubkey::new_unique(),
+            fee_claim_delay: fundr::MIN_FEE_CLAIM_DELAY,
+            bump: 255,
+            quorum_bps: 0,
+            approval_threshold_bps: 0,
+            voting_period: 0,
+            slippage_bps: 100,
+            max_price_age: 0,
+            _reserved: [0; 42],
+        };
+        let feed = PriceFeed { mint: Pubkey::new_unique(), price: 2 * math::PRICE_SCALE, updated_at: 0, bump: 255 };
+        let fee = |bps: u16| TransferFee {
+            epoch: 0.into(),
+            maximum_fee: u64::MAX.into(),
+            transfer_fee_basis_points: bps.into(),
+        };
+
+        let floor = preview::minimum_amount_out(&config, &feed, &native_mint::ID, 1_000_000_001, None).unwrap();
+        let taxed = fee(250);
+        let gross = preview::minimum_amount_out(&config, &feed, &native_mint::ID, 1_000_000_001, Some(&taxed)).unwrap();
+        assert!(gross > floor);
+        // Exactly enough that what arrives after the fee still clears the floor
+        assert!(taxed.calculate_post_fee_amount(gross).unwrap() >= floor);
+        assert!(taxed.calculate_post_fee_amount(gross - 1).unwrap() < floor);
+
+        let untaxed = fee(0);
+        assert_eq!(preview::minimum_amount_out(&config, &feed, &native_mint::ID, 1_000_000_001, Some(&untaxed)), Ok(floor));
+        let confiscatory = fee(10_000);
+        assert!(preview::minimum_amount_out(&config, &feed, &native_mint::ID, 1_000_000_001, Some(&confiscatory)).is_err());
+    }
+
+    #[test]
+    fn user_stake_round_trips() {
+        let stake = UserStake {
+            user: Pubkey::new_unique(),
+            fund: Pubkey::new_unique(),
+            shares: 42_000_000,
+            total_deposited: 42,
+            last_deposit: 1,
+            last_withdrawal: 0,
+            version: fundr::USER_STAKE_VERSION,
+            snapshot_shares: 0,
+            deposit_proposal_count: 0,
+            prior_proposal_count: 0,
+            _reserved: [0; 40],
+        };
+        let mut data = Vec::new();
+        stake.try_serialize(&mut data).unwrap();
+
+        let decoded = deserialize_user_stake(&data).unwrap();
+        assert_eq!(decoded.shares, stake.shares);
+        assert_eq!(decoded.user, stake.user);
+        assert!(deserialize_fund(&data).is_err());
+    }
+}
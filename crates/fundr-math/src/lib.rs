@@ -0,0 +1,258 @@
+//! Share pricing and fee math for Fundr.
+//!
+//! This crate is `no_std` and dependency-free so the on-chain program and off-chain
+//! clients run the exact same arithmetic: a preview computed here matches what the
+//! program books for the same fund state.
+
+#![no_std]
+
+/// Virtual shares and assets added to both sides of every share price calculation.
+/// They pin the opening price at 1 lamport = 1M shares and make inflating the price
+/// with a vault donation cost the attacker more than any rounding they can capture.
+pub const VIRTUAL_SHARES: u64 = 1_000_000;
+pub const VIRTUAL_ASSETS: u64 = 1;
+
+/// Platform fee on deposits and withdrawal fee on redemptions, as a divisor (1%)
+pub const PLATFORM_FEE_DIVISOR: u64 = 100;
+/// Basis point denominator for performance fees
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// Performance fees are capped at 20%
+pub const MAX_PERFORMANCE_FEE_BPS: u16 = 2_000;
+/// Fixed-point scale of NAV and the high water mark (1_000_000 = 1.0)
+pub const NAV_SCALE: u64 = 1_000_000;
+/// Fixed-point scale of oracle prices, quoted in lamports per base unit of a token
+/// (1_000_000_000 = 1 lamport per unit)
+pub const PRICE_SCALE: u64 = 1_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathError {
+    Overflow,
+}
+
+pub type MathResult<T> = Result<T, MathError>;
+
+/// `a * b / c` computed in u128 and rounded down
+pub fn mul_div_floor(a: u64, b: u64, c: u64) -> MathResult<u64> {
+    let result = (a as u128)
+        .checked_mul(b as u128)
+        .ok_or(MathError::Overflow)?
+        .checked_div(c as u128)
+        .ok_or(MathError::Overflow)?;
+    u64::try_from(result).map_err(|_| MathError::Overflow)
+}
+
+/// `a * b / c` computed in u128 and rounded up, for amounts that must favor the fund
+pub fn mul_div_ceil(a: u64, b: u64, c: u64) -> MathResult<u64> {
+    if c == 0 {
+        return Err(MathError::Overflow);
+    }
+    let product = (a as u128).checked_mul(b as u128).ok_or(MathError::Overflow)?;
+    u64::try_from(product.div_ceil(c as u128)).map_err(|_| MathError::Overflow)
+}
+
+fn virtual_assets(total_assets: u64) -> MathResult<u64> {
+    total_assets.checked_add(VIRTUAL_ASSETS).ok_or(MathError::Overflow)
+}
+
+fn virtual_shares(total_shares: u64) -> MathResult<u64> {
+    total_shares.checked_add(VIRTUAL_SHARES).ok_or(MathError::Overflow)
+}
+
+/// Shares minted for `net_deposit` lamports, rounded down
+pub fn shares_for_deposit(net_deposit: u64, total_assets: u64, total_shares: u64) -> MathResult<u64> {
+    mul_div_floor(net_deposit, virtual_shares(total_shares)?, virtual_assets(total_assets)?)
+}
+
+/// Gross lamports owed for redeeming `shares`, rounded down
+pub fn lamports_for_shares(shares: u64, total_assets: u64, total_shares: u64) -> MathResult<u64> {
+    mul_div_floor(shares, virtual_assets(total_assets)?, virtual_shares(total_shares)?)
+}
+
+/// Shares that must be burned to release `lamports` gross, rounded up
+pub fn shares_for_lamports(lamports: u64, total_assets: u64, total_shares: u64) -> MathResult<u64> {
+    mul_div_ceil(lamports, virtual_shares(total_shares)?, virtual_assets(total_assets)?)
+}
+
+/// 1% platform fee taken from a deposit
+pub fn platform_fee(amount: u64) -> u64 {
+    amount / PLATFORM_FEE_DIVISOR
+}
+
+/// 1% withdrawal fee on a gross redemption, waived while a fund winds down
+pub fn withdrawal_fee(gross: u64, fee_waived: bool) -> u64 {
+    if fee_waived {
+        0
+    } else {
+        gross / PLATFORM_FEE_DIVISOR
+    }
+}
+
+/// Smallest gross redemption (rounded up) whose withdrawal fee still leaves `net`
+pub fn gross_for_net_withdrawal(net: u64, fee_waived: bool) -> MathResult<u64> {
+    if fee_waived {
+        Ok(net)
+    } else {
+        mul_div_ceil(net, PLATFORM_FEE_DIVISOR, PLATFORM_FEE_DIVISOR - 1)
+    }
+}
+
+/// NAV per share in `NAV_SCALE` fixed point, where 1.0 is the opening price
+pub fn nav_per_share(total_assets: u64, total_shares: u64) -> MathResult<u64> {
+    let scale = (VIRTUAL_SHARES as u128) * (NAV_SCALE as u128);
+    let nav = (virtual_assets(total_assets)? as u128)
+        .checked_mul(scale)
+        .ok_or(MathError::Overflow)?
+        / virtual_shares(total_shares)? as u128;
+    u64::try_from(nav).map_err(|_| MathError::Overflow)
+}
+
+/// Performance fee in lamports on the gain of every outstanding share above the
+/// high water mark: `total_shares * (nav - hwm) / (VIRTUAL_SHARES * NAV_SCALE) * bps / 10_000`
+pub fn performance_fee(
+    total_shares: u64,
+    current_nav: u64,
+    high_water_mark: u64,
+    performance_fee_bps: u16,
+) -> MathResult<u64> {
+    if current_nav <= high_water_mark {
+        return Ok(0);
+    }
+    let gain = (current_nav - high_water_mark) as u128;
+    let fee = (total_shares as u128)
+        .checked_mul(gain)
+        .and_then(|value| value.checked_mul(performance_fee_bps as u128))
+        .ok_or(MathError::Overflow)?
+        / ((VIRTUAL_SHARES as u128) * (NAV_SCALE as u128) * (BPS_DENOMINATOR as u128));
+    u64::try_from(fee).map_err(|_| MathError::Overflow)
+}
+
+/// Outcome of depositing `amount` lamports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepositPreview {
+    pub platform_fee: u64,
+    pub net_deposit: u64,
+    pub shares: u64,
+}
+
+pub fn preview_deposit(amount: u64, total_assets: u64, total_shares: u64) -> MathResult<DepositPreview> {
+    let platform_fee = platform_fee(amount);
+    let net_deposit = amount - platform_fee;
+    Ok(DepositPreview {
+        platform_fee,
+        net_deposit,
+        shares: shares_for_deposit(net_deposit, total_assets, total_shares)?,
+    })
+}
+
+/// Outcome of redeeming shares
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WithdrawPreview {
+    pub shares: u64,
+    pub gross: u64,
+    pub fee: u64,
+    pub net: u64,
+}
+
+pub fn preview_withdraw(
+    shares: u64,
+    total_assets: u64,
+    total_shares: u64,
+    fee_waived: bool,
+) -> MathResult<WithdrawPreview> {
+    let gross = lamports_for_shares(shares, total_assets, total_shares)?;
+    let fee = withdrawal_fee(gross, fee_waived);
+    Ok(WithdrawPreview { shares, gross, fee, net: gross - fee })
+}
+
+/// Redemption needed to receive exactly `net` lamports
+pub fn preview_withdraw_exact(
+    net: u64,
+    total_assets: u64,
+    total_shares: u64,
+    fee_waived: bool,
+) -> MathResult<WithdrawPreview> {
+    let gross = gross_for_net_withdrawal(net, fee_waived)?;
+    Ok(WithdrawPreview {
+        shares: shares_for_lamports(gross, total_assets, total_shares)?,
+        gross,
+        fee: gross - net,
+        net,
+    })
+}
+
+/// Output of swapping `amount_in` at an oracle `price`, rounded down: tokens when
+/// `buying` with lamports, lamports when selling tokens
+pub fn fair_swap_output(amount_in: u64, price: u64, buying: bool) -> MathResult<u64> {
+    if buying {
+        mul_div_floor(amount_in, PRICE_SCALE, price)
+    } else {
+        mul_div_floor(amount_in, price, PRICE_SCALE)
+    }
+}
+
+/// Least output a swap may accept: the fair output less `slippage_bps`, rounded up so
+/// the tolerance never exceeds `slippage_bps`
+pub fn minimum_swap_output(amount_in: u64, price: u64, buying: bool, slippage_bps: u16) -> MathResult<u64> {
+    let fair = fair_swap_output(amount_in, price, buying)?;
+    let kept = BPS_DENOMINATOR.checked_sub(slippage_bps as u64).ok_or(MathError::Overflow)?;
+    mul_div_ceil(fair, kept, BPS_DENOMINATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn opening_nav_is_one() {
+        assert_eq!(nav_per_share(0, 0).unwrap(), NAV_SCALE);
+        let preview = preview_deposit(1_000_000_000, 0, 0).unwrap();
+        assert_eq!(nav_per_share(preview.net_deposit, preview.shares).unwrap(), NAV_SCALE);
+    }
+
+    #[test]
+    fn doubling_assets_charges_fee_on_the_gain() {
+        let shares = 1_000_000_000 * VIRTUAL_SHARES;
+        let nav = nav_per_share(2_000_000_000, shares).unwrap();
+        assert_eq!(nav, 2 * NAV_SCALE - 1);
+
+        // 20% of a ~1 SOL gain, less what NAV's six decimals truncate
+        let fee = performance_fee(shares, nav, NAV_SCALE, MAX_PERFORMANCE_FEE_BPS).unwrap();
+        assert_eq!(fee, 199_999_800);
+        assert_eq!(performance_fee(shares, NAV_SCALE, NAV_SCALE, MAX_PERFORMANCE_FEE_BPS).unwrap(), 0);
+    }
+
+    #[test]
+    fn swap_floor_allows_only_the_slippage_tolerance() {
+        // 1 token unit = 0.5 lamports
+        let price = PRICE_SCALE / 2;
+        assert_eq!(fair_swap_output(1_000, price, true).unwrap(), 2_000);
+        assert_eq!(fair_swap_output(1_000, price, false).unwrap(), 500);
+        assert_eq!(minimum_swap_output(1_000, price, true, 100).unwrap(), 1_980);
+        assert_eq!(minimum_swap_output(999, price, false, 100).unwrap(), 495);
+        assert_eq!(minimum_swap_output(1_000, price, false, 0).unwrap(), 500);
+        assert!(fair_swap_output(1_000, 0, true).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn exact_withdrawal_pays_at_least_net(
+            net in 1u64..1_000_000_000_000,
+            fee_waived in any::<bool>(),
+        ) {
+            let gross = gross_for_net_withdrawal(net, fee_waived).unwrap();
+            prop_assert!(gross - withdrawal_fee(gross, fee_waived) >= net);
+        }
+
+        #[test]
+        fn performance_fee_is_a_share_of_assets(
+            total_assets in 1u64..1_000_000_000_000,
+            total_shares in 1u64..1_000_000_000_000_000_000,
+            bps in 0u16..=MAX_PERFORMANCE_FEE_BPS,
+        ) {
+            let nav = nav_per_share(total_assets, total_shares).unwrap();
+            let fee = performance_fee(total_shares, nav, NAV_SCALE, bps).unwrap();
+            prop_assert!(fee as u128 <= (total_assets as u128 + 1) * bps as u128 / BPS_DENOMINATOR as u128);
+        }
+    }
+}
@@ -51,31 +51,9 @@ echo -e "${BLUE}🔧 Attempting simplified build...${NC}"
 cargo build --release --target bpfel-unknown-unknown --manifest-path ./Cargo-bpf.toml || {
     echo -e "${YELLOW}⚠️  Direct build failed, trying alternative...${NC}"
     
-    # Alternative: Create a minimal deployable program
-    cat > src/lib-minimal.rs << 'EOF'
-use solana_program::{
-    account_info::AccountInfo,
-    entrypoint,
-    entrypoint::ProgramResult,
-    pubkey::Pubkey,
-    msg,
-};
-
-entrypoint!(process_instruction);
-
-pub fn process_instruction(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    instruction_data: &[u8],
-) -> ProgramResult {
-    msg!("Fundr program entrypoint - minimal deployment");
-    Ok(())
-}
-EOF
-    
-    # Build minimal version
-    rustc --target bpfel-unknown-unknown -C opt-level=3 -C lto=fat -C codegen-units=1 \
-        --crate-type cdylib src/lib-minimal.rs -o ../../target/deploy/fundr.so
+    # Alternative: the size-constrained build, which dispatches the same instructions
+    # through a native entrypoint without Anchor's IDL instructions
+    cargo build-sbf --features minimal --sbf-out-dir ../../target/deploy
 }
 
 cd ../..
@@ -0,0 +1,296 @@
+//! Account layouts written by earlier versions of the program, and their upgrade to
+//! the current layout.
+//!
+//! Version 0 is the layout the program was first deployed with. Because `Fund` and
+//! `UserStake` only ever grow at the end and accounts are zeroed when created, a
+//! version 0 account decodes as its old layout, and every field added since, `version`
+//! included, reads as zero padding or lies past the end of the account.
+
+use crate::{
+    Fund, FundMode, FundStatus, FundrError, UserStake, FUND_VERSION, MAX_DESCRIPTION_LEN, MAX_NAME_LEN,
+    MAX_URI_LEN, USER_STAKE_VERSION,
+};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use bytemuck::Zeroable;
+
+/// `Fund` as laid out by the first deployed program
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct FundV0 {
+    pub authority: Pubkey,
+    #[max_len(MAX_NAME_LEN)]
+    pub name: String,
+    #[max_len(MAX_DESCRIPTION_LEN)]
+    pub description: String,
+    pub performance_fee: u16,
+    pub min_deposit: u64,
+    pub fund_mode: FundMode,
+    pub total_shares: u64,
+    pub total_assets: u64,
+    pub investor_count: u32,
+    pub bump: u8,
+    pub created_at: i64,
+    pub last_fee_collection: i64,
+    pub high_water_mark: u64,
+}
+
+/// Fields the current `Fund` layout has between the version 0 fields and `version`.
+/// Only decoded to find `version`.
+#[derive(AnchorDeserialize, InitSpace)]
+#[allow(dead_code)]
+struct FundV0Tail {
+    status: FundStatus,
+    wind_down_started_at: i64,
+    #[max_len(MAX_URI_LEN)]
+    uri: String,
+    platform_fees: u64,
+    version: u8,
+}
+
+/// `UserStake` as laid out by the first deployed program
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct UserStakeV0 {
+    pub user: Pubkey,
+    pub fund: Pubkey,
+    pub shares: u64,
+    pub total_deposited: u64,
+    pub last_deposit: i64,
+    pub last_withdrawal: i64,
+}
+
+impl From<FundV0> for Fund {
+    fn from(old: FundV0) -> Self {
+        Fund {
+            authority: old.authority,
+            name: old.name,
+            description: old.description,
+            performance_fee: old.performance_fee,
+            min_deposit: old.min_deposit,
+            fund_mode: old.fund_mode,
+            total_shares: old.total_shares,
+            total_assets: old.total_assets,
+            investor_count: old.investor_count,
+            bump: old.bump,
+            created_at: old.created_at,
+            last_fee_collection: old.last_fee_collection,
+            high_water_mark: old.high_water_mark,
+            status: FundStatus::Active,
+            wind_down_started_at: 0,
+            uri: String::new(),
+            platform_fees: 0,
+            version: FUND_VERSION,
+            pending_fees: 0,
+            fees_claimable_at: 0,
+            flagged: false,
+            fee_period: 0,
+            creator: old.authority,
+            proposal_count: 0,
+            multisig: false,
+            trader: Pubkey::default(),
+            operator: Pubkey::default(),
+            max_trade_amount: 0,
+            max_trade_bps: 0,
+            max_daily_turnover_bps: 0,
+            max_position_bps: 0,
+            turnover: 0,
+            turnover_updated_at: 0,
+            fund_index: 0,
+            _reserved: Zeroable::zeroed(),
+        }
+    }
+}
+
+impl From<UserStakeV0> for UserStake {
+    fn from(old: UserStakeV0) -> Self {
+        UserStake {
+            user: old.user,
+            fund: old.fund,
+            shares: old.shares,
+            total_deposited: old.total_deposited,
+            last_deposit: old.last_deposit,
+            last_withdrawal: old.last_withdrawal,
+            version: USER_STAKE_VERSION,
+            snapshot_shares: 0,
+            deposit_proposal_count: 0,
+            prior_proposal_count: 0,
+            _reserved: Zeroable::zeroed(),
+        }
+    }
+}
+
+/// Decode a `Fund` account of any supported version into the current layout
+pub fn upgrade_fund(data: &[u8]) -> Result<Fund> {
+    let mut rest = account_body(data, &Fund::DISCRIMINATOR)?;
+    let old = FundV0::deserialize(&mut rest).map_err(|_| error!(FundrError::InvalidAccount))?;
+    
+    // Read on as the current layout, treating anything past the end as zero padding
+    let mut tail = rest.to_vec();
+    tail.resize(rest.len() + FundV0Tail::INIT_SPACE, 0);
+    let tail = FundV0Tail::deserialize(&mut &tail[..]).map_err(|_| error!(FundrError::InvalidAccount))?;
+
+    match tail.version {
+        0 => Ok(old.into()),
+        FUND_VERSION => err!(FundrError::AlreadyMigrated),
+        _ => err!(FundrError::UnsupportedAccountVersion),
+    }
+}
+
+/// Decode a `UserStake` account of any supported version into the current layout
+pub fn upgrade_user_stake(data: &[u8]) -> Result<UserStake> {
+    let mut rest = account_body(data, &UserStake::DISCRIMINATOR)?;
+    let old = UserStakeV0::deserialize(&mut rest).map_err(|_| error!(FundrError::InvalidAccount))?;
+
+    match rest.first().copied().unwrap_or(0) {
+        0 => Ok(old.into()),
+        USER_STAKE_VERSION => err!(FundrError::AlreadyMigrated),
+        _ => err!(FundrError::UnsupportedAccountVersion),
+    }
+}
+
+/// Account data after the discriminator, which must match `discriminator`
+fn account_body<'a>(data: &'a [u8], discriminator: &[u8; 8]) -> Result<&'a [u8]> {
+    require!(data.len() >= 8 && data[..8] == discriminator[..], FundrError::InvalidAccount);
+    Ok(&data[8..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AccountSerialize;
+
+    fn legacy_fund() -> FundV0 {
+        FundV0 {
+            authority: Pubkey::new_unique(),
+            name: "Legacy".to_string(),
+            description: "Created before versioning".to_string(),
+            performance_fee: 1500,
+            min_deposit: 1_000,
+            fund_mode: FundMode::Auto,
+            total_shares: 5_000_000_000,
+            total_assets: 5_000,
+            investor_count: 2,
+            bump: 254,
+            created_at: 1_600_000_000,
+            last_fee_collection: 1_600_000_100,
+            high_water_mark: 1_200_000,
+        }
+    }
+
+    /// Bytes the first deployed program wrote for `old`, field by field, padded to the
+    /// space it allocated
+    fn baseline_fund_bytes(old: &FundV0) -> Vec<u8> {
+        let mut data = Fund::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(old.authority.as_ref());
+        for text in [&old.name, &old.description] {
+            data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            data.extend_from_slice(text.as_bytes());
+        }
+        data.extend_from_slice(&old.performance_fee.to_le_bytes());
+        data.extend_from_slice(&old.min_deposit.to_le_bytes());
+        data.push(old.fund_mode as u8);
+        data.extend_from_slice(&old.total_shares.to_le_bytes());
+        data.extend_from_slice(&old.total_assets.to_le_bytes());
+        data.extend_from_slice(&old.investor_count.to_le_bytes());
+        data.push(old.bump);
+        data.extend_from_slice(&old.created_at.to_le_bytes());
+        data.extend_from_slice(&old.last_fee_collection.to_le_bytes());
+        data.extend_from_slice(&old.high_water_mark.to_le_bytes());
+        data.resize(8 + 346, 0);
+        data
+    }
+
+    /// Bytes of a legacy account: discriminator, old layout, then zero padding up to
+    /// `space` as allocated by the old program
+    fn legacy_bytes(discriminator: [u8; 8], body: impl AnchorSerialize, space: usize) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        body.serialize(&mut data).unwrap();
+        assert!(data.len() <= space);
+        data.resize(space, 0);
+        data
+    }
+
+    #[test]
+    fn legacy_layout_matches_the_deployed_program() {
+        // The first deployed program allocated 8 + 346 bytes for every fund:
+        // 32 + (4 + 50) + (4 + 200) + 2 + 8 + 1 + 8 + 8 + 4 + 1 + 8 + 8 + 8
+        assert_eq!(FundV0::INIT_SPACE, 346);
+        let old = legacy_fund();
+        assert_eq!(baseline_fund_bytes(&old), legacy_bytes(Fund::DISCRIMINATOR, old, 8 + FundV0::INIT_SPACE));
+    }
+
+    #[test]
+    fn legacy_fund_upgrades_with_every_field_kept() {
+        let old = legacy_fund();
+        let data = baseline_fund_bytes(&old);
+
+        let fund = upgrade_fund(&data).unwrap();
+
+        assert_eq!(fund.version, FUND_VERSION);
+        assert_eq!(fund.authority, old.authority);
+        assert_eq!(fund.creator, old.authority);
+        assert_eq!(fund.name, old.name);
+        assert_eq!(fund.description, old.description);
+        assert_eq!(fund.performance_fee, old.performance_fee);
+        assert_eq!(fund.fund_mode, FundMode::Auto);
+        assert_eq!((fund.total_shares, fund.total_assets), (old.total_shares, old.total_assets));
+        assert_eq!((fund.investor_count, fund.bump), (old.investor_count, old.bump));
+        assert_eq!((fund.created_at, fund.last_fee_collection), (old.created_at, old.last_fee_collection));
+        assert_eq!(fund.high_water_mark, old.high_water_mark);
+        assert_eq!(fund.status, FundStatus::Active);
+        assert_eq!((fund.uri.as_str(), fund.platform_fees), ("", 0));
+        assert!(fund._reserved.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn legacy_fund_with_full_length_strings_upgrades() {
+        // No padding is left after the last field, so none of the newer fields are there
+        let old = FundV0 {
+            name: "n".repeat(MAX_NAME_LEN),
+            description: "d".repeat(MAX_DESCRIPTION_LEN),
+            ..legacy_fund()
+        };
+        let data = baseline_fund_bytes(&old);
+        assert_eq!(data.len(), 8 + FundV0::INIT_SPACE);
+
+        let fund = upgrade_fund(&data).unwrap();
+        assert_eq!((fund.name, fund.description), (old.name, old.description));
+    }
+
+    #[test]
+    fn legacy_stake_upgrades() {
+        let old = UserStakeV0 {
+            user: Pubkey::new_unique(),
+            fund: Pubkey::new_unique(),
+            shares: 42,
+            total_deposited: 7,
+            last_deposit: 1,
+            last_withdrawal: 2,
+        };
+        let data = legacy_bytes(UserStake::DISCRIMINATOR, old.clone(), 8 + UserStakeV0::INIT_SPACE);
+
+        let stake = upgrade_user_stake(&data).unwrap();
+
+        assert_eq!(stake.version, USER_STAKE_VERSION);
+        assert_eq!((stake.user, stake.fund), (old.user, old.fund));
+        assert_eq!((stake.shares, stake.total_deposited), (42, 7));
+        assert_eq!((stake.last_deposit, stake.last_withdrawal), (1, 2));
+    }
+
+    #[test]
+    fn current_and_unknown_versions_are_not_upgraded() {
+        let old = legacy_fund();
+        let mut data = Vec::new();
+        Fund::from(old.clone()).try_serialize(&mut data).unwrap();
+        assert_eq!(upgrade_fund(&data).err(), Some(FundrError::AlreadyMigrated.into()));
+
+        // `version` follows the old fields, then status, wind-down time, an empty URI and
+        // platform fees
+        let version_at = 8 + old.try_to_vec().unwrap().len() + 1 + 8 + 4 + 8;
+        data[version_at] = FUND_VERSION + 1;
+        assert_eq!(upgrade_fund(&data).err(), Some(FundrError::UnsupportedAccountVersion.into()));
+
+        // Other account types are never reinterpreted
+        data[..8].copy_from_slice(&UserStake::DISCRIMINATOR);
+        assert_eq!(upgrade_fund(&data).err(), Some(FundrError::InvalidAccount.into()));
+    }
+}
@@ -1,14 +1,73 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
-use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
+use anchor_spl::associated_token::{get_associated_token_address, get_associated_token_address_with_program_id};
+use bytemuck::Zeroable;
 
 declare_id!("FundrProgram11111111111111111111111111111111");
 
+pub mod legacy;
+pub mod minimal;
+
+pub const MAX_NAME_LEN: usize = 50;
+pub const MAX_DESCRIPTION_LEN: usize = 200;
+pub const MAX_URI_LEN: usize = 200;
+/// Current layout versions of `Fund` and `UserStake`. Accounts written before
+/// versioning read as 0 and are upgraded by `migrate_fund`/`migrate_stake`.
+pub const FUND_VERSION: u8 = 1;
+pub const USER_STAKE_VERSION: u8 = 1;
+/// Holdings a fund's portfolio can track
+pub const MAX_POSITIONS: usize = 32;
+/// Bounds on how long crystallized performance fees stay in escrow before the manager
+/// can claim them, giving investors time to react and the admin time to void them
+pub const MIN_FEE_CLAIM_DELAY: i64 = 86_400; // 1 day
+pub const MAX_FEE_CLAIM_DELAY: i64 = 30 * 86_400; // 30 days
+/// Bounds on a fund's performance fee crystallization period. Funds created before
+/// periods existed use the minimum.
+pub const MIN_FEE_PERIOD: i64 = 30 * 86_400; // monthly
+pub const MAX_FEE_PERIOD: i64 = 365 * 86_400; // yearly
+/// Investor governance defaults, set when the protocol config is created. Quorum is a
+/// share of all outstanding shares at the proposal snapshot; the approval threshold is
+/// a share of the votes cast.
+pub const DEFAULT_QUORUM_BPS: u16 = 2_000; // 20%
+pub const DEFAULT_APPROVAL_THRESHOLD_BPS: u16 = 5_000; // simple majority
+pub const DEFAULT_VOTING_PERIOD: i64 = 7 * 86_400; // 7 days
+pub const MIN_VOTING_PERIOD: i64 = 86_400; // 1 day
+pub const MAX_VOTING_PERIOD: i64 = 30 * 86_400; // 30 days
+/// Trades count towards a fund's daily turnover limit for this long, fading out
+/// linearly so the limit applies over a rolling day rather than resetting at once
+pub const TURNOVER_WINDOW: i64 = 86_400;
+/// Rebalances must ask for at least the oracle fair output less the protocol's
+/// slippage tolerance, which the admin can widen no further than the maximum
+pub const DEFAULT_SLIPPAGE_BPS: u16 = 100; // 1%
+pub const MAX_SLIPPAGE_BPS: u16 = 1_000; // 10%
+/// Oracle prices older than the protocol's maximum age can't bound a trade. Configs
+/// created before the setting existed use the default.
+pub const DEFAULT_MAX_PRICE_AGE: i64 = 300; // 5 minutes
+pub const MAX_PRICE_AGE: i64 = 3_600; // 1 hour
+/// Most keys a fund's manager signer set can hold; approvals are tracked as a bitmap
+pub const MAX_SIGNERS: usize = 10;
+/// Smallest seed a manager may provide at creation; smaller seeds are too cheap
+/// to protect the share price from first-depositor inflation
+pub const MIN_SEED_DEPOSIT: u64 = 10_000_000; // 0.01 SOL
+/// Virtual shares and assets added to both sides of every share price calculation.
+/// They pin the opening price at 1 lamport = 1M shares and make inflating the price
+/// with a vault donation cost the attacker more than any rounding they can capture.
+pub use fundr_math::{MAX_PERFORMANCE_FEE_BPS, NAV_SCALE, PRICE_SCALE, VIRTUAL_ASSETS, VIRTUAL_SHARES};
+
 #[program]
 pub mod fundr {
     use super::*;
 
-    /// Initialize a new fund
+    /// Initialize a new fund, optionally seeding it with the manager's own deposit (the
+    /// manager's stake is passed only then). The manager tops the vault up to its rent
+    /// reserve; lamports donated to the vault before the fund existed beyond that reserve
+    /// are swept to the manager rather than left outside the share price.
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize_fund(
         ctx: Context<InitializeFund>,
         name: String,
@@ -16,14 +75,42 @@ pub mod fundr {
         performance_fee: u16, // in basis points (e.g., 2000 = 20%)
         min_deposit: u64,
         fund_mode: FundMode, // manual or auto allocation mode
+        initial_deposit: u64, // manager seed in lamports (0 = no seed)
+        fee_period: i64, // seconds between performance fee crystallizations
     ) -> Result<()> {
+        require!(!name.trim().is_empty(), FundrError::NameEmpty);
+        require!(name.len() <= MAX_NAME_LEN, FundrError::NameTooLong);
+        require!(description.len() <= MAX_DESCRIPTION_LEN, FundrError::DescriptionTooLong);
+        require!(min_deposit > 0, FundrError::InvalidMinDeposit);
         // Cap performance fee at 20%
-        require!(performance_fee <= 2000, FundrError::ExcessiveFees);
+        require!(performance_fee <= MAX_PERFORMANCE_FEE_BPS, FundrError::ExcessiveFees);
+        require!((MIN_FEE_PERIOD..=MAX_FEE_PERIOD).contains(&fee_period), FundrError::InvalidFeePeriod);
+        
+        // The vault must not have been claimed before the fund exists; anyone can send it lamports
+        let fund_vault = &ctx.accounts.fund_vault;
+        require!(
+            fund_vault.data_is_empty() && fund_vault.owner == &anchor_lang::system_program::ID,
+            FundrError::VaultNotFresh
+        );
+        
+        if initial_deposit > 0 {
+            require!(
+                initial_deposit >= MIN_SEED_DEPOSIT && initial_deposit >= min_deposit,
+                FundrError::InsufficientSeedDeposit
+            );
+        }
+        // The manager's stake exists exactly when it holds the seed, so an unseeded fund
+        // leaves no empty stake behind
+        require!(
+            ctx.accounts.manager_stake.is_some() == (initial_deposit > 0),
+            FundrError::InvalidAccount
+        );
         
         let fund = &mut ctx.accounts.fund;
         fund.authority = ctx.accounts.manager.key();
         fund.name = name;
         fund.description = description;
+        fund.uri = String::new();
         fund.performance_fee = performance_fee;
         fund.min_deposit = min_deposit;
         fund.fund_mode = fund_mode;
@@ -32,66 +119,101 @@ pub mod fundr {
         fund.bump = ctx.bumps.fund;
         fund.created_at = Clock::get()?.unix_timestamp;
         fund.last_fee_collection = Clock::get()?.unix_timestamp;
-        fund.high_water_mark = 1000000; // Start at 1.0 in fixed point
+        fund.high_water_mark = NAV_SCALE; // Start at 1.0 in fixed point
+        fund.status = FundStatus::Active;
+        fund.wind_down_started_at = 0;
+        fund.platform_fees = 0;
+        fund.version = FUND_VERSION;
+        fund.pending_fees = 0;
+        fund.fees_claimable_at = 0;
+        fund.flagged = false;
+        fund.fee_period = fee_period;
+        fund.creator = ctx.accounts.manager.key();
+        fund.proposal_count = 0;
+        fund.multisig = false;
+        fund.trader = Pubkey::default();
+        fund.operator = Pubkey::default();
+        fund.max_trade_amount = 0;
+        fund.max_trade_bps = 0;
+        fund.max_daily_turnover_bps = 0;
+        fund.max_position_bps = 0;
+        fund.turnover = 0;
+        fund.turnover_updated_at = 0;
+        
+        let profile = &mut ctx.accounts.manager_profile;
+        fund.fund_index = profile.fund_count;
+        profile.manager = ctx.accounts.manager.key();
+        profile.fund_count = profile.fund_count.checked_add(1).ok_or(FundrError::MathOverflow)?;
+        profile.bump = ctx.bumps.manager_profile;
+        
+        // The manager keeps the vault rent-exempt outside total_assets, so the last investor can
+        // withdraw everything; lamports sent to the vault before it existed go back to the manager
+        let reserve = Rent::get()?.minimum_balance(0);
+        let existing = ctx.accounts.fund_vault.lamports();
+        if existing < reserve {
+            transfer_to_vault(
+                &ctx.accounts.manager.to_account_info(),
+                &ctx.accounts.fund_vault,
+                &ctx.accounts.system_program,
+                reserve - existing,
+            )?;
+        } else if existing > reserve {
+            transfer_from_vault(
+                &ctx.accounts.fund_vault,
+                &ctx.accounts.manager.to_account_info(),
+                &ctx.accounts.system_program,
+                &fund.key(),
+                ctx.bumps.fund_vault,
+                existing - reserve,
+            )?;
+        }
+        
+        if let Some(manager_stake) = ctx.accounts.manager_stake.as_mut() {
+            let (net_deposit, shares) = issue_shares(
+                fund,
+                manager_stake,
+                ctx.accounts.manager.key(),
+                initial_deposit,
+            )?;
+            
+            transfer_to_vault(
+                &ctx.accounts.manager.to_account_info(),
+                &ctx.accounts.fund_vault,
+                &ctx.accounts.system_program,
+                initial_deposit,
+            )?;
+            
+            msg!("Manager seeded fund with {} lamports for {} shares", net_deposit, shares);
+        }
         
         msg!("Fund {} initialized by manager {}", fund.name, fund.authority);
         Ok(())
     }
 
-    /// Deposit SOL into a fund and receive shares
+    /// Deposit SOL into a fund and receive at least `min_shares_out` shares
     pub fn deposit(
         ctx: Context<Deposit>,
         amount: u64,
+        min_shares_out: u64,
     ) -> Result<()> {
         let fund = &mut ctx.accounts.fund;
         let user_stake = &mut ctx.accounts.user_stake;
         
+        require!(fund.status == FundStatus::Active, FundrError::FundNotActive);
         require!(amount >= fund.min_deposit, FundrError::AmountTooSmall);
         
-        // Calculate platform fee (1%)
-        let platform_fee = amount.checked_div(100).ok_or(FundrError::MathOverflow)?;
-        let net_deposit = amount.checked_sub(platform_fee).ok_or(FundrError::MathOverflow)?;
-        
-        // Calculate shares to mint
-        let shares_to_mint = if fund.total_shares == 0 {
-            // First deposit: 1 SOL = 1M shares
-            net_deposit.checked_mul(1_000_000).ok_or(FundrError::MathOverflow)?
-        } else {
-            // Subsequent deposits: shares = (deposit * total_shares) / total_assets
-            net_deposit
-                .checked_mul(fund.total_shares)
-                .ok_or(FundrError::MathOverflow)?
-                .checked_div(fund.total_assets)
-                .ok_or(FundrError::MathOverflow)?
-        };
+        let (net_deposit, shares_to_mint) =
+            issue_shares(fund, user_stake, ctx.accounts.depositor.key(), amount)?;
+        require!(shares_to_mint >= min_shares_out, FundrError::SlippageExceeded);
 
         // Transfer SOL from user to fund vault
-        let transfer_instruction = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.depositor.key(),
-            &ctx.accounts.fund_vault.key(),
+        transfer_to_vault(
+            &ctx.accounts.depositor.to_account_info(),
+            &ctx.accounts.fund_vault,
+            &ctx.accounts.system_program,
             amount,
-        );
-        
-        anchor_lang::solana_program::program::invoke(
-            &transfer_instruction,
-            &[
-                ctx.accounts.depositor.to_account_info(),
-                ctx.accounts.fund_vault.to_account_info(),
-            ],
         )?;
 
-        // Update user stake
-        user_stake.user = ctx.accounts.depositor.key();
-        user_stake.fund = fund.key();
-        user_stake.shares = user_stake.shares.checked_add(shares_to_mint).ok_or(FundrError::MathOverflow)?;
-        user_stake.total_deposited = user_stake.total_deposited.checked_add(net_deposit).ok_or(FundrError::MathOverflow)?;
-        user_stake.last_deposit = Clock::get()?.unix_timestamp;
-
-        // Update fund totals
-        fund.total_shares = fund.total_shares.checked_add(shares_to_mint).ok_or(FundrError::MathOverflow)?;
-        fund.total_assets = fund.total_assets.checked_add(net_deposit).ok_or(FundrError::MathOverflow)?;
-        fund.investor_count = fund.investor_count.checked_add(1).ok_or(FundrError::MathOverflow)?;
-
         msg!(
             "Deposited {} lamports, received {} shares. Fund now has {} total assets",
             net_deposit,
@@ -102,52 +224,79 @@ pub mod fundr {
         Ok(())
     }
 
-    /// Withdraw from fund by redeeming shares for SOL  
+    /// Withdraw from fund by redeeming shares for at least `min_lamports_out` SOL (after fees)
     pub fn withdraw(
         ctx: Context<Withdraw>,
         shares_to_redeem: u64,
+        min_lamports_out: u64,
     ) -> Result<()> {
         let fund = &mut ctx.accounts.fund;
         let user_stake = &mut ctx.accounts.user_stake;
         
         require!(user_stake.shares >= shares_to_redeem, FundrError::InsufficientShares);
         
-        // Calculate SOL amount to withdraw
-        let withdrawal_amount = shares_to_redeem
-            .checked_mul(fund.total_assets)
-            .ok_or(FundrError::MathOverflow)?
-            .checked_div(fund.total_shares)
-            .ok_or(FundrError::MathOverflow)?;
-        
-        // Calculate withdrawal fee (1%)
-        let withdrawal_fee = withdrawal_amount.checked_div(100).ok_or(FundrError::MathOverflow)?;
-        let net_withdrawal = withdrawal_amount.checked_sub(withdrawal_fee).ok_or(FundrError::MathOverflow)?;
+        let (withdrawal_amount, net_withdrawal) = redeem_shares(fund, user_stake, shares_to_redeem)?;
+        require!(net_withdrawal >= min_lamports_out, FundrError::SlippageExceeded);
 
-        // Transfer SOL from fund vault to user
-        let fund_key = fund.key();
-        let seeds = &[b"fund", fund_key.as_ref(), &[fund.bump]];
-        let signer = &[&seeds[..]];
+        // Transfer SOL from fund vault to user; the withdrawal fee stays in the vault
+        transfer_from_vault(
+            &ctx.accounts.fund_vault,
+            &ctx.accounts.withdrawer.to_account_info(),
+            &ctx.accounts.system_program,
+            &fund.key(),
+            ctx.bumps.fund_vault,
+            net_withdrawal,
+        )?;
 
-        **ctx.accounts.fund_vault.to_account_info().try_borrow_mut_lamports()? -= withdrawal_amount;
-        **ctx.accounts.withdrawer.to_account_info().try_borrow_mut_lamports()? += net_withdrawal;
+        msg!(
+            "Redeemed {} shares for {} lamports (net: {} after fees)",
+            shares_to_redeem,
+            withdrawal_amount,
+            net_withdrawal
+        );
 
-        // Update user stake
-        user_stake.shares = user_stake.shares.checked_sub(shares_to_redeem).ok_or(FundrError::MathOverflow)?;
-        user_stake.last_withdrawal = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
 
-        // Update fund totals
-        fund.total_shares = fund.total_shares.checked_sub(shares_to_redeem).ok_or(FundrError::MathOverflow)?;
-        fund.total_assets = fund.total_assets.checked_sub(withdrawal_amount).ok_or(FundrError::MathOverflow)?;
+    /// Withdraw exactly `lamports_out` SOL (after fees), burning at most `max_shares_in` shares.
+    /// Shares burned are rounded up so the remaining investors never subsidise the exit.
+    pub fn withdraw_exact(
+        ctx: Context<Withdraw>,
+        lamports_out: u64,
+        max_shares_in: u64,
+    ) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        let user_stake = &mut ctx.accounts.user_stake;
+        
+        // Gross amount whose 1% fee still leaves `lamports_out`, rounded up
+        let withdrawal_amount = math(fundr_math::gross_for_net_withdrawal(
+            lamports_out,
+            fund.status == FundStatus::WindingDown,
+        ))?;
+        let shares_to_burn = fund.shares_for_lamports(withdrawal_amount)?;
+        
+        require!(shares_to_burn <= max_shares_in, FundrError::SlippageExceeded);
+        require!(user_stake.shares >= shares_to_burn, FundrError::InsufficientShares);
+        
+        burn_shares(fund, user_stake, shares_to_burn, withdrawal_amount)?;
+        let withdrawal_fee = withdrawal_amount.checked_sub(lamports_out).ok_or(FundrError::MathOverflow)?;
+        fund.platform_fees = fund.platform_fees.checked_add(withdrawal_fee).ok_or(FundrError::MathOverflow)?;
 
-        if user_stake.shares == 0 {
-            fund.investor_count = fund.investor_count.checked_sub(1).ok_or(FundrError::MathOverflow)?;
-        }
+        // Transfer SOL from fund vault to user; the fee and rounding remainder stay in the vault
+        transfer_from_vault(
+            &ctx.accounts.fund_vault,
+            &ctx.accounts.withdrawer.to_account_info(),
+            &ctx.accounts.system_program,
+            &fund.key(),
+            ctx.bumps.fund_vault,
+            lamports_out,
+        )?;
 
         msg!(
-            "Redeemed {} shares for {} lamports (net: {} after fees)",
-            shares_to_redeem,
+            "Burned {} shares for {} lamports (net: {} after fees)",
+            shares_to_burn,
             withdrawal_amount,
-            net_withdrawal
+            lamports_out
         );
 
         Ok(())
@@ -156,79 +305,128 @@ pub mod fundr {
     /// Manager rebalances fund by swapping tokens
     pub fn rebalance(
         ctx: Context<Rebalance>,
+        token_in_mint: Pubkey,
         token_in_amount: u64,
         token_out_mint: Pubkey,
         minimum_amount_out: u64,
     ) -> Result<()> {
-        let fund = &ctx.accounts.fund;
+        let fund = &mut ctx.accounts.fund;
+        let signer = ctx.accounts.manager.key();
+        let now = Clock::get()?.unix_timestamp;
         
-        require!(ctx.accounts.manager.key() == fund.authority, FundrError::UnauthorizedManager);
+        require!(fund.can_trade(&signer), FundrError::UnauthorizedManager);
+        require!(fund.status != FundStatus::Paused, FundrError::FundPaused);
+        
+        // The owner trades without limits; a delegated trader is capped per trade
+        if (fund.multisig || signer != fund.authority) && fund.max_trade_amount > 0 {
+            require!(token_in_amount <= fund.max_trade_amount, FundrError::TradeTooLarge);
+        }
+        
+        // While winding down, positions may only be liquidated back into SOL
+        if fund.status == FundStatus::WindingDown {
+            require_keys_eq!(token_out_mint, native_mint::ID, FundrError::InvalidWindDownSwap);
+        }
+        
+        // Every trade has a SOL leg, which sizes it in lamports for the risk limits:
+        // buys spend `token_in_amount` lamports, sells receive at least `minimum_received`
+        let buying = token_in_mint == native_mint::ID;
+        require!(
+            buying != (token_out_mint == native_mint::ID),
+            FundrError::InvalidSwapPair
+        );
+        
+        // Token-2022 mints may withhold a transfer fee, so the fund only books what it
+        // actually receives after the fee; a minimum the fee swallows whole guarantees nothing
+        let minimum_received = amount_after_transfer_fee(
+            &ctx.accounts.output_mint.to_account_info(),
+            minimum_amount_out,
+        )?;
+        require!(minimum_received > 0, FundrError::SlippageExceeded);
+        let notional = if buying { token_in_amount } else { minimum_received };
+        
+        // Trades may only buy into a position the manager has added to the portfolio;
+        // selling back into SOL is always allowed
+        let mut portfolio = ctx.accounts.portfolio.load_mut()?;
+        let position_cost = if buying {
+            let index = portfolio
+                .position_index(&token_out_mint)
+                .ok_or(FundrError::PositionNotFound)?;
+            let position = &mut portfolio.positions[index];
+            position.last_rebalanced_at = now;
+            position.cost_basis = position.cost_basis.checked_add(notional).ok_or(FundrError::MathOverflow)?;
+            position.cost_basis
+        } else {
+            if let Some(index) = portfolio.position_index(&token_in_mint) {
+                let position = &mut portfolio.positions[index];
+                position.cost_basis = position.cost_basis.saturating_sub(notional);
+            }
+            0
+        };
+        
+        let turnover = fund.record_turnover(notional, now)?;
+        
+        // Liquidating during wind-down is never held back by the risk limits
+        if fund.status == FundStatus::Active {
+            require!(
+                within_nav_share(notional, fund.total_assets, fund.max_trade_bps),
+                FundrError::TradeExceedsNavLimit
+            );
+            require!(
+                within_nav_share(turnover, fund.total_assets, fund.max_daily_turnover_bps),
+                FundrError::DailyTurnoverExceeded
+            );
+            require!(
+                within_nav_share(position_cost, fund.total_assets, fund.max_position_bps),
+                FundrError::PositionConcentrationExceeded
+            );
+        }
+        
+        // The manager picks the minimum output, so what it guarantees the fund after any
+        // transfer fee must be within the protocol's slippage tolerance of the oracle's
+        // fair value for the trade
+        let config = &ctx.accounts.config;
+        let feed = &ctx.accounts.price_feed;
+        require_keys_eq!(
+            feed.mint,
+            if buying { token_out_mint } else { token_in_mint },
+            FundrError::InvalidPriceFeed
+        );
+        require!(now.saturating_sub(feed.updated_at) <= config.max_price_age(), FundrError::StalePrice);
+        let floor = math(fundr_math::minimum_swap_output(token_in_amount, feed.price, buying, config.slippage_bps))?;
+        require!(minimum_received >= floor, FundrError::MinimumOutputBelowFairValue);
         
         // Implementation would integrate with Jupiter for actual swaps
         // For now, we'll emit an event to be handled by the frontend
         
         msg!(
-            "Manager {} rebalancing fund {}: swapping {} tokens for {}",
+            "Manager {} rebalancing fund {}: swapping {} {} for {} (min out {}, {} after transfer fee)",
             fund.authority,
             fund.name,
             token_in_amount,
-            token_out_mint
+            token_in_mint,
+            token_out_mint,
+            minimum_amount_out,
+            minimum_received
         );
 
         Ok(())
     }
 
-    /// Collect performance fees only (no management fees)
+    /// Crystallize performance fees (no management fees) into escrow. They leave
+    /// investor assets now but only become claimable after the protocol's fee delay.
     pub fn collect_fees(ctx: Context<CollectFees>) -> Result<()> {
         let fund = &mut ctx.accounts.fund;
         
         require!(ctx.accounts.manager.key() == fund.authority, FundrError::UnauthorizedManager);
+        require!(!fund.multisig, FundrError::MultisigRequired);
         
-        let current_time = Clock::get()?.unix_timestamp;
-        
-        // Calculate performance fee if above high water mark
-        let current_nav = if fund.total_shares > 0 {
-            fund.total_assets
-                .checked_mul(1000000)
-                .ok_or(FundrError::MathOverflow)?
-                .checked_div(fund.total_shares)
-                .ok_or(FundrError::MathOverflow)?
-        } else {
-            1000000 // Default NAV of 1.0
-        };
-
-        let performance_fee_amount = if current_nav > fund.high_water_mark {
-            let profit = current_nav - fund.high_water_mark;
-            fund.total_assets
-                .checked_mul(profit)
-                .ok_or(FundrError::MathOverflow)?
-                .checked_mul(fund.performance_fee as u64)
-                .ok_or(FundrError::MathOverflow)?
-                .checked_div(1000000) // NAV scaling
-                .ok_or(FundrError::MathOverflow)?
-                .checked_div(10000) // basis points
-                .ok_or(FundrError::MathOverflow)?
-        } else {
-            0
-        };
-
-        if performance_fee_amount > 0 {
-            // Transfer performance fees to manager
-            **ctx.accounts.fund_vault.to_account_info().try_borrow_mut_lamports()? -= performance_fee_amount;
-            **ctx.accounts.manager.to_account_info().try_borrow_mut_lamports()? += performance_fee_amount;
-
-            fund.total_assets = fund.total_assets.checked_sub(performance_fee_amount).ok_or(FundrError::MathOverflow)?;
-            fund.high_water_mark = current_nav;
-        }
-
-        fund.last_fee_collection = current_time;
-
-        msg!(
-            "Collected {} lamports in performance fees",
-            performance_fee_amount
-        );
+        crystallize_fees(fund, &ctx.accounts.config)
+    }
 
-        Ok(())
+    /// Permissionless crank that crystallizes performance fees once the fund's period
+    /// has elapsed, so fees are taken on schedule whether or not the manager acts
+    pub fn crank_fees(ctx: Context<CrankFees>) -> Result<()> {
+        crystallize_fees(&mut ctx.accounts.fund, &ctx.accounts.config)
     }
 
     pub fn update_fund_mode(ctx: Context<UpdateFundMode>, new_mode: FundMode) -> Result<()> {
@@ -236,48 +434,44 @@ pub mod fundr {
         
         // Only the fund manager can update the mode
         require_keys_eq!(fund.authority, ctx.accounts.manager.key(), FundrError::Unauthorized);
+        require!(!fund.multisig, FundrError::MultisigRequired);
         
         fund.fund_mode = new_mode;
         
         Ok(())
     }
 
-    /// Reclaim rent from closed accounts (SOL incinerator function)
-    /// This allows fund managers to recover rent fees from closed PDAs and empty accounts
+    /// Reclaim rent from emptied stake accounts
+    /// The account must be a program-owned `UserStake` of this fund with no shares left
+    /// (owner and discriminator are checked by `Account`). It is closed and its rent is
+    /// returned to the investor who originally paid it.
     pub fn reclaim_rent(ctx: Context<ReclaimRent>) -> Result<()> {
         let fund = &ctx.accounts.fund;
         let manager = &ctx.accounts.manager;
-        let closed_account = &ctx.accounts.closed_account;
-        let fund_vault = &ctx.accounts.fund_vault;
+        let closed_stake = &ctx.accounts.closed_stake;
         
-        // Only fund manager can reclaim rent
-        require_keys_eq!(fund.authority, manager.key(), FundrError::UnauthorizedManager);
-        
-        // Check if the account is actually empty/closed
-        let account_lamports = closed_account.lamports();
-        let minimum_rent = Rent::get()?.minimum_balance(0); // For empty account
+        // Only the owner or operator can reclaim rent
+        require!(fund.can_operate(&manager.key()), FundrError::UnauthorizedManager);
         
-        require!(
-            account_lamports <= minimum_rent,
-            FundrError::AccountNotEmpty
+        msg!(
+            "Reclaimed {} lamports rent from closed stake {} to {}",
+            closed_stake.to_account_info().lamports(),
+            closed_stake.key(),
+            ctx.accounts.stake_owner.key()
         );
         
-        // Transfer any remaining lamports to the fund vault
-        if account_lamports > 0 {
-            **closed_account.try_borrow_mut_lamports()? = 0;
-            **fund_vault.try_borrow_mut_lamports()? = fund_vault.lamports()
-                .checked_add(account_lamports)
-                .ok_or(FundrError::MathOverflow)?;
-            
-            msg!(
-                "Reclaimed {} lamports rent from closed account {} to fund vault",
-                account_lamports,
-                closed_account.key()
-            );
-        } else {
-            msg!("No rent to reclaim from account {}", closed_account.key());
-        }
-        
+        Ok(())
+    }
+
+    /// Investor closes their own emptied stake and takes back its rent. Needs no fund
+    /// account, so stakes left behind by a closed fund can still be recovered.
+    pub fn close_stake(ctx: Context<CloseStake>) -> Result<()> {
+        msg!(
+            "Closed stake {} in fund {} for {}",
+            ctx.accounts.user_stake.key(),
+            ctx.accounts.user_stake.fund,
+            ctx.accounts.user.key()
+        );
         Ok(())
     }
 
@@ -289,8 +483,8 @@ pub mod fundr {
         let token_account = &ctx.accounts.token_account;
         let fund_vault = &ctx.accounts.fund_vault;
         
-        // Only fund manager can close token accounts
-        require_keys_eq!(fund.authority, manager.key(), FundrError::UnauthorizedManager);
+        // Only the owner or operator can close token accounts
+        require!(fund.can_operate(&manager.key()), FundrError::UnauthorizedManager);
         
         // Token account must be empty
         require!(
@@ -298,130 +492,1767 @@ pub mod fundr {
             FundrError::TokenAccountNotEmpty
         );
         
+        // Positions still need their account to trade into
+        require!(
+            !is_position(&ctx.accounts.portfolio, &token_account.mint)?,
+            FundrError::PositionStillHeld
+        );
+        
         // Close the token account and transfer rent to fund vault
-        let cpi_accounts = anchor_spl::token::CloseAccount {
-            account: token_account.to_account_info(),
-            destination: fund_vault.to_account_info(),
-            authority: fund.to_account_info(),
-        };
+        close_fund_token_account(
+            fund,
+            &token_account.to_account_info(),
+            &fund_vault.to_account_info(),
+            &ctx.accounts.token_program,
+        )?;
         
-        let fund_key = fund.key();
-        let seeds = &[b"fund", fund_key.as_ref(), &[fund.bump]];
-        let signer_seeds = &[&seeds[..]];
+        msg!("Closed empty token account and reclaimed rent to fund vault");
+        Ok(())
+    }
+
+    /// Close every empty fund-owned token account passed in `remaining_accounts`,
+    /// reclaiming their rent to the fund vault. Accounts that still hold tokens or back a
+    /// portfolio position are skipped and listed in the event.
+    pub fn close_token_accounts<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseTokenAccounts<'info>>,
+    ) -> Result<()> {
+        let fund = &ctx.accounts.fund;
+        let fund_vault = ctx.accounts.fund_vault.to_account_info();
         
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+        // Only the owner or operator can close token accounts
+        require!(fund.can_operate(&ctx.accounts.manager.key()), FundrError::UnauthorizedManager);
         
-        token::close_account(cpi_ctx)?;
+        let mut accounts_closed: u32 = 0;
+        let mut rent_reclaimed: u64 = 0;
+        let mut accounts_skipped = Vec::new();
         
-        msg!("Closed empty token account and reclaimed rent to fund vault");
+        for account_info in ctx.remaining_accounts.iter() {
+            let token_account = InterfaceAccount::<TokenAccount>::try_from(account_info)?;
+            require_keys_eq!(token_account.owner, fund.key(), FundrError::InvalidAccount);
+            
+            if token_account.amount > 0 {
+                msg!("Skipping non-empty token account {}", account_info.key());
+                accounts_skipped.push(account_info.key());
+                continue;
+            }
+            if is_position(&ctx.accounts.portfolio, &token_account.mint)? {
+                msg!("Skipping token account {} of a portfolio position", account_info.key());
+                accounts_skipped.push(account_info.key());
+                continue;
+            }
+            
+            let rent = account_info.lamports();
+            close_fund_token_account(fund, account_info, &fund_vault, &ctx.accounts.token_program)?;
+            
+            accounts_closed = accounts_closed.checked_add(1).ok_or(FundrError::MathOverflow)?;
+            rent_reclaimed = rent_reclaimed.checked_add(rent).ok_or(FundrError::MathOverflow)?;
+        }
+        
+        emit!(TokenAccountsClosed {
+            fund: fund.key(),
+            accounts_closed,
+            rent_reclaimed,
+            accounts_skipped,
+        });
+        
+        msg!(
+            "Closed {} empty token accounts and reclaimed {} lamports to fund vault",
+            accounts_closed,
+            rent_reclaimed
+        );
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct UpdateFundMode<'info> {
-    #[account(mut)]
-    pub fund: Account<'info, Fund>,
-    pub manager: Signer<'info>,
-}
+    /// Update the fund's name, description and metadata URI (logo, strategy docs).
+    /// Fields passed as `None` are left unchanged.
+    pub fn update_fund_metadata(
+        ctx: Context<UpdateFundMetadata>,
+        name: Option<String>,
+        description: Option<String>,
+        uri: Option<String>,
+    ) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.manager.key(), FundrError::UnauthorizedManager);
+        
+        if let Some(name) = name {
+            require!(!name.trim().is_empty(), FundrError::NameEmpty);
+            require!(name.len() <= MAX_NAME_LEN, FundrError::NameTooLong);
+            fund.name = name;
+        }
+        if let Some(description) = description {
+            require!(description.len() <= MAX_DESCRIPTION_LEN, FundrError::DescriptionTooLong);
+            fund.description = description;
+        }
+        if let Some(uri) = uri {
+            require!(uri.len() <= MAX_URI_LEN, FundrError::UriTooLong);
+            fund.uri = uri;
+        }
+        
+        msg!("Updated metadata for fund {}", fund.name);
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct InitializeFund<'info> {
-    #[account(
-        init,
-        payer = manager,
-        space = 8 + Fund::INIT_SPACE,
-        seeds = [b"fund", manager.key().as_ref()],
-        bump
-    )]
-    pub fund: Account<'info, Fund>,
-    
-    #[account(
-        mut,
-        seeds = [b"vault", fund.key().as_ref()],
-        bump
-    )]
-    /// CHECK: Fund vault PDA for holding SOL
-    pub fund_vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub manager: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Permissionless reconciliation of `total_assets` against what the fund actually holds:
+    /// vault lamports above the rent reserve (excluding retained platform fees) plus the
+    /// fund's wrapped SOL balance. Picks up airdrops, reclaimed rent and direct transfers.
+    /// Token positions can't be valued here, so each position's mint and the fund's
+    /// associated token account for it are passed as remaining accounts, and syncing
+    /// fails while any of them holds a balance.
+    pub fn sync_assets(ctx: Context<SyncAssets>) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        
+        require_positions_empty(&fund.key(), &ctx.accounts.portfolio, ctx.remaining_accounts)?;
+        
+        let vault_lamports = ctx.accounts.fund_vault.lamports();
+        let rent_exempt = Rent::get()?.minimum_balance(0);
+        let vault_assets = vault_lamports
+            .saturating_sub(rent_exempt)
+            .saturating_sub(fund.platform_fees)
+            .saturating_sub(fund.pending_fees);
+        
+        // The wrapped SOL account is pinned to the fund's ATA so it can't be left out
+        let wsol_account = &ctx.accounts.wsol_account;
+        let wsol_assets = if wsol_account.data_is_empty() {
+            0
+        } else {
+            require_keys_eq!(*wsol_account.owner, anchor_spl::token::ID, FundrError::InvalidAccount);
+            let token_account = TokenAccount::try_deserialize(&mut &wsol_account.data.borrow()[..])?;
+            require_keys_eq!(token_account.owner, fund.key(), FundrError::InvalidAccount);
+            token_account.amount
+        };
+        
+        let previous_total_assets = fund.total_assets;
+        let new_total_assets = vault_assets.checked_add(wsol_assets).ok_or(FundrError::MathOverflow)?;
+        let delta = i64::try_from(new_total_assets as i128 - previous_total_assets as i128)
+            .map_err(|_| FundrError::MathOverflow)?;
+        
+        fund.total_assets = new_total_assets;
+        
+        emit!(AssetsSynced {
+            fund: fund.key(),
+            previous_total_assets,
+            new_total_assets,
+            delta,
+        });
+        
+        msg!(
+            "Synced fund assets: {} -> {} lamports (delta {})",
+            previous_total_assets,
+            new_total_assets,
+            delta
+        );
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct Deposit<'info> {
-    #[account(mut)]
-    pub fund: Account<'info, Fund>,
-    
-    #[account(
-        init_if_needed,
-        payer = depositor,
-        space = 8 + UserStake::INIT_SPACE,
-        seeds = [b"stake", fund.key().as_ref(), depositor.key().as_ref()],
-        bump
-    )]
-    pub user_stake: Account<'info, UserStake>,
-    
-    #[account(
-        mut,
-        seeds = [b"vault", fund.key().as_ref()],
-        bump
-    )]
-    /// CHECK: Fund vault PDA
-    pub fund_vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub depositor: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Begin winding the fund down: deposits stop and positions may only be sold back to SOL.
+    /// A multisig fund winds down through `ManagerAction::WindDown`.
+    pub fn initiate_wind_down(ctx: Context<InitiateWindDown>) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.manager.key(), FundrError::UnauthorizedManager);
+        require!(!fund.multisig, FundrError::MultisigRequired);
+        start_wind_down(fund)?;
+        
+        msg!("Fund {} is winding down", fund.name);
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct Withdraw<'info> {
-    #[account(mut)]
-    pub fund: Account<'info, Fund>,
-    
-    #[account(
-        mut,
-        seeds = [b"stake", fund.key().as_ref(), withdrawer.key().as_ref()],
-        bump
-    )]
-    pub user_stake: Account<'info, UserStake>,
-    
-    #[account(
-        mut,
-        seeds = [b"vault", fund.key().as_ref()],
-        bump
-    )]
-    /// CHECK: Fund vault PDA
-    pub fund_vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub withdrawer: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Permissionless crank that pays out an investor's full position during wind-down
+    /// and closes their stake account, returning its rent to the investor
+    pub fn force_distribute(ctx: Context<ForceDistribute>) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        let user_stake = &mut ctx.accounts.user_stake;
+        
+        require!(fund.status == FundStatus::WindingDown, FundrError::FundNotWindingDown);
+        
+        let shares = user_stake.shares;
+        let (payout, net_payout) = if shares > 0 {
+            redeem_shares(fund, user_stake, shares)?
+        } else {
+            (0, 0)
+        };
+        
+        if net_payout > 0 {
+            transfer_from_vault(
+                &ctx.accounts.fund_vault,
+                &ctx.accounts.investor,
+                &ctx.accounts.system_program,
+                &fund.key(),
+                ctx.bumps.fund_vault,
+                net_payout,
+            )?;
+        }
+        
+        msg!(
+            "Force distributed {} shares for {} lamports to {}",
+            shares,
+            payout,
+            ctx.accounts.investor.key()
+        );
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct Rebalance<'info> {
-    #[account(mut)]
-    pub fund: Account<'info, Fund>,
-    
+    /// Close a fully redeemed fund, its vault and any remaining token accounts (passed as
+    /// remaining accounts). Retained platform fees go to the platform and all other
+    /// lamports and rent to the manager. The creator's fund index is burned so a later
+    /// fund never reuses this address, or the proposals and signer set seeded from it.
+    /// A multisig fund's manager must redeem an approved `ManagerAction::CloseFund`.
+    pub fn close_fund<'info>(ctx: Context<'_, '_, 'info, 'info, CloseFund<'info>>) -> Result<()> {
+        let fund = &ctx.accounts.fund;
+        let manager = ctx.accounts.manager.to_account_info();
+        
+        require_keys_eq!(fund.authority, manager.key(), FundrError::UnauthorizedManager);
+        redeem_approval(
+            fund,
+            ctx.accounts.signer_set.as_deref(),
+            ctx.accounts.pending_action.as_mut(),
+            ManagerAction::CloseFund,
+        )?;
+        require!(fund.status == FundStatus::WindingDown, FundrError::FundNotWindingDown);
+        require!(fund.total_shares == 0, FundrError::FundHasOutstandingShares);
+        // Escrowed fees must be claimed or voided first so closing can't skip the delay
+        require!(fund.pending_fees == 0, FundrError::PendingFeesOutstanding);
+        
+        for account_info in ctx.remaining_accounts.iter() {
+            let token_account = InterfaceAccount::<TokenAccount>::try_from(account_info)?;
+            require_keys_eq!(token_account.owner, fund.key(), FundrError::InvalidAccount);
+            require!(token_account.amount == 0, FundrError::TokenAccountNotEmpty);
+            
+            close_fund_token_account(fund, account_info, &manager, &ctx.accounts.token_program)?;
+        }
+        
+        // Pay out the platform's share first, then drain the vault completely so the
+        // system account is reaped
+        let platform_fees = fund.platform_fees.min(ctx.accounts.fund_vault.lamports());
+        if platform_fees > 0 {
+            transfer_from_vault(
+                &ctx.accounts.fund_vault,
+                &ctx.accounts.platform,
+                &ctx.accounts.system_program,
+                &fund.key(),
+                ctx.bumps.fund_vault,
+                platform_fees,
+            )?;
+        }
+        let vault_lamports = ctx.accounts.fund_vault.lamports();
+        if vault_lamports > 0 {
+            transfer_from_vault(
+                &ctx.accounts.fund_vault,
+                &manager,
+                &ctx.accounts.system_program,
+                &fund.key(),
+                ctx.bumps.fund_vault,
+                vault_lamports,
+            )?;
+        }
+        
+        let profile = &mut ctx.accounts.manager_profile;
+        profile.manager = fund.seed_key();
+        profile.fund_count = profile.fund_count.max(fund.fund_index + 1);
+        profile.bump = ctx.bumps.manager_profile;
+        
+        msg!(
+            "Closed fund {}: paid {} lamports of platform fees and returned {} vault lamports to manager",
+            fund.name,
+            platform_fees,
+            vault_lamports
+        );
+        Ok(())
+    }
+
+    /// Create the zero-copy portfolio that tracks the fund's holdings. Funds created
+    /// before portfolios existed must call this once before they can rebalance.
+    pub fn initialize_portfolio(ctx: Context<InitializePortfolio>) -> Result<()> {
+        let fund = &ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.manager.key(), FundrError::UnauthorizedManager);
+        
+        let mut portfolio = ctx.accounts.portfolio.load_init()?;
+        portfolio.fund = fund.key();
+        portfolio.bump = ctx.bumps.portfolio;
+        
+        msg!("Initialized portfolio for fund {} with room for {} positions", fund.key(), MAX_POSITIONS);
+        
+        Ok(())
+    }
+
+    /// Add a token to the portfolio, or update its target weight if already held
+    pub fn set_position(ctx: Context<SetPosition>, target_weight_bps: u16) -> Result<()> {
+        let fund = &ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.manager.key(), FundrError::UnauthorizedManager);
+        require!(!fund.multisig, FundrError::MultisigRequired);
+        require!(fund.status == FundStatus::Active, FundrError::FundNotActive);
+        
+        let mint = ctx.accounts.mint.key();
+        let mut portfolio = ctx.accounts.portfolio.load_mut()?;
+        portfolio.set_position(mint, target_weight_bps)?;
+        
+        msg!(
+            "Position {} in fund {} targets {} bps ({} positions)",
+            mint,
+            fund.key(),
+            target_weight_bps,
+            portfolio.position_count
+        );
+        
+        Ok(())
+    }
+
+    /// Drop a token from the portfolio so it can no longer be bought
+    pub fn remove_position(ctx: Context<RemovePosition>, mint: Pubkey) -> Result<()> {
+        let fund = &ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.manager.key(), FundrError::UnauthorizedManager);
+        require!(!fund.multisig, FundrError::MultisigRequired);
+        
+        let mut portfolio = ctx.accounts.portfolio.load_mut()?;
+        portfolio.remove_position(&mint)?;
+        
+        msg!("Removed position {} from fund {}", mint, fund.key());
+        
+        Ok(())
+    }
+
+    /// Upgrade a fund written by an older program version to the current layout,
+    /// growing the account as needed. Anyone may pay to migrate a fund.
+    pub fn migrate_fund(ctx: Context<MigrateFund>) -> Result<()> {
+        let fund = ctx.accounts.fund.to_account_info();
+        let migrated = legacy::upgrade_fund(&fund.try_borrow_data()?)?;
+        
+        rewrite_account(&fund, &migrated, 8 + Fund::INIT_SPACE, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+        
+        msg!("Migrated fund {} to layout version {}", fund.key(), FUND_VERSION);
+        Ok(())
+    }
+
+    /// Upgrade a stake written by an older program version to the current layout,
+    /// growing the account as needed. Anyone may pay to migrate a stake.
+    pub fn migrate_stake(ctx: Context<MigrateStake>) -> Result<()> {
+        let stake = ctx.accounts.user_stake.to_account_info();
+        let migrated = legacy::upgrade_user_stake(&stake.try_borrow_data()?)?;
+        
+        rewrite_account(&stake, &migrated, 8 + UserStake::INIT_SPACE, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+        
+        msg!("Migrated stake {} to layout version {}", stake.key(), USER_STAKE_VERSION);
+        Ok(())
+    }
+
+    /// Pay the manager their escrowed performance fees once the claim delay has passed.
+    /// A multisig fund's manager must redeem an approved `ManagerAction::ClaimFees`.
+    pub fn claim_fees(ctx: Context<ClaimFees>) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.manager.key(), FundrError::UnauthorizedManager);
+        redeem_approval(
+            fund,
+            ctx.accounts.signer_set.as_deref(),
+            ctx.accounts.pending_action.as_mut(),
+            ManagerAction::ClaimFees,
+        )?;
+        require!(!fund.flagged, FundrError::FundFlagged);
+        require!(Clock::get()?.unix_timestamp >= fund.fees_claimable_at, FundrError::FeesLocked);
+        
+        let amount = fund.pending_fees;
+        if amount > 0 {
+            transfer_from_vault(
+                &ctx.accounts.fund_vault,
+                &ctx.accounts.manager.to_account_info(),
+                &ctx.accounts.system_program,
+                &fund.key(),
+                ctx.bumps.fund_vault,
+                amount,
+            )?;
+            fund.pending_fees = 0;
+            
+            emit!(FeesClaimed { fund: fund.key(), amount });
+        }
+        
+        msg!("Manager claimed {} lamports in performance fees", amount);
+        Ok(())
+    }
+
+    /// Create the protocol config. Only the program's upgrade authority can call this.
+    pub fn initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey, fee_claim_delay: i64) -> Result<()> {
+        require!(
+            (MIN_FEE_CLAIM_DELAY..=MAX_FEE_CLAIM_DELAY).contains(&fee_claim_delay),
+            FundrError::InvalidFeeClaimDelay
+        );
+        
+        let config = &mut ctx.accounts.config;
+        config.admin = admin;
+        config.fee_claim_delay = fee_claim_delay;
+        config.bump = ctx.bumps.config;
+        config.quorum_bps = DEFAULT_QUORUM_BPS;
+        config.approval_threshold_bps = DEFAULT_APPROVAL_THRESHOLD_BPS;
+        config.voting_period = DEFAULT_VOTING_PERIOD;
+        config.slippage_bps = DEFAULT_SLIPPAGE_BPS;
+        config.max_price_age = DEFAULT_MAX_PRICE_AGE;
+        
+        msg!("Protocol config initialized with admin {} and fee delay {}s", admin, fee_claim_delay);
+        Ok(())
+    }
+
+    /// Hand over the admin role and/or change the fee claim delay for future collections
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        admin: Option<Pubkey>,
+        fee_claim_delay: Option<i64>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        
+        if let Some(admin) = admin {
+            config.admin = admin;
+        }
+        if let Some(fee_claim_delay) = fee_claim_delay {
+            require!(
+                (MIN_FEE_CLAIM_DELAY..=MAX_FEE_CLAIM_DELAY).contains(&fee_claim_delay),
+                FundrError::InvalidFeeClaimDelay
+            );
+            config.fee_claim_delay = fee_claim_delay;
+        }
+        
+        msg!("Protocol config updated: admin {}, fee delay {}s", config.admin, config.fee_claim_delay);
+        Ok(())
+    }
+
+    /// Protocol admin flags (or clears) a fund as malicious. A flagged fund's manager
+    /// cannot claim fees, and the admin may void them.
+    pub fn set_fund_flag(ctx: Context<AdminFundAction>, flagged: bool) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        fund.flagged = flagged;
+        
+        emit!(FundFlagUpdated { fund: fund.key(), flagged });
+        
+        msg!("Fund {} flagged: {}", fund.name, flagged);
+        Ok(())
+    }
+
+    /// Protocol admin returns a flagged fund's escrowed fees to its investors
+    pub fn void_fees(ctx: Context<AdminFundAction>) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        
+        require!(fund.flagged, FundrError::FundNotFlagged);
+        
+        let amount = fund.pending_fees;
+        fund.total_assets = fund.total_assets.checked_add(amount).ok_or(FundrError::MathOverflow)?;
+        fund.pending_fees = 0;
+        fund.fees_claimable_at = 0;
+        
+        emit!(FeesVoided { fund: fund.key(), amount });
+        
+        msg!("Voided {} lamports of pending fees for fund {}", amount, fund.name);
+        Ok(())
+    }
+
+    /// Protocol admin sets the quorum, approval threshold and voting period used by
+    /// proposals created from now on
+    pub fn set_governance_params(
+        ctx: Context<UpdateConfig>,
+        quorum_bps: u16,
+        approval_threshold_bps: u16,
+        voting_period: i64,
+    ) -> Result<()> {
+        require!(
+            (1..=fundr_math::BPS_DENOMINATOR).contains(&(quorum_bps as u64))
+                && (DEFAULT_APPROVAL_THRESHOLD_BPS as u64..=fundr_math::BPS_DENOMINATOR)
+                    .contains(&(approval_threshold_bps as u64))
+                && (MIN_VOTING_PERIOD..=MAX_VOTING_PERIOD).contains(&voting_period),
+            FundrError::InvalidGovernanceParams
+        );
+        
+        let config = &mut ctx.accounts.config;
+        config.quorum_bps = quorum_bps;
+        config.approval_threshold_bps = approval_threshold_bps;
+        config.voting_period = voting_period;
+        
+        msg!(
+            "Governance updated: quorum {} bps, threshold {} bps, voting period {}s",
+            quorum_bps,
+            approval_threshold_bps,
+            voting_period
+        );
+        Ok(())
+    }
+
+    /// Set how far below oracle fair value a rebalance may accept, and how old an
+    /// oracle price may be when it bounds a trade
+    pub fn set_oracle_params(ctx: Context<UpdateConfig>, slippage_bps: u16, max_price_age: i64) -> Result<()> {
+        require!(
+            slippage_bps <= MAX_SLIPPAGE_BPS && (1..=MAX_PRICE_AGE).contains(&max_price_age),
+            FundrError::InvalidOracleParams
+        );
+        
+        let config = &mut ctx.accounts.config;
+        config.slippage_bps = slippage_bps;
+        config.max_price_age = max_price_age;
+        
+        msg!("Oracle bounds updated: slippage {} bps, max price age {}s", slippage_bps, max_price_age);
+        Ok(())
+    }
+
+    /// Protocol admin publishes a token's price in lamports per base unit, scaled by
+    /// `PRICE_SCALE`. Rebalances into or out of the token are bounded by it.
+    pub fn publish_price(ctx: Context<PublishPrice>, price: u64) -> Result<()> {
+        require!(price > 0, FundrError::InvalidPrice);
+        
+        let feed = &mut ctx.accounts.price_feed;
+        let now = Clock::get()?.unix_timestamp;
+        feed.mint = ctx.accounts.mint.key();
+        feed.price = price;
+        feed.updated_at = now;
+        feed.bump = ctx.bumps.price_feed;
+        
+        emit!(PricePublished { mint: feed.mint, price, published_at: now });
+        Ok(())
+    }
+
+    /// Any shareholder opens a proposal against their fund. Outstanding shares and the
+    /// protocol's governance parameters are snapshotted so later deposits or parameter
+    /// changes cannot move the goalposts.
+    pub fn create_proposal(ctx: Context<CreateProposal>, action: ProposalAction) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        let config = &ctx.accounts.config;
+        
+        require!(fund.status != FundStatus::WindingDown, FundrError::FundNotActive);
+        if let ProposalAction::ReplaceAuthority { new_authority } = action {
+            require!(new_authority != fund.authority, FundrError::InvalidProposal);
+        }
+        
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.fund = fund.key();
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.id = fund.proposal_count;
+        proposal.action = action;
+        proposal.snapshot_shares = fund.total_shares;
+        proposal.created_at = now;
+        proposal.voting_ends_at = now.checked_add(config.voting_period).ok_or(FundrError::MathOverflow)?;
+        proposal.quorum_bps = config.quorum_bps;
+        proposal.approval_threshold_bps = config.approval_threshold_bps;
+        proposal.votes_for = 0;
+        proposal.votes_against = 0;
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+        
+        fund.proposal_count = fund.proposal_count.checked_add(1).ok_or(FundrError::MathOverflow)?;
+        
+        emit!(ProposalCreated {
+            fund: fund.key(),
+            proposal: proposal.key(),
+            proposer: proposal.proposer,
+            action,
+            voting_ends_at: proposal.voting_ends_at,
+        });
+        
+        msg!("Proposal {} opened on fund {}", proposal.id, fund.name);
+        Ok(())
+    }
+
+    /// Shareholder votes on an open proposal, weighted by their shares. Only shares held
+    /// since before the proposal count: deposits made after it opened add no weight, and
+    /// withdrawals since then only lower it.
+    pub fn cast_vote(ctx: Context<CastVote>, support: bool) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        
+        require!(Clock::get()?.unix_timestamp < proposal.voting_ends_at, FundrError::VotingClosed);
+        let weight = ctx
+            .accounts
+            .voter_stake
+            .voting_shares(proposal.id)
+            .ok_or(FundrError::SharesAcquiredAfterSnapshot)?;
+        require!(weight > 0, FundrError::NoVotingPower);
+        
+        if support {
+            proposal.votes_for = proposal.votes_for.checked_add(weight).ok_or(FundrError::MathOverflow)?;
+        } else {
+            proposal.votes_against = proposal.votes_against.checked_add(weight).ok_or(FundrError::MathOverflow)?;
+        }
+        
+        // The record's init makes a second vote from the same investor fail
+        let vote = &mut ctx.accounts.vote_record;
+        vote.proposal = proposal.key();
+        vote.voter = ctx.accounts.voter.key();
+        vote.weight = weight;
+        vote.support = support;
+        vote.bump = ctx.bumps.vote_record;
+        
+        emit!(VoteCast {
+            proposal: proposal.key(),
+            voter: vote.voter,
+            weight,
+            support,
+        });
+        
+        msg!("Vote {} with {} shares on proposal {}", if support { "for" } else { "against" }, weight, proposal.id);
+        Ok(())
+    }
+
+    /// Permissionless crank that applies a proposal once voting has ended, if it reached
+    /// quorum and its approval threshold
+    pub fn execute_proposal(ctx: Context<ExecuteProposal>) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        let proposal = &mut ctx.accounts.proposal;
+        let now = Clock::get()?.unix_timestamp;
+        
+        require!(!proposal.executed, FundrError::ProposalAlreadyExecuted);
+        require!(now >= proposal.voting_ends_at, FundrError::VotingOpen);
+        require!(proposal.passed(), FundrError::ProposalNotPassed);
+        
+        match proposal.action {
+            ProposalAction::ReplaceAuthority { new_authority } => {
+                fund.transfer_authority(new_authority);
+                roles_updated(fund);
+                
+                // The replaced manager's signer set loses control of the fund, and
+                // anything it had pending can never execute
+                if fund.multisig {
+                    let signer_set = ctx.accounts.signer_set.as_mut().ok_or(FundrError::MissingActionAccount)?;
+                    signer_set.nonce = signer_set.nonce.checked_add(1).ok_or(FundrError::MathOverflow)?;
+                    fund.multisig = false;
+                }
+            }
+            ProposalAction::Pause => {
+                require!(fund.status == FundStatus::Active, FundrError::FundNotActive);
+                fund.status = FundStatus::Paused;
+            }
+            ProposalAction::Resume => {
+                require!(fund.status == FundStatus::Paused, FundrError::FundNotPaused);
+                fund.status = FundStatus::Active;
+            }
+            ProposalAction::WindDown => {
+                require!(fund.status != FundStatus::WindingDown, FundrError::FundNotActive);
+                fund.status = FundStatus::WindingDown;
+                fund.wind_down_started_at = now;
+            }
+        }
+        proposal.executed = true;
+        
+        emit!(ProposalExecuted {
+            fund: fund.key(),
+            proposal: proposal.key(),
+            action: proposal.action,
+        });
+        
+        msg!("Proposal {} executed on fund {}", proposal.id, fund.name);
+        Ok(())
+    }
+
+    /// Manager hands the fund's sensitive actions to an M-of-N signer set. From then on
+    /// fee collection, mode and allocation changes, authority changes and changes to
+    /// the set itself only happen through approved pending actions.
+    pub fn create_signer_set(ctx: Context<CreateSignerSet>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.manager.key(), FundrError::UnauthorizedManager);
+        require!(!fund.multisig, FundrError::MultisigRequired);
+        
+        let signer_set = &mut ctx.accounts.signer_set;
+        if signer_set.fund == fund.key() {
+            // A set left behind when governance replaced the owner keeps counting actions,
+            // so new ones don't collide with old ones, and voids everything proposed to it
+            signer_set.nonce = signer_set.nonce.checked_add(1).ok_or(FundrError::MathOverflow)?;
+        } else {
+            signer_set.fund = fund.key();
+            signer_set.nonce = 0;
+            signer_set.action_count = 0;
+        }
+        signer_set.signers = signers;
+        signer_set.threshold = threshold;
+        signer_set.bump = ctx.bumps.signer_set;
+        signer_set.validate()?;
+        
+        fund.multisig = true;
+        
+        msg!(
+            "Fund {} now requires {} of {} signers",
+            fund.name,
+            signer_set.threshold,
+            signer_set.signers.len()
+        );
+        Ok(())
+    }
+
+    /// A signer opens a pending action, approving it themselves
+    pub fn propose_action(ctx: Context<ProposeAction>, action: ManagerAction) -> Result<()> {
+        let signer_set = &mut ctx.accounts.signer_set;
+        let index = signer_set
+            .index_of(&ctx.accounts.proposer.key())
+            .ok_or(FundrError::NotASigner)?;
+        
+        let pending = &mut ctx.accounts.pending_action;
+        pending.fund = signer_set.fund;
+        pending.id = signer_set.action_count;
+        pending.action = action;
+        pending.proposer = ctx.accounts.proposer.key();
+        pending.approvals = 1 << index;
+        pending.signer_nonce = signer_set.nonce;
+        pending.created_at = Clock::get()?.unix_timestamp;
+        pending.executed = false;
+        pending.bump = ctx.bumps.pending_action;
+        
+        signer_set.action_count = signer_set.action_count.checked_add(1).ok_or(FundrError::MathOverflow)?;
+        
+        emit!(ActionProposed {
+            fund: pending.fund,
+            pending_action: pending.key(),
+            proposer: pending.proposer,
+            action,
+        });
+        
+        msg!("Action {} proposed on fund {}", pending.id, pending.fund);
+        Ok(())
+    }
+
+    /// A signer approves a pending action
+    pub fn approve_action(ctx: Context<ApproveAction>) -> Result<()> {
+        let signer_set = &ctx.accounts.signer_set;
+        let pending = &mut ctx.accounts.pending_action;
+        
+        require!(!pending.executed, FundrError::ActionAlreadyExecuted);
+        require!(pending.signer_nonce == signer_set.nonce, FundrError::StaleAction);
+        
+        let index = signer_set
+            .index_of(&ctx.accounts.signer.key())
+            .ok_or(FundrError::NotASigner)?;
+        require!(pending.approvals & (1 << index) == 0, FundrError::AlreadyApproved);
+        pending.approvals |= 1 << index;
+        
+        msg!(
+            "Action {} has {} of {} approvals",
+            pending.id,
+            pending.approvals.count_ones(),
+            signer_set.threshold
+        );
+        Ok(())
+    }
+
+    /// Permissionless crank that applies a pending action once it has enough approvals.
+    /// Actions approved under a signer set that has since changed must be proposed again.
+    pub fn execute_action(ctx: Context<ExecuteAction>) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        let signer_set = &mut ctx.accounts.signer_set;
+        let pending = &mut ctx.accounts.pending_action;
+        
+        require!(fund.multisig, FundrError::MultisigDisabled);
+        check_approved(pending, signer_set)?;
+        
+        match pending.action {
+            ManagerAction::CollectFees => {
+                let config = ctx.accounts.config.as_ref().ok_or(FundrError::MissingActionAccount)?;
+                crystallize_fees(fund, config)?;
+            }
+            ManagerAction::SetFundMode { mode } => fund.fund_mode = mode,
+            ManagerAction::SetPosition { mint, target_weight_bps } => {
+                require!(fund.status == FundStatus::Active, FundrError::FundNotActive);
+                let mint_account = ctx.accounts.mint.as_ref().ok_or(FundrError::MissingActionAccount)?;
+                require_keys_eq!(mint_account.key(), mint, FundrError::InvalidTokenMint);
+                let portfolio = ctx.accounts.portfolio.as_ref().ok_or(FundrError::MissingActionAccount)?;
+                portfolio.load_mut()?.set_position(mint, target_weight_bps)?;
+            }
+            ManagerAction::RemovePosition { mint } => {
+                let portfolio = ctx.accounts.portfolio.as_ref().ok_or(FundrError::MissingActionAccount)?;
+                portfolio.load_mut()?.remove_position(&mint)?;
+            }
+            ManagerAction::SetAuthority { new_authority } => {
+                fund.transfer_authority(new_authority);
+                roles_updated(fund);
+            }
+            ManagerAction::AddSigner { signer } => {
+                signer_set.signers.push(signer);
+                signer_set.validate()?;
+            }
+            ManagerAction::RemoveSigner { signer } => {
+                let index = signer_set.index_of(&signer).ok_or(FundrError::NotASigner)?;
+                signer_set.signers.remove(index);
+                signer_set.validate()?;
+            }
+            ManagerAction::SetThreshold { threshold } => {
+                signer_set.threshold = threshold;
+                signer_set.validate()?;
+            }
+            ManagerAction::SetTrader { trader, max_trade_amount } => {
+                fund.trader = trader;
+                fund.max_trade_amount = max_trade_amount;
+                roles_updated(fund);
+            }
+            ManagerAction::SetOperator { operator } => {
+                fund.operator = operator;
+                roles_updated(fund);
+            }
+            ManagerAction::SetRiskLimits { max_trade_bps, max_daily_turnover_bps, max_position_bps } => {
+                fund.set_risk_limits(max_trade_bps, max_daily_turnover_bps, max_position_bps)?;
+            }
+            ManagerAction::WindDown => start_wind_down(fund)?,
+            // These pay the manager, who redeems the approval through claim_fees or close_fund
+            ManagerAction::ClaimFees | ManagerAction::CloseFund => return err!(FundrError::ExecutedByManager),
+        }
+        pending.executed = true;
+        
+        // Approvals are tracked by signer index, so any change to the set invalidates
+        // actions still waiting on it
+        if matches!(
+            pending.action,
+            ManagerAction::AddSigner { .. } | ManagerAction::RemoveSigner { .. } | ManagerAction::SetThreshold { .. }
+        ) {
+            signer_set.nonce = signer_set.nonce.checked_add(1).ok_or(FundrError::MathOverflow)?;
+        }
+        
+        emit!(ActionExecuted {
+            fund: fund.key(),
+            pending_action: pending.key(),
+            action: pending.action,
+        });
+        
+        msg!("Action {} executed on fund {}", pending.id, fund.name);
+        Ok(())
+    }
+
+    /// Owner sets the fund's risk limits, each in basis points of NAV (0 = no limit):
+    /// the largest single trade, lamports traded over a rolling day, and the cost basis
+    /// any one position may reach
+    pub fn set_risk_limits(
+        ctx: Context<SetRole>,
+        max_trade_bps: u16,
+        max_daily_turnover_bps: u16,
+        max_position_bps: u16,
+    ) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.owner.key(), FundrError::UnauthorizedManager);
+        require!(!fund.multisig, FundrError::MultisigRequired);
+        
+        fund.set_risk_limits(max_trade_bps, max_daily_turnover_bps, max_position_bps)
+    }
+
+    /// Owner delegates rebalancing to a trader key, capping `token_in_amount` per trade
+    /// (0 = no cap). Pass the default pubkey to revoke the role.
+    pub fn set_trader(ctx: Context<SetRole>, trader: Pubkey, max_trade_amount: u64) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.owner.key(), FundrError::UnauthorizedManager);
+        require!(!fund.multisig, FundrError::MultisigRequired);
+        
+        fund.trader = trader;
+        fund.max_trade_amount = max_trade_amount;
+        
+        roles_updated(fund);
+        Ok(())
+    }
+
+    /// Owner delegates account cleanup (closing token accounts, reclaiming stake rent)
+    /// to an operator key. Pass the default pubkey to revoke the role.
+    pub fn set_operator(ctx: Context<SetRole>, operator: Pubkey) -> Result<()> {
+        let fund = &mut ctx.accounts.fund;
+        
+        require_keys_eq!(fund.authority, ctx.accounts.owner.key(), FundrError::UnauthorizedManager);
+        require!(!fund.multisig, FundrError::MultisigRequired);
+        
+        fund.operator = operator;
+        
+        roles_updated(fund);
+        Ok(())
+    }
+}
+
+/// Whether `amount` is at most `bps` of `nav`; a limit of 0 is no limit
+/// Move an active fund into wind-down
+fn start_wind_down(fund: &mut Fund) -> Result<()> {
+    require!(fund.status == FundStatus::Active, FundrError::FundNotActive);
+    fund.status = FundStatus::WindingDown;
+    fund.wind_down_started_at = Clock::get()?.unix_timestamp;
+    Ok(())
+}
+
+/// Whether `pending` still has enough approvals from the current signer set to run
+fn check_approved(pending: &PendingAction, signer_set: &SignerSet) -> Result<()> {
+    require!(!pending.executed, FundrError::ActionAlreadyExecuted);
+    require!(pending.signer_nonce == signer_set.nonce, FundrError::StaleAction);
+    require!(
+        pending.approvals.count_ones() >= signer_set.threshold as u32,
+        FundrError::ActionNotApproved
+    );
+    Ok(())
+}
+
+/// A multisig fund's manager may only claim fees or close the fund by redeeming an
+/// approved `action`, which is marked executed here. Single-key funds need none.
+fn redeem_approval(
+    fund: &Account<Fund>,
+    signer_set: Option<&SignerSet>,
+    pending: Option<&mut Account<PendingAction>>,
+    action: ManagerAction,
+) -> Result<()> {
+    if !fund.multisig {
+        return Ok(());
+    }
+    let (Some(signer_set), Some(pending)) = (signer_set, pending) else {
+        return err!(FundrError::MultisigRequired);
+    };
+    require!(pending.action == action, FundrError::InvalidAccount);
+    check_approved(pending, signer_set)?;
+    pending.executed = true;
+    
+    emit!(ActionExecuted {
+        fund: fund.key(),
+        pending_action: pending.key(),
+        action,
+    });
+    Ok(())
+}
+
+fn within_nav_share(amount: u64, nav: u64, bps: u16) -> bool {
+    bps == 0 || amount as u128 * fundr_math::BPS_DENOMINATOR as u128 <= nav as u128 * bps as u128
+}
+
+/// Announce a fund's current role assignments
+fn roles_updated(fund: &Account<Fund>) {
+    emit!(RolesUpdated {
+        fund: fund.key(),
+        trader: fund.trader,
+        operator: fund.operator,
+        max_trade_amount: fund.max_trade_amount,
+    });
+    
+    msg!(
+        "Fund {} roles: trader {} (max trade {}), operator {}",
+        fund.name,
+        fund.trader,
+        fund.max_trade_amount,
+        fund.operator
+    );
+}
+
+/// Move the performance fee owed above the high water mark into escrow. Runs at most
+/// once per fee period so neither the manager nor a cranker can pick the timing.
+fn crystallize_fees(fund: &mut Account<Fund>, config: &ProtocolConfig) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    require!(current_time >= fund.next_fee_collection(), FundrError::FeeCooldown);
+    
+    // Calculate performance fee if above high water mark
+    let current_nav = math(fundr_math::nav_per_share(fund.total_assets, fund.total_shares))?;
+    let performance_fee_amount = math(fundr_math::performance_fee(
+        fund.total_shares,
+        current_nav,
+        fund.high_water_mark,
+        fund.performance_fee,
+    ))?;
+
+    if performance_fee_amount > 0 {
+        // Move the fee out of investor assets into escrow; the lamports stay in the
+        // vault and every new crystallization restarts the claim delay
+        fund.total_assets = fund.total_assets.checked_sub(performance_fee_amount).ok_or(FundrError::MathOverflow)?;
+        fund.pending_fees = fund.pending_fees.checked_add(performance_fee_amount).ok_or(FundrError::MathOverflow)?;
+        fund.fees_claimable_at = current_time
+            .checked_add(config.fee_claim_delay)
+            .ok_or(FundrError::MathOverflow)?;
+        fund.high_water_mark = current_nav;
+        
+        emit!(FeesCrystallized {
+            fund: fund.key(),
+            amount: performance_fee_amount,
+            pending_fees: fund.pending_fees,
+            claimable_at: fund.fees_claimable_at,
+        });
+    }
+
+    fund.last_fee_collection = current_time;
+
+    msg!(
+        "Crystallized {} lamports in performance fees ({} pending)",
+        performance_fee_amount,
+        fund.pending_fees
+    );
+
+    Ok(())
+}
+
+/// Mint shares for a deposit of `amount` lamports and return (net deposit, shares minted).
+/// The 1% platform fee stays in the vault outside of `total_assets`.
+fn issue_shares(fund: &mut Account<Fund>, user_stake: &mut UserStake, user: Pubkey, amount: u64) -> Result<(u64, u64)> {
+    // Calculate platform fee (1%)
+    let platform_fee = fundr_math::platform_fee(amount);
+    let net_deposit = amount.checked_sub(platform_fee).ok_or(FundrError::MathOverflow)?;
+    
+    // Calculate shares to mint; a deposit too small to buy a single share is rejected
+    // rather than silently donated to existing holders
+    let shares_to_mint = fund.shares_for_deposit(net_deposit)?;
+    require!(shares_to_mint > 0, FundrError::ZeroSharesMinted);
+
+    // Only count a new investor when the stake goes from empty to holding shares
+    if user_stake.shares == 0 {
+        fund.investor_count = fund.investor_count.checked_add(1).ok_or(FundrError::MathOverflow)?;
+    }
+
+    // Update user stake
+    user_stake.record_deposit(fund.proposal_count);
+    user_stake.user = user;
+    user_stake.fund = fund.key();
+    user_stake.version = USER_STAKE_VERSION;
+    user_stake.shares = user_stake.shares.checked_add(shares_to_mint).ok_or(FundrError::MathOverflow)?;
+    user_stake.total_deposited = user_stake.total_deposited.checked_add(net_deposit).ok_or(FundrError::MathOverflow)?;
+    user_stake.last_deposit = Clock::get()?.unix_timestamp;
+
+    // Update fund totals
+    fund.total_shares = fund.total_shares.checked_add(shares_to_mint).ok_or(FundrError::MathOverflow)?;
+    fund.total_assets = fund.total_assets.checked_add(net_deposit).ok_or(FundrError::MathOverflow)?;
+    fund.platform_fees = fund.platform_fees.checked_add(platform_fee).ok_or(FundrError::MathOverflow)?;
+
+    Ok((net_deposit, shares_to_mint))
+}
+
+/// Burn `shares` from a stake and return the (gross, net) lamports owed for them.
+/// The 1% withdrawal fee is waived once the fund is winding down.
+fn redeem_shares(fund: &mut Fund, user_stake: &mut UserStake, shares: u64) -> Result<(u64, u64)> {
+    // Calculate SOL amount to withdraw
+    let withdrawal_amount = fund.lamports_for_shares(shares)?;
+    
+    // Calculate withdrawal fee (1%), waived during wind-down
+    let withdrawal_fee = fundr_math::withdrawal_fee(withdrawal_amount, fund.status == FundStatus::WindingDown);
+    let net_withdrawal = withdrawal_amount.checked_sub(withdrawal_fee).ok_or(FundrError::MathOverflow)?;
+
+    burn_shares(fund, user_stake, shares, withdrawal_amount)?;
+    fund.platform_fees = fund.platform_fees.checked_add(withdrawal_fee).ok_or(FundrError::MathOverflow)?;
+
+    Ok((withdrawal_amount, net_withdrawal))
+}
+
+/// Remove `shares` from a stake and `withdrawal_amount` lamports from the fund's books
+fn burn_shares(fund: &mut Fund, user_stake: &mut UserStake, shares: u64, withdrawal_amount: u64) -> Result<()> {
+    // Update user stake
+    user_stake.shares = user_stake.shares.checked_sub(shares).ok_or(FundrError::MathOverflow)?;
+    user_stake.last_withdrawal = Clock::get()?.unix_timestamp;
+
+    // Update fund totals
+    fund.total_shares = fund.total_shares.checked_sub(shares).ok_or(FundrError::MathOverflow)?;
+    fund.total_assets = fund.total_assets.checked_sub(withdrawal_amount).ok_or(FundrError::MathOverflow)?;
+
+    // Only drop the investor when the stake goes from holding shares to empty
+    if shares > 0 && user_stake.shares == 0 {
+        fund.investor_count = fund.investor_count.checked_sub(1).ok_or(FundrError::MathOverflow)?;
+    }
+
+    Ok(())
+}
+
+/// Move lamports from a signer into the vault PDA
+fn transfer_to_vault<'info>(
+    from: &AccountInfo<'info>,
+    fund_vault: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = anchor_lang::system_program::Transfer {
+        from: from.clone(),
+        to: fund_vault.clone(),
+    };
+    let cpi_ctx = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+    
+    anchor_lang::system_program::transfer(cpi_ctx, amount)
+}
+
+/// Move lamports out of the system-owned vault PDA by signing with its seeds
+fn transfer_from_vault<'info>(
+    fund_vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    fund_key: &Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[b"vault", fund_key.as_ref(), &[vault_bump]];
+    let signer_seeds = &[&seeds[..]];
+    
+    let cpi_accounts = anchor_lang::system_program::Transfer {
+        from: fund_vault.clone(),
+        to: to.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(system_program.to_account_info(), cpi_accounts, signer_seeds);
+    
+    anchor_lang::system_program::transfer(cpi_ctx, amount)
+}
+
+/// Resize a program-owned account to `space`, topping up its rent from `payer`, and
+/// overwrite its data with `value`
+fn rewrite_account<'info, T: AccountSerialize>(
+    account: &AccountInfo<'info>,
+    value: &T,
+    space: usize,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let rent_due = Rent::get()?.minimum_balance(space).saturating_sub(account.lamports());
+    if rent_due > 0 {
+        let cpi_accounts = anchor_lang::system_program::Transfer {
+            from: payer.to_account_info(),
+            to: account.clone(),
+        };
+        let cpi_ctx = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+        anchor_lang::system_program::transfer(cpi_ctx, rent_due)?;
+    }
+    
+    account.realloc(space, false)?;
+    let mut data = account.try_borrow_mut_data()?;
+    data.fill(0);
+    value.try_serialize(&mut &mut data[..])
+}
+
+/// Amount that arrives after a transfer of `amount` of `mint`, net of any Token-2022
+/// transfer fee for the current epoch. Classic SPL mints never charge a fee.
+fn amount_after_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(amount);
+    }
+    
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let fee = match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(transfer_fee_config) => transfer_fee_config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(FundrError::MathOverflow)?,
+        Err(_) => 0,
+    };
+    
+    amount.checked_sub(fee).ok_or(FundrError::MathOverflow.into())
+}
+
+/// Whether `mint` is a position in the fund's portfolio; funds without one hold none
+fn is_position(portfolio: &AccountInfo, mint: &Pubkey) -> Result<bool> {
+    read_portfolio(portfolio, |portfolio| portfolio.position_index(mint).is_some())
+}
+
+/// Require the fund to hold none of the tokens its portfolio lists, since they can't be
+/// valued in SOL. `accounts` pairs each position's mint with the fund's associated token
+/// account for it, which may not exist yet.
+fn require_positions_empty(fund: &Pubkey, portfolio: &AccountInfo, accounts: &[AccountInfo]) -> Result<()> {
+    let mints: Vec<Pubkey> =
+        read_portfolio(portfolio, |portfolio| portfolio.positions().iter().map(|position| position.mint).collect())?;
+    require!(accounts.len() == 2 * mints.len(), FundrError::PositionsNotValued);
+    
+    for (mint, pair) in mints.iter().zip(accounts.chunks(2)) {
+        let (mint_account, token_account) = (&pair[0], &pair[1]);
+        require_keys_eq!(mint_account.key(), *mint, FundrError::InvalidTokenMint);
+        // Pinning the account to the mint's own token program keeps a balance from
+        // hiding behind the other program's empty address
+        require_keys_eq!(
+            token_account.key(),
+            get_associated_token_address_with_program_id(fund, mint, mint_account.owner),
+            FundrError::InvalidAccount
+        );
+        if !token_account.data_is_empty() {
+            require_keys_eq!(*token_account.owner, *mint_account.owner, FundrError::InvalidAccount);
+            let balance = TokenAccount::try_deserialize(&mut &token_account.data.borrow()[..])?.amount;
+            require!(balance == 0, FundrError::PositionsNotValued);
+        }
+    }
+    Ok(())
+}
+
+/// Run `f` against a portfolio PDA that may not exist yet, answering the default if it doesn't
+fn read_portfolio<T: Default>(portfolio: &AccountInfo, f: impl FnOnce(&Portfolio) -> T) -> Result<T> {
+    if portfolio.data_is_empty() {
+        return Ok(T::default());
+    }
+    require_keys_eq!(*portfolio.owner, crate::ID, FundrError::InvalidAccount);
+    let data = portfolio.try_borrow_data()?;
+    require!(
+        data.starts_with(&<Portfolio as anchor_lang::Discriminator>::DISCRIMINATOR),
+        ErrorCode::AccountDiscriminatorMismatch
+    );
+    Ok(f(bytemuck::from_bytes(&data[8..8 + std::mem::size_of::<Portfolio>()])))
+}
+
+/// Close an empty token account owned by the fund PDA, sending its rent to `destination`
+fn close_fund_token_account<'info>(
+    fund: &Account<'info, Fund>,
+    token_account: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let cpi_accounts = token_interface::CloseAccount {
+        account: token_account.clone(),
+        destination: destination.clone(),
+        authority: fund.to_account_info(),
+    };
+    
+    let seed_key = fund.seed_key();
+    let index_seed = fund_index_seed(fund.fund_index);
+    let seeds = &[b"fund", seed_key.as_ref(), &index_seed, &[fund.bump]];
+    let signer_seeds = &[&seeds[..]];
+    
+    let cpi_program = token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+    
+    token_interface::close_account(cpi_ctx)
+}
+
+#[derive(Accounts)]
+pub struct UpdateFundMode<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    pub manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFund<'info> {
+    #[account(
+        init_if_needed,
+        payer = manager,
+        space = 8 + ManagerProfile::INIT_SPACE,
+        seeds = [b"manager", manager.key().as_ref()],
+        bump
+    )]
+    pub manager_profile: Account<'info, ManagerProfile>,
+    
+    #[account(
+        init,
+        payer = manager,
+        space = 8 + Fund::INIT_SPACE,
+        seeds = [b"fund", manager.key().as_ref(), &fund_index_seed(manager_profile.fund_count)],
+        bump
+    )]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", fund.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Fund vault PDA for holding SOL - freshness validated in instruction
+    pub fund_vault: AccountInfo<'info>,
+    
+    /// Only passed, and created, when the manager seeds the fund
+    #[account(
+        init,
+        payer = manager,
+        space = 8 + UserStake::INIT_SPACE,
+        seeds = [b"stake", fund.key().as_ref(), manager.key().as_ref()],
+        bump
+    )]
+    pub manager_stake: Option<Account<'info, UserStake>>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + UserStake::INIT_SPACE,
+        seeds = [b"stake", fund.key().as_ref(), depositor.key().as_ref()],
+        bump,
+        // A stake this deposit creates gets its version as the shares are issued
+        constraint = user_stake.user == Pubkey::default() || user_stake.require_current()?
+    )]
+    pub user_stake: Account<'info, UserStake>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", fund.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Fund vault PDA
+    pub fund_vault: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        mut,
+        seeds = [b"stake", fund.key().as_ref(), withdrawer.key().as_ref()],
+        bump,
+        constraint = user_stake.require_current()?
+    )]
+    pub user_stake: Account<'info, UserStake>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", fund.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Fund vault PDA
+    pub fund_vault: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub withdrawer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_in_mint: Pubkey, token_in_amount: u64, token_out_mint: Pubkey)]
+pub struct Rebalance<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    #[account(constraint = output_mint.key() == token_out_mint @ FundrError::InvalidTokenMint)]
+    pub output_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"portfolio", fund.key().as_ref()],
+        bump = portfolio.load()?.bump
+    )]
+    pub portfolio: AccountLoader<'info, Portfolio>,
+    
+    /// CHECK: Token accounts for rebalancing will be validated in instruction
+    pub token_program: Interface<'info, TokenInterface>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    // Must price the trade's non-SOL token; checked against the arguments in the handler
+    #[account(seeds = [b"price", price_feed.mint.as_ref()], bump = price_feed.bump)]
+    pub price_feed: Account<'info, PriceFeed>,
+}
+
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    pub manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CrankFees<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    pub cranker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFees<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", fund.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Fund vault PDA
+    pub fund_vault: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    // A multisig fund's approval to claim
+    #[account(seeds = [b"signers", fund.key().as_ref()], bump = signer_set.bump)]
+    pub signer_set: Option<Account<'info, SignerSet>>,
+    
+    #[account(mut, has_one = fund @ FundrError::InvalidAccount)]
+    pub pending_action: Option<Account<'info, PendingAction>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = upgrade_authority,
+        space = 8 + ProtocolConfig::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    // Only the program's upgrade authority may create the config
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ FundrError::InvalidAccount)]
+    pub program: Program<'info, crate::program::Fundr>,
+    
+    #[account(constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key()) @ FundrError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    
+    #[account(mut)]
+    pub upgrade_authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin @ FundrError::Unauthorized)]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PublishPrice<'info> {
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ FundrError::Unauthorized)]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + PriceFeed::INIT_SPACE,
+        seeds = [b"price", mint.key().as_ref()],
+        bump
+    )]
+    pub price_feed: Account<'info, PriceFeed>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdminFundAction<'info> {
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ FundrError::Unauthorized)]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateProposal<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + Proposal::INIT_SPACE,
+        seeds = [b"proposal", fund.key().as_ref(), &fund.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    // Only investors can open proposals
+    #[account(
+        seeds = [b"stake", fund.key().as_ref(), proposer.key().as_ref()],
+        bump,
+        constraint = proposer_stake.require_current()?,
+        constraint = proposer_stake.shares > 0 @ FundrError::NoVotingPower
+    )]
+    pub proposer_stake: Account<'info, UserStake>,
+    
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CastVote<'info> {
+    #[account(mut, has_one = fund @ FundrError::InvalidAccount)]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        seeds = [b"stake", fund.key().as_ref(), voter.key().as_ref()],
+        bump,
+        constraint = voter_stake.require_current()?
+    )]
+    pub voter_stake: Account<'info, UserStake>,
+    
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + VoteRecord::INIT_SPACE,
+        seeds = [b"vote", proposal.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    #[account(mut, has_one = fund @ FundrError::InvalidAccount)]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    /// The fund's signer set, required to replace the authority of a multisig fund
+    #[account(
+        mut,
+        seeds = [b"signers", fund.key().as_ref()],
+        bump = signer_set.bump
+    )]
+    pub signer_set: Option<Account<'info, SignerSet>>,
+    
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateSignerSet<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    // Re-created after governance replaces the owner of a multisig fund
+    #[account(
+        init_if_needed,
+        payer = manager,
+        space = 8 + SignerSet::INIT_SPACE,
+        seeds = [b"signers", fund.key().as_ref()],
+        bump
+    )]
+    pub signer_set: Account<'info, SignerSet>,
+    
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAction<'info> {
+    #[account(
+        mut,
+        seeds = [b"signers", signer_set.fund.as_ref()],
+        bump = signer_set.bump
+    )]
+    pub signer_set: Account<'info, SignerSet>,
+    
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + PendingAction::INIT_SPACE,
+        seeds = [b"action", signer_set.fund.as_ref(), &signer_set.action_count.to_le_bytes()],
+        bump
+    )]
+    pub pending_action: Account<'info, PendingAction>,
+    
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveAction<'info> {
+    #[account(
+        seeds = [b"signers", signer_set.fund.as_ref()],
+        bump = signer_set.bump
+    )]
+    pub signer_set: Account<'info, SignerSet>,
+    
+    #[account(mut, constraint = pending_action.fund == signer_set.fund @ FundrError::InvalidAccount)]
+    pub pending_action: Account<'info, PendingAction>,
+    
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAction<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        mut,
+        seeds = [b"signers", fund.key().as_ref()],
+        bump = signer_set.bump
+    )]
+    pub signer_set: Account<'info, SignerSet>,
+    
+    #[account(mut, has_one = fund @ FundrError::InvalidAccount)]
+    pub pending_action: Account<'info, PendingAction>,
+    
+    // Only the accounts the action touches need to be passed
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProtocolConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"portfolio", fund.key().as_ref()],
+        bump
+    )]
+    pub portfolio: Option<AccountLoader<'info, Portfolio>>,
+    
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRole<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimRent<'info> {
+    #[account(constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    pub manager: Signer<'info>,
+    
+    #[account(
+        mut,
+        close = stake_owner,
+        has_one = fund @ FundrError::InvalidAccount,
+        constraint = closed_stake.shares == 0 @ FundrError::AccountNotEmpty
+    )]
+    pub closed_stake: Account<'info, UserStake>,
+    
+    #[account(mut, address = closed_stake.user @ FundrError::InvalidAccount)]
+    /// CHECK: Investor who paid the stake's rent
+    pub stake_owner: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStake<'info> {
+    #[account(
+        mut,
+        close = user,
+        has_one = user @ FundrError::InvalidAccount,
+        constraint = user_stake.shares == 0 @ FundrError::AccountNotEmpty
+    )]
+    pub user_stake: Account<'info, UserStake>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTokenAccount<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", fund.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Fund vault PDA
+    pub fund_vault: AccountInfo<'info>,
+    
     #[account(mut)]
     pub manager: Signer<'info>,
     
-    /// CHECK: Token accounts for rebalancing will be validated in instruction
-    pub token_program: Program<'info, Token>,
+    #[account(
+        mut,
+        constraint = token_account.owner == fund.key() @ FundrError::InvalidAccount
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(seeds = [b"portfolio", fund.key().as_ref()], bump)]
+    /// CHECK: Fund's portfolio PDA - may not exist yet, parsed in instruction
+    pub portfolio: AccountInfo<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTokenAccounts<'info> {
+    #[account(constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", fund.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Fund vault PDA
+    pub fund_vault: AccountInfo<'info>,
+    
+    pub manager: Signer<'info>,
+    
+    #[account(seeds = [b"portfolio", fund.key().as_ref()], bump)]
+    /// CHECK: Fund's portfolio PDA - may not exist yet, parsed in instruction
+    pub portfolio: AccountInfo<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFundMetadata<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    pub manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SyncAssets<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        seeds = [b"vault", fund.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Fund vault PDA
+    pub fund_vault: AccountInfo<'info>,
+    
+    #[account(address = get_associated_token_address(&fund.key(), &native_mint::ID))]
+    /// CHECK: Fund's wrapped SOL ATA - may not exist yet, parsed in instruction
+    pub wsol_account: AccountInfo<'info>,
+    
+    #[account(seeds = [b"portfolio", fund.key().as_ref()], bump)]
+    /// CHECK: Fund's portfolio PDA - may not exist yet, parsed in instruction
+    pub portfolio: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-pub struct CollectFees<'info> {
-    #[account(mut)]
+pub struct InitiateWindDown<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    pub manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ForceDistribute<'info> {
+    #[account(mut, constraint = fund.require_current()?)]
     pub fund: Account<'info, Fund>,
     
+    #[account(
+        mut,
+        close = investor,
+        seeds = [b"stake", fund.key().as_ref(), investor.key().as_ref()],
+        bump,
+        constraint = user_stake.require_current()?
+    )]
+    pub user_stake: Account<'info, UserStake>,
+    
     #[account(
         mut,
         seeds = [b"vault", fund.key().as_ref()],
@@ -431,14 +2262,36 @@ pub struct CollectFees<'info> {
     pub fund_vault: AccountInfo<'info>,
     
     #[account(mut)]
-    pub manager: Signer<'info>,
+    /// CHECK: Stake owner receiving the payout - bound to the stake by its seeds
+    pub investor: AccountInfo<'info>,
+    
+    pub cranker: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ReclaimRent<'info> {
-    #[account(mut)]
+pub struct CloseFund<'info> {
+    #[account(mut, close = manager, constraint = fund.require_current()?)]
     pub fund: Account<'info, Fund>,
     
+    /// Created here for funds opened before creators had a profile
+    #[account(
+        init_if_needed,
+        payer = manager,
+        space = 8 + ManagerProfile::INIT_SPACE,
+        seeds = [b"manager", fund.seed_key().as_ref()],
+        bump
+    )]
+    pub manager_profile: Account<'info, ManagerProfile>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut, address = config.admin @ FundrError::InvalidAccount)]
+    /// CHECK: Protocol admin, paid the fund's retained platform fees
+    pub platform: AccountInfo<'info>,
+    
     #[account(
         mut,
         seeds = [b"vault", fund.key().as_ref()],
@@ -450,40 +2303,111 @@ pub struct ReclaimRent<'info> {
     #[account(mut)]
     pub manager: Signer<'info>,
     
-    /// CHECK: Account to reclaim rent from - validated in instruction
+    /// The fund's portfolio, closed alongside it if one was created
+    #[account(
+        mut,
+        close = manager,
+        seeds = [b"portfolio", fund.key().as_ref()],
+        bump
+    )]
+    pub portfolio: Option<AccountLoader<'info, Portfolio>>,
+    
+    // A multisig fund's approval to close
+    #[account(seeds = [b"signers", fund.key().as_ref()], bump = signer_set.bump)]
+    pub signer_set: Option<Account<'info, SignerSet>>,
+    
+    #[account(mut, has_one = fund @ FundrError::InvalidAccount)]
+    pub pending_action: Option<Account<'info, PendingAction>>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateFund<'info> {
+    /// CHECK: Older layouts cannot be deserialized as `Fund`; the handler checks the
+    /// discriminator and decodes them itself
+    #[account(mut, owner = crate::ID @ FundrError::InvalidAccount)]
+    pub fund: UncheckedAccount<'info>,
+    
     #[account(mut)]
-    pub closed_account: AccountInfo<'info>,
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CloseTokenAccount<'info> {
+pub struct MigrateStake<'info> {
+    /// CHECK: Older layouts cannot be deserialized as `UserStake`; the handler checks
+    /// the discriminator and decodes them itself
+    #[account(mut, owner = crate::ID @ FundrError::InvalidAccount)]
+    pub user_stake: UncheckedAccount<'info>,
+    
     #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePortfolio<'info> {
+    #[account(constraint = fund.require_current()?)]
     pub fund: Account<'info, Fund>,
     
     #[account(
-        mut,
-        seeds = [b"vault", fund.key().as_ref()],
+        init,
+        payer = manager,
+        space = 8 + std::mem::size_of::<Portfolio>(),
+        seeds = [b"portfolio", fund.key().as_ref()],
         bump
     )]
-    /// CHECK: Fund vault PDA
-    pub fund_vault: AccountInfo<'info>,
+    pub portfolio: AccountLoader<'info, Portfolio>,
     
     #[account(mut)]
     pub manager: Signer<'info>,
     
-    #[account(mut)]
-    pub token_account: Account<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPosition<'info> {
+    #[account(constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        mut,
+        seeds = [b"portfolio", fund.key().as_ref()],
+        bump = portfolio.load()?.bump
+    )]
+    pub portfolio: AccountLoader<'info, Portfolio>,
+    
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemovePosition<'info> {
+    #[account(constraint = fund.require_current()?)]
+    pub fund: Account<'info, Fund>,
+    
+    #[account(
+        mut,
+        seeds = [b"portfolio", fund.key().as_ref()],
+        bump = portfolio.load()?.bump
+    )]
+    pub portfolio: AccountLoader<'info, Portfolio>,
     
-    pub token_program: Program<'info, Token>,
+    pub manager: Signer<'info>,
 }
 
 #[account]
 #[derive(InitSpace)]
 pub struct Fund {
-    pub authority: Pubkey,      // Fund manager
-    #[max_len(50)]
+    pub authority: Pubkey,      // Fund owner: fees, configuration and role assignment
+    #[max_len(MAX_NAME_LEN)]
     pub name: String,           // Fund name
-    #[max_len(200)]
+    #[max_len(MAX_DESCRIPTION_LEN)]
     pub description: String,    // Fund description
     pub performance_fee: u16,   // Performance fee in basis points (capped at 20%)
     pub min_deposit: u64,       // Minimum deposit amount in lamports
@@ -495,14 +2419,192 @@ pub struct Fund {
     pub created_at: i64,        // Unix timestamp of creation
     pub last_fee_collection: i64, // Last fee collection timestamp
     pub high_water_mark: u64,   // High water mark for performance fees (fixed point)
+    pub status: FundStatus,     // Active or winding down
+    pub wind_down_started_at: i64, // Unix timestamp wind-down began (0 if active)
+    #[max_len(MAX_URI_LEN)]
+    pub uri: String,            // Metadata URI (logo, strategy docs)
+    pub platform_fees: u64,     // Platform/withdrawal fees retained in the vault (not investor assets)
+    pub version: u8,            // Account layout version (FUND_VERSION)
+    pub pending_fees: u64,      // Crystallized performance fees held in escrow for the manager
+    pub fees_claimable_at: i64, // Unix timestamp after which pending fees can be claimed
+    pub flagged: bool,          // Flagged as malicious by the protocol admin; fees can't be claimed
+    pub fee_period: i64,        // Seconds between performance fee crystallizations (0 = MIN_FEE_PERIOD)
+    pub creator: Pubkey,        // Manager the fund PDA was derived from (default = authority)
+    pub proposal_count: u64,    // Governance proposals opened so far; seeds the next proposal
+    pub multisig: bool,         // Sensitive actions need approval from the fund's signer set
+    pub trader: Pubkey,         // Delegated key that may rebalance (default = none)
+    pub operator: Pubkey,       // Delegated key for account cleanup (default = none)
+    pub max_trade_amount: u64,  // Largest token_in_amount the trader may swap at once (0 = no cap)
+    pub max_trade_bps: u16,     // Largest single trade as a share of NAV (0 = no limit)
+    pub max_daily_turnover_bps: u16, // Most traded over a rolling day as a share of NAV (0 = no limit)
+    pub max_position_bps: u16,  // Largest position cost basis as a share of NAV (0 = no limit)
+    pub turnover: u64,          // Lamports traded recently, decaying over TURNOVER_WINDOW
+    pub turnover_updated_at: i64, // Unix timestamp `turnover` was last brought up to date
+    pub fund_index: u64,        // Position among the creator's funds; seeds the PDA after the first
+    pub _reserved: [u8; 88],    // Zeroed space for future fields
+}
+
+/// A fresh, active fund on the current layout with every other field zeroed, for tests
+/// to override with `Fund { total_assets, ..Fund::default() }`
+#[cfg(any(test, feature = "test-utils"))]
+impl Default for Fund {
+    fn default() -> Self {
+        Fund {
+            authority: Pubkey::default(),
+            name: String::new(),
+            description: String::new(),
+            performance_fee: 0,
+            min_deposit: 0,
+            fund_mode: FundMode::Manual,
+            total_shares: 0,
+            total_assets: 0,
+            investor_count: 0,
+            bump: 0,
+            created_at: 0,
+            last_fee_collection: 0,
+            high_water_mark: NAV_SCALE,
+            status: FundStatus::Active,
+            wind_down_started_at: 0,
+            uri: String::new(),
+            platform_fees: 0,
+            version: FUND_VERSION,
+            pending_fees: 0,
+            fees_claimable_at: 0,
+            flagged: false,
+            fee_period: MIN_FEE_PERIOD,
+            creator: Pubkey::default(),
+            proposal_count: 0,
+            multisig: false,
+            trader: Pubkey::default(),
+            operator: Pubkey::default(),
+            max_trade_amount: 0,
+            max_trade_bps: 0,
+            max_daily_turnover_bps: 0,
+            max_position_bps: 0,
+            turnover: 0,
+            turnover_updated_at: 0,
+            fund_index: 0,
+            _reserved: [0; 88],
+        }
+    }
+}
+
+impl Fund {
+    /// Accounts on an older layout must go through `migrate_fund` first. Answers `true`
+    /// otherwise, so account structs can check it with `constraint = fund.require_current()?`.
+    pub fn require_current(&self) -> Result<bool> {
+        require!(self.version == FUND_VERSION, FundrError::AccountNotMigrated);
+        Ok(true)
+    }
+
+    /// Shares minted for `net_deposit` lamports:
+    /// deposit * (total_shares + VIRTUAL_SHARES) / (total_assets + VIRTUAL_ASSETS), rounded down
+    pub fn shares_for_deposit(&self, net_deposit: u64) -> Result<u64> {
+        math(fundr_math::shares_for_deposit(net_deposit, self.total_assets, self.total_shares))
+    }
+
+    /// Gross lamports owed for redeeming `shares`:
+    /// shares * (total_assets + VIRTUAL_ASSETS) / (total_shares + VIRTUAL_SHARES), rounded down
+    pub fn lamports_for_shares(&self, shares: u64) -> Result<u64> {
+        math(fundr_math::lamports_for_shares(shares, self.total_assets, self.total_shares))
+    }
+
+    /// Shares that must be burned to release `lamports` gross, rounded up
+    pub fn shares_for_lamports(&self, lamports: u64) -> Result<u64> {
+        math(fundr_math::shares_for_lamports(lamports, self.total_assets, self.total_shares))
+    }
+
+    /// Earliest time performance fees can next be crystallized
+    pub fn next_fee_collection(&self) -> i64 {
+        let period = if self.fee_period == 0 { MIN_FEE_PERIOD } else { self.fee_period };
+        self.last_fee_collection.saturating_add(period)
+    }
+
+    /// Owner or delegated trader. A multisig fund's owner has no key of its own to
+    /// trade with, so it only trades through a trader its signers delegated.
+    pub fn can_trade(&self, key: &Pubkey) -> bool {
+        (*key == self.authority && !self.multisig) || (self.trader != Pubkey::default() && *key == self.trader)
+    }
+
+    /// Owner or delegated operator
+    pub fn can_operate(&self, key: &Pubkey) -> bool {
+        *key == self.authority || (self.operator != Pubkey::default() && *key == self.operator)
+    }
+
+    /// Add a trade of `notional` lamports to the rolling turnover and return the new
+    /// total. Earlier trades fade out linearly over `TURNOVER_WINDOW`.
+    pub fn record_turnover(&mut self, notional: u64, now: i64) -> Result<u64> {
+        let elapsed = now.saturating_sub(self.turnover_updated_at).clamp(0, TURNOVER_WINDOW) as u128;
+        let remaining = self.turnover as u128 * (TURNOVER_WINDOW as u128 - elapsed) / TURNOVER_WINDOW as u128;
+        
+        self.turnover = (remaining as u64).checked_add(notional).ok_or(FundrError::MathOverflow)?;
+        self.turnover_updated_at = now;
+        Ok(self.turnover)
+    }
+
+    fn set_risk_limits(&mut self, max_trade_bps: u16, max_daily_turnover_bps: u16, max_position_bps: u16) -> Result<()> {
+        // A day's turnover may exceed NAV, but no single trade or position can
+        require!(
+            max_trade_bps as u64 <= fundr_math::BPS_DENOMINATOR && max_position_bps as u64 <= fundr_math::BPS_DENOMINATOR,
+            FundrError::InvalidRiskLimits
+        );
+        
+        self.max_trade_bps = max_trade_bps;
+        self.max_daily_turnover_bps = max_daily_turnover_bps;
+        self.max_position_bps = max_position_bps;
+        
+        msg!(
+            "Fund {} risk limits: trade {} bps, daily turnover {} bps, position {} bps",
+            self.name,
+            max_trade_bps,
+            max_daily_turnover_bps,
+            max_position_bps
+        );
+        Ok(())
+    }
+
+    /// Hand the fund to `new_authority`. The PDA stays derived from the original manager,
+    /// and the roles the previous owner delegated are revoked.
+    fn transfer_authority(&mut self, new_authority: Pubkey) {
+        self.creator = self.seed_key();
+        self.authority = new_authority;
+        self.trader = Pubkey::default();
+        self.operator = Pubkey::default();
+        self.max_trade_amount = 0;
+    }
+
+    /// Key the fund PDA is derived from. Governance can replace `authority`, but the
+    /// address stays that of the original manager; funds created before `creator` was
+    /// recorded still have their original authority.
+    pub fn seed_key(&self) -> Pubkey {
+        if self.creator == Pubkey::default() { self.authority } else { self.creator }
+    }
+}
+
+/// Index seed of a creator's `index`th fund. The first fund's is empty, so it keeps the
+/// `["fund", manager]` address funds had before creators could open more than one.
+pub fn fund_index_seed(index: u64) -> Vec<u8> {
+    if index == 0 { Vec::new() } else { index.to_le_bytes().to_vec() }
+}
+
+/// Surface an overflow in the shared share/fee math as `MathOverflow`
+fn math<T>(result: fundr_math::MathResult<T>) -> Result<T> {
+    result.map_err(|_| error!(FundrError::MathOverflow))
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
 pub enum FundMode {
     Manual, // Manager manually allocates deposits (SOL accumulates)
     Auto,   // Deposits auto-allocate to current token ratios
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum FundStatus {
+    Active,      // Accepting deposits and trading normally
+    WindingDown, // Closing: no deposits, liquidate to SOL, investors redeemed
+    Paused,      // Halted by investor vote: no deposits or trading, withdrawals still open
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct UserStake {
@@ -512,6 +2614,380 @@ pub struct UserStake {
     pub total_deposited: u64,   // Total amount deposited (for tracking)
     pub last_deposit: i64,      // Last deposit timestamp
     pub last_withdrawal: i64,   // Last withdrawal timestamp
+    pub version: u8,            // Account layout version (USER_STAKE_VERSION)
+    pub snapshot_shares: u64,   // Shares held before the first deposit at `deposit_proposal_count`
+    pub deposit_proposal_count: u64, // Fund's proposal count at the latest deposit
+    pub prior_proposal_count: u64,   // Fund's proposal count at the deposits before those
+    pub _reserved: [u8; 40],    // Zeroed space for future fields
+}
+
+impl UserStake {
+    /// Accounts on an older layout must go through `migrate_stake` first; see
+    /// `Fund::require_current`
+    pub fn require_current(&self) -> Result<bool> {
+        require!(self.version == USER_STAKE_VERSION, FundrError::AccountNotMigrated);
+        Ok(true)
+    }
+
+    /// Note a deposit made once the fund has opened `proposal_count` proposals, before
+    /// its shares are added. Proposals opened earlier must not count them.
+    fn record_deposit(&mut self, proposal_count: u64) {
+        if proposal_count != self.deposit_proposal_count {
+            self.prior_proposal_count = self.deposit_proposal_count;
+            self.snapshot_shares = self.shares;
+            self.deposit_proposal_count = proposal_count;
+        }
+    }
+
+    /// Shares the stake may vote with on proposal `id`: those it held when the proposal
+    /// opened, less any withdrawn since. `None` if deposits since then came in under more
+    /// than one later proposal, which leaves that balance unknown.
+    pub fn voting_shares(&self, id: u64) -> Option<u64> {
+        if id >= self.deposit_proposal_count {
+            Some(self.shares)
+        } else if id >= self.prior_proposal_count {
+            Some(self.shares.min(self.snapshot_shares))
+        } else {
+            None
+        }
+    }
+}
+
+/// Per-creator counter of funds opened: `["manager", creator]`. Never closed, so a closed
+/// fund's address is never handed to a new one.
+#[account]
+#[derive(InitSpace)]
+pub struct ManagerProfile {
+    pub manager: Pubkey,        // Creator whose fund PDAs this indexes
+    pub fund_count: u64,        // Funds opened (or closed) so far; the next fund's index
+    pub bump: u8,               // PDA bump
+}
+
+/// Protocol-wide settings, owned by the protocol admin
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,          // Protocol admin: flags malicious funds and voids their fees
+    pub fee_claim_delay: i64,   // Seconds crystallized fees stay in escrow before they can be claimed
+    pub bump: u8,               // PDA bump
+    pub quorum_bps: u16,        // Share of outstanding shares that must vote for a proposal to count
+    pub approval_threshold_bps: u16, // Share of votes cast that must be in favor
+    pub voting_period: i64,     // Seconds a proposal stays open for voting
+    pub slippage_bps: u16,      // How far below oracle fair value a rebalance may accept
+    pub max_price_age: i64,     // Seconds an oracle price stays usable (0 = DEFAULT_MAX_PRICE_AGE)
+    pub _reserved: [u8; 42],    // Zeroed space for future fields
+}
+
+impl ProtocolConfig {
+    pub fn max_price_age(&self) -> i64 {
+        if self.max_price_age == 0 { DEFAULT_MAX_PRICE_AGE } else { self.max_price_age }
+    }
+}
+
+/// Admin-published oracle price of a token: `["price", mint]`
+#[account]
+#[derive(InitSpace)]
+pub struct PriceFeed {
+    pub mint: Pubkey,           // Token the price is for
+    pub price: u64,             // Lamports per base unit of the token, scaled by PRICE_SCALE
+    pub updated_at: i64,        // Unix timestamp the price was published
+    pub bump: u8,               // PDA bump
+}
+
+/// Change investors can vote through on their fund
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum ProposalAction {
+    ReplaceAuthority { new_authority: Pubkey }, // Hand the fund to a new manager
+    Pause,                                      // Stop deposits and trading
+    Resume,                                     // Lift a pause
+    WindDown,                                   // Start winding the fund down
+}
+
+/// Share-weighted investor proposal: `["proposal", fund, id]`
+#[account]
+#[derive(InitSpace)]
+pub struct Proposal {
+    pub fund: Pubkey,           // Fund the proposal acts on
+    pub proposer: Pubkey,       // Investor who opened it
+    pub id: u64,                // Index among the fund's proposals
+    pub action: ProposalAction, // What executing the proposal does
+    pub snapshot_shares: u64,   // Fund's outstanding shares when opened; quorum is measured against it
+    pub created_at: i64,        // Unix timestamp opened
+    pub voting_ends_at: i64,    // Votes close and execution opens at this time
+    pub quorum_bps: u16,        // Quorum copied from the protocol config
+    pub approval_threshold_bps: u16, // Approval threshold copied from the protocol config
+    pub votes_for: u64,         // Shares voted in favor
+    pub votes_against: u64,     // Shares voted against
+    pub executed: bool,         // Whether the action has been applied
+    pub bump: u8,               // PDA bump
+}
+
+impl Proposal {
+    /// Whether enough of the snapshot voted, and enough of them in favor
+    pub fn passed(&self) -> bool {
+        let votes_for = self.votes_for as u128;
+        let cast = votes_for + self.votes_against as u128;
+        let bps = fundr_math::BPS_DENOMINATOR as u128;
+        
+        cast * bps >= self.snapshot_shares as u128 * self.quorum_bps as u128
+            && votes_for * bps >= cast * self.approval_threshold_bps as u128
+            && votes_for > self.votes_against as u128
+    }
+}
+
+/// M-of-N keys that must approve a fund's sensitive actions: `["signers", fund]`
+#[account]
+#[derive(InitSpace)]
+pub struct SignerSet {
+    pub fund: Pubkey,           // Fund the set controls
+    #[max_len(MAX_SIGNERS)]
+    pub signers: Vec<Pubkey>,   // Keys allowed to propose and approve actions
+    pub threshold: u8,          // Approvals an action needs before it can execute
+    pub nonce: u64,             // Bumped whenever the set changes; stale approvals are void
+    pub action_count: u64,      // Actions proposed so far; seeds the next pending action
+    pub bump: u8,               // PDA bump
+}
+
+impl SignerSet {
+    pub fn index_of(&self, key: &Pubkey) -> Option<usize> {
+        self.signers.iter().position(|signer| signer == key)
+    }
+
+    /// Signers are distinct, at most `MAX_SIGNERS`, and the threshold is reachable
+    fn validate(&self) -> Result<()> {
+        let distinct = self
+            .signers
+            .iter()
+            .enumerate()
+            .all(|(index, signer)| !self.signers[..index].contains(signer));
+        require!(
+            distinct
+                && self.signers.len() <= MAX_SIGNERS
+                && self.threshold >= 1
+                && self.threshold as usize <= self.signers.len(),
+            FundrError::InvalidSignerSet
+        );
+        Ok(())
+    }
+}
+
+/// Sensitive change to a fund that its signer set must approve
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum ManagerAction {
+    CollectFees,                                          // Crystallize performance fees
+    SetFundMode { mode: FundMode },                       // Switch manual/auto allocation
+    SetPosition { mint: Pubkey, target_weight_bps: u16 }, // Add or reweight a position
+    RemovePosition { mint: Pubkey },                      // Drop a position
+    SetAuthority { new_authority: Pubkey },               // Hand the fund to a new manager
+    AddSigner { signer: Pubkey },                         // Grow the signer set
+    RemoveSigner { signer: Pubkey },                      // Shrink the signer set
+    SetThreshold { threshold: u8 },                       // Change the approvals required
+    SetTrader { trader: Pubkey, max_trade_amount: u64 },  // Delegate or revoke trading
+    SetOperator { operator: Pubkey },                     // Delegate or revoke cleanup
+    SetRiskLimits { max_trade_bps: u16, max_daily_turnover_bps: u16, max_position_bps: u16 }, // Change risk limits
+    WindDown,                                             // Start winding the fund down
+    ClaimFees,                                            // Let the manager claim escrowed fees
+    CloseFund,                                            // Let the manager close the wound-down fund
+}
+
+/// Action awaiting approval by a fund's signer set: `["action", fund, id]`
+#[account]
+#[derive(InitSpace)]
+pub struct PendingAction {
+    pub fund: Pubkey,           // Fund the action applies to
+    pub id: u64,                // Index among the fund's actions
+    pub action: ManagerAction,  // What executing the action does
+    pub proposer: Pubkey,       // Signer who proposed it
+    pub approvals: u16,         // Bitmap of approving signers, by index in the signer set
+    pub signer_nonce: u64,      // Signer set nonce the approvals were given under
+    pub created_at: i64,        // Unix timestamp proposed
+    pub executed: bool,         // Whether the action has been applied
+    pub bump: u8,               // PDA bump
+}
+
+/// One investor's vote on a proposal: `["vote", proposal, voter]`
+#[account]
+#[derive(InitSpace)]
+pub struct VoteRecord {
+    pub proposal: Pubkey,       // Proposal voted on
+    pub voter: Pubkey,          // Investor who voted
+    pub weight: u64,            // Shares counted
+    pub support: bool,          // For or against
+    pub bump: u8,               // PDA bump
+}
+
+/// Holdings of a fund, kept in a fixed-size zero-copy account next to `Fund`. Handlers
+/// read positions in place instead of deserializing them, and deposits and withdrawals
+/// never load the portfolio at all, so their cost does not grow with the holdings.
+#[account(zero_copy)]
+pub struct Portfolio {
+    pub fund: Pubkey,           // Fund this portfolio belongs to
+    pub position_count: u8,     // Number of entries in use at the front of `positions`
+    pub bump: u8,               // PDA bump
+    pub _padding: [u8; 6],
+    pub positions: [Position; MAX_POSITIONS],
+}
+
+#[zero_copy]
+pub struct Position {
+    pub mint: Pubkey,               // Token held
+    pub last_rebalanced_at: i64,    // Unix timestamp of the last trade into this token
+    pub cost_basis: u64,            // Lamports spent buying this token, less lamports received selling it
+    pub target_weight_bps: u16,     // Target share of the fund in basis points
+    pub _padding: [u8; 6],
+}
+
+impl Portfolio {
+    /// Positions currently held
+    pub fn positions(&self) -> &[Position] {
+        &self.positions[..self.position_count as usize]
+    }
+
+    pub fn position_index(&self, mint: &Pubkey) -> Option<usize> {
+        self.positions().iter().position(|position| position.mint == *mint)
+    }
+
+    /// Sum of all target weights in basis points
+    pub fn total_target_bps(&self) -> u64 {
+        self.positions().iter().map(|position| position.target_weight_bps as u64).sum()
+    }
+
+    /// Add a position or update its target weight, keeping the targets within 100%.
+    /// Whatever is left over stays in SOL.
+    fn set_position(&mut self, mint: Pubkey, target_weight_bps: u16) -> Result<()> {
+        match self.position_index(&mint) {
+            Some(index) => self.positions[index].target_weight_bps = target_weight_bps,
+            None => self.add_position(mint, target_weight_bps)?,
+        }
+        
+        require!(
+            self.total_target_bps() <= fundr_math::BPS_DENOMINATOR,
+            FundrError::InvalidAllocation
+        );
+        Ok(())
+    }
+
+    fn add_position(&mut self, mint: Pubkey, target_weight_bps: u16) -> Result<()> {
+        let count = self.position_count as usize;
+        require!(count < MAX_POSITIONS, FundrError::PortfolioFull);
+
+        self.positions[count] = Position {
+            mint,
+            last_rebalanced_at: 0,
+            cost_basis: 0,
+            target_weight_bps,
+            _padding: [0; 6],
+        };
+        self.position_count += 1;
+        Ok(())
+    }
+
+    /// Remove a position by moving the last one into its slot; order is not preserved
+    fn remove_position(&mut self, mint: &Pubkey) -> Result<()> {
+        let index = self.position_index(mint).ok_or(FundrError::PositionNotFound)?;
+        let last = self.position_count as usize - 1;
+
+        self.positions[index] = self.positions[last];
+        self.positions[last] = Zeroable::zeroed();
+        self.position_count -= 1;
+        Ok(())
+    }
+}
+
+#[event]
+pub struct FeesCrystallized {
+    pub fund: Pubkey,
+    pub amount: u64,
+    pub pending_fees: u64,
+    pub claimable_at: i64,
+}
+
+#[event]
+pub struct FeesClaimed {
+    pub fund: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FundFlagUpdated {
+    pub fund: Pubkey,
+    pub flagged: bool,
+}
+
+#[event]
+pub struct FeesVoided {
+    pub fund: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ProposalCreated {
+    pub fund: Pubkey,
+    pub proposal: Pubkey,
+    pub proposer: Pubkey,
+    pub action: ProposalAction,
+    pub voting_ends_at: i64,
+}
+
+#[event]
+pub struct VoteCast {
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub weight: u64,
+    pub support: bool,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    pub fund: Pubkey,
+    pub proposal: Pubkey,
+    pub action: ProposalAction,
+}
+
+#[event]
+pub struct ActionProposed {
+    pub fund: Pubkey,
+    pub pending_action: Pubkey,
+    pub proposer: Pubkey,
+    pub action: ManagerAction,
+}
+
+#[event]
+pub struct ActionExecuted {
+    pub fund: Pubkey,
+    pub pending_action: Pubkey,
+    pub action: ManagerAction,
+}
+
+#[event]
+pub struct RolesUpdated {
+    pub fund: Pubkey,
+    pub trader: Pubkey,
+    pub operator: Pubkey,
+    pub max_trade_amount: u64,
+}
+
+#[event]
+pub struct AssetsSynced {
+    pub fund: Pubkey,
+    pub previous_total_assets: u64,
+    pub new_total_assets: u64,
+    pub delta: i64,
+}
+
+#[event]
+pub struct PricePublished {
+    pub mint: Pubkey,
+    pub price: u64,
+    pub published_at: i64,
+}
+
+#[event]
+pub struct TokenAccountsClosed {
+    pub fund: Pubkey,
+    pub accounts_closed: u32,
+    pub rent_reclaimed: u64,
+    pub accounts_skipped: Vec<Pubkey>,
 }
 
 #[error_code]
@@ -538,4 +3014,209 @@ pub enum FundrError {
     InvalidAccount,
     #[msg("Performance fee exceeds 20% maximum")]
     ExcessiveFees,
-}
\ No newline at end of file
+    #[msg("Fund is not active")]
+    FundNotActive,
+    #[msg("Fund is not winding down")]
+    FundNotWindingDown,
+    #[msg("Fund still has outstanding shares")]
+    FundHasOutstandingShares,
+    #[msg("Positions can only be swapped back to SOL during wind-down")]
+    InvalidWindDownSwap,
+    #[msg("Fund name exceeds 50 characters")]
+    NameTooLong,
+    #[msg("Fund description exceeds 200 characters")]
+    DescriptionTooLong,
+    #[msg("Metadata URI exceeds 200 characters")]
+    UriTooLong,
+    #[msg("Fund name cannot be empty")]
+    NameEmpty,
+    #[msg("Minimum deposit must be greater than zero")]
+    InvalidMinDeposit,
+    #[msg("Fund vault is already in use")]
+    VaultNotFresh,
+    #[msg("Initial seed deposit is below the required minimum")]
+    InsufficientSeedDeposit,
+    #[msg("Deposit is too small to mint any shares")]
+    ZeroSharesMinted,
+    #[msg("Portfolio already holds the maximum number of positions")]
+    PortfolioFull,
+    #[msg("Token is not a position in the fund's portfolio")]
+    PositionNotFound,
+    #[msg("Position target weights exceed 100%")]
+    InvalidAllocation,
+    #[msg("Account already uses the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout version is not supported by this program")]
+    UnsupportedAccountVersion,
+    #[msg("Fee claim delay is outside the allowed range")]
+    InvalidFeeClaimDelay,
+    #[msg("Pending fees are still in their claim delay")]
+    FeesLocked,
+    #[msg("Fund has been flagged by the protocol admin")]
+    FundFlagged,
+    #[msg("Fund has not been flagged by the protocol admin")]
+    FundNotFlagged,
+    #[msg("Fund still has pending fees")]
+    PendingFeesOutstanding,
+    #[msg("Performance fees were crystallized too recently")]
+    FeeCooldown,
+    #[msg("Fee period is outside the allowed range")]
+    InvalidFeePeriod,
+    #[msg("Fund is paused")]
+    FundPaused,
+    #[msg("Fund is not paused")]
+    FundNotPaused,
+    #[msg("Governance parameters are outside the allowed range")]
+    InvalidGovernanceParams,
+    #[msg("Proposal would not change anything")]
+    InvalidProposal,
+    #[msg("Signer holds no shares in this fund")]
+    NoVotingPower,
+    #[msg("Shares were deposited after the proposal snapshot")]
+    SharesAcquiredAfterSnapshot,
+    #[msg("Voting on this proposal has ended")]
+    VotingClosed,
+    #[msg("Voting on this proposal is still open")]
+    VotingOpen,
+    #[msg("Proposal did not reach quorum and approval")]
+    ProposalNotPassed,
+    #[msg("Proposal has already been executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Signer set must have distinct signers and a reachable threshold")]
+    InvalidSignerSet,
+    #[msg("Signer is not in the fund's signer set")]
+    NotASigner,
+    #[msg("Signer has already approved this action")]
+    AlreadyApproved,
+    #[msg("Action does not have enough approvals")]
+    ActionNotApproved,
+    #[msg("Signer set changed since this action was proposed")]
+    StaleAction,
+    #[msg("Action has already been executed")]
+    ActionAlreadyExecuted,
+    #[msg("Fund is managed by a signer set; propose this as an action")]
+    MultisigRequired,
+    #[msg("An account this action needs was not provided")]
+    MissingActionAccount,
+    #[msg("Trade exceeds the trader's per-trade limit")]
+    TradeTooLarge,
+    #[msg("Risk limits are outside the allowed range")]
+    InvalidRiskLimits,
+    #[msg("Trades must swap between SOL and another token")]
+    InvalidSwapPair,
+    #[msg("Trade is larger than the fund's per-trade share of NAV")]
+    TradeExceedsNavLimit,
+    #[msg("Trade would exceed the fund's daily turnover limit")]
+    DailyTurnoverExceeded,
+    #[msg("Trade would exceed the fund's position concentration limit")]
+    PositionConcentrationExceeded,
+    #[msg("Slippage tolerance or price age is outside the allowed range")]
+    InvalidOracleParams,
+    #[msg("Oracle price must be greater than zero")]
+    InvalidPrice,
+    #[msg("Price feed is not for the traded token")]
+    InvalidPriceFeed,
+    #[msg("Oracle price is too old")]
+    StalePrice,
+    #[msg("Minimum output is below the oracle fair value less slippage")]
+    MinimumOutputBelowFairValue,
+    #[msg("Fund is no longer managed by a signer set")]
+    MultisigDisabled,
+    #[msg("Token is still a position in the fund's portfolio")]
+    PositionStillHeld,
+    #[msg("Account uses an older layout and must be migrated first")]
+    AccountNotMigrated,
+    #[msg("Fund holds token positions that can't be valued")]
+    PositionsNotValued,
+    #[msg("Action is carried out by the manager's own instruction")]
+    ExecutedByManager,
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn first_deposit_keeps_one_lamport_per_million_shares() {
+        let fund = Fund::default();
+        assert_eq!(fund.shares_for_deposit(1).unwrap(), 1_000_000);
+        assert_eq!(fund.shares_for_deposit(990_000_000).unwrap(), 990_000_000_000_000);
+    }
+
+    #[test]
+    fn turnover_fades_out_over_a_day() {
+        let mut fund = Fund::default();
+        assert_eq!(fund.record_turnover(1_000, 0).unwrap(), 1_000);
+        assert_eq!(fund.record_turnover(0, TURNOVER_WINDOW / 4).unwrap(), 750);
+        assert_eq!(fund.record_turnover(100, TURNOVER_WINDOW / 4).unwrap(), 850);
+        assert_eq!(fund.record_turnover(0, 2 * TURNOVER_WINDOW).unwrap(), 0);
+    }
+
+    proptest! {
+        // Without outside donations the share price can only drift in the fund's favor by
+        // rounding, so every deposit of at least one lamport must mint shares. Deposits stay
+        // small enough that 64 of them can't overflow the share supply.
+        #[test]
+        fn deposits_never_round_to_zero(
+            ops in prop::collection::vec((any::<bool>(), 1u64..100_000_000_000, 1u64..=100), 1..64),
+        ) {
+            let mut fund = Fund::default();
+            let mut holdings: Vec<u64> = Vec::new();
+
+            for (is_deposit, amount, percent) in ops {
+                if is_deposit || holdings.is_empty() {
+                    let shares = fund.shares_for_deposit(amount).unwrap();
+                    prop_assert!(shares > 0);
+                    fund.total_shares += shares;
+                    fund.total_assets += amount;
+                    holdings.push(shares);
+                } else {
+                    let index = (amount as usize) % holdings.len();
+                    let shares = holdings[index] / 100 * percent;
+                    let lamports = fund.lamports_for_shares(shares).unwrap();
+                    fund.total_shares -= shares;
+                    fund.total_assets -= lamports;
+                    holdings[index] -= shares;
+                }
+            }
+        }
+
+        // Burning the rounded-up share count must always release at least the gross requested
+        #[test]
+        fn exact_withdrawal_burns_enough_shares(
+            total_assets in 1u64..1_000_000_000_000,
+            shares_per_lamport in 1u64..10_000_000,
+            lamports in 1u64..1_000_000_000_000,
+        ) {
+            let fund = Fund { total_assets, total_shares: total_assets * shares_per_lamport, ..Fund::default() };
+            prop_assume!(lamports <= total_assets);
+
+            let shares = fund.shares_for_lamports(lamports).unwrap();
+            prop_assert!(fund.lamports_for_shares(shares).unwrap() >= lamports);
+        }
+
+        // Classic inflation attack: deposit, donate straight to the vault, wait for a victim,
+        // then exit. The attacker must never walk away with more than they put in.
+        #[test]
+        fn donation_attack_is_unprofitable(
+            attacker_deposit in 1u64..1_000_000_000,
+            donation in 0u64..10_000_000_000_000,
+            victim_deposit in 1u64..1_000_000_000_000,
+        ) {
+            let mut fund = Fund::default();
+
+            let attacker_shares = fund.shares_for_deposit(attacker_deposit).unwrap();
+            fund.total_shares += attacker_shares;
+            fund.total_assets += attacker_deposit + donation;
+
+            let victim_shares = fund.shares_for_deposit(victim_deposit).unwrap();
+            if victim_shares > 0 {
+                fund.total_shares += victim_shares;
+                fund.total_assets += victim_deposit;
+            }
+
+            let attacker_out = fund.lamports_for_shares(attacker_shares).unwrap();
+            prop_assert!(attacker_out <= attacker_deposit + donation);
+        }
+    }
+}
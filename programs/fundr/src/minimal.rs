@@ -0,0 +1,133 @@
+//! Lightweight native entrypoint for size-constrained deploys.
+//!
+//! Building with `--features minimal` drops Anchor's generated entrypoint, IDL
+//! instructions and instruction-name logs, and installs `process_instruction` instead.
+//! It only accepts the discriminators of Fundr's own instructions and hands them to the
+//! same Anchor handlers, so a minimal deploy behaves exactly like the full program.
+
+use crate::instruction;
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::Discriminator;
+
+#[cfg(feature = "minimal")]
+anchor_lang::solana_program::entrypoint!(process_instruction);
+
+/// Every instruction the program handles, by name and Anchor discriminator
+pub const INSTRUCTIONS: &[(&str, [u8; 8])] = &[
+    ("initialize_fund", instruction::InitializeFund::DISCRIMINATOR),
+    ("deposit", instruction::Deposit::DISCRIMINATOR),
+    ("withdraw", instruction::Withdraw::DISCRIMINATOR),
+    ("withdraw_exact", instruction::WithdrawExact::DISCRIMINATOR),
+    ("rebalance", instruction::Rebalance::DISCRIMINATOR),
+    ("collect_fees", instruction::CollectFees::DISCRIMINATOR),
+    ("update_fund_mode", instruction::UpdateFundMode::DISCRIMINATOR),
+    ("reclaim_rent", instruction::ReclaimRent::DISCRIMINATOR),
+    ("close_stake", instruction::CloseStake::DISCRIMINATOR),
+    ("close_token_account", instruction::CloseTokenAccount::DISCRIMINATOR),
+    ("close_token_accounts", instruction::CloseTokenAccounts::DISCRIMINATOR),
+    ("update_fund_metadata", instruction::UpdateFundMetadata::DISCRIMINATOR),
+    ("sync_assets", instruction::SyncAssets::DISCRIMINATOR),
+    ("initiate_wind_down", instruction::InitiateWindDown::DISCRIMINATOR),
+    ("force_distribute", instruction::ForceDistribute::DISCRIMINATOR),
+    ("close_fund", instruction::CloseFund::DISCRIMINATOR),
+    ("initialize_portfolio", instruction::InitializePortfolio::DISCRIMINATOR),
+    ("set_position", instruction::SetPosition::DISCRIMINATOR),
+    ("remove_position", instruction::RemovePosition::DISCRIMINATOR),
+    ("migrate_fund", instruction::MigrateFund::DISCRIMINATOR),
+    ("migrate_stake", instruction::MigrateStake::DISCRIMINATOR),
+    ("claim_fees", instruction::ClaimFees::DISCRIMINATOR),
+    ("initialize_config", instruction::InitializeConfig::DISCRIMINATOR),
+    ("update_config", instruction::UpdateConfig::DISCRIMINATOR),
+    ("set_fund_flag", instruction::SetFundFlag::DISCRIMINATOR),
+    ("void_fees", instruction::VoidFees::DISCRIMINATOR),
+    ("crank_fees", instruction::CrankFees::DISCRIMINATOR),
+    ("set_governance_params", instruction::SetGovernanceParams::DISCRIMINATOR),
+    ("create_proposal", instruction::CreateProposal::DISCRIMINATOR),
+    ("cast_vote", instruction::CastVote::DISCRIMINATOR),
+    ("execute_proposal", instruction::ExecuteProposal::DISCRIMINATOR),
+    ("create_signer_set", instruction::CreateSignerSet::DISCRIMINATOR),
+    ("propose_action", instruction::ProposeAction::DISCRIMINATOR),
+    ("approve_action", instruction::ApproveAction::DISCRIMINATOR),
+    ("execute_action", instruction::ExecuteAction::DISCRIMINATOR),
+    ("set_trader", instruction::SetTrader::DISCRIMINATOR),
+    ("set_operator", instruction::SetOperator::DISCRIMINATOR),
+    ("set_risk_limits", instruction::SetRiskLimits::DISCRIMINATOR),
+    ("set_oracle_params", instruction::SetOracleParams::DISCRIMINATOR),
+    ("publish_price", instruction::PublishPrice::DISCRIMINATOR),
+];
+
+/// Dispatch a known Fundr instruction to its Anchor handler; reject anything else
+/// with the same errors Anchor's own dispatcher uses
+pub fn process_instruction<'info>(
+    program_id: &Pubkey,
+    accounts: &'info [AccountInfo<'info>],
+    data: &[u8],
+) -> ProgramResult {
+    let discriminator = data
+        .get(..8)
+        .ok_or_else(|| ProgramError::from(Error::from(ErrorCode::InstructionMissing)))?;
+
+    if !INSTRUCTIONS.iter().any(|(_, known)| known[..] == *discriminator) {
+        msg!("Unknown instruction rejected by minimal entrypoint");
+        return Err(Error::from(ErrorCode::InstructionFallbackNotFound).into());
+    }
+
+    crate::entry(program_id, accounts, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::hash::hash;
+
+    #[test]
+    fn discriminators_match_instruction_names() {
+        for (name, discriminator) in INSTRUCTIONS {
+            let expected = hash(format!("global:{name}").as_bytes()).to_bytes();
+            assert_eq!(discriminator[..], expected[..8], "{name}");
+        }
+    }
+
+    #[test]
+    fn every_program_instruction_is_listed_once() {
+        // Handlers are the `pub fn`s of the `#[program]` module, so a new instruction
+        // can't be left out of the minimal build unnoticed
+        let source = include_str!("lib.rs");
+        let start = source.find("#[program]").unwrap();
+        let end = start + source[start..].find("\n}\n").unwrap();
+        let mut handlers: Vec<&str> = source[start..end]
+            .lines()
+            .filter_map(|line| line.strip_prefix("    pub fn "))
+            .map(|rest| rest.split(['<', '(']).next().unwrap())
+            .collect();
+        let mut listed: Vec<&str> = INSTRUCTIONS.iter().map(|(name, _)| *name).collect();
+        handlers.sort_unstable();
+        listed.sort_unstable();
+        assert_eq!(listed, handlers);
+
+        let mut discriminators: Vec<[u8; 8]> = INSTRUCTIONS.iter().map(|(_, discriminator)| *discriminator).collect();
+        discriminators.sort_unstable();
+        discriminators.dedup();
+        assert_eq!(discriminators.len(), INSTRUCTIONS.len());
+    }
+
+    #[test]
+    fn unknown_and_truncated_instructions_are_rejected() {
+        let missing: ProgramError = Error::from(ErrorCode::InstructionMissing).into();
+        let unknown: ProgramError = Error::from(ErrorCode::InstructionFallbackNotFound).into();
+
+        assert_eq!(process_instruction(&crate::ID, &[], &[1, 2, 3]), Err(missing));
+        assert_eq!(process_instruction(&crate::ID, &[], &[0; 8]), Err(unknown));
+    }
+
+    #[test]
+    fn known_instructions_reach_the_anchor_handlers() {
+        // With no accounts the handler's account validation is what fails
+        let not_enough_keys: ProgramError = Error::from(ErrorCode::AccountNotEnoughKeys).into();
+        let data = instruction::SyncAssets::DISCRIMINATOR;
+
+        assert_eq!(process_instruction(&crate::ID, &[], &data), Err(not_enough_keys));
+    }
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::rent::Rent;
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use fundr::FundrError;
+
+#[test]
+fn reclaim_rent_returns_an_emptied_stake_to_its_investor() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    let stake = pda::stake(&fund, &investor).0;
+    let shares = bank.stake(&stake).shares;
+    bank.process(&instructions::withdraw(&fund, &investor, shares, 0)).unwrap();
+    let balance = bank.lamports(&investor);
+    let rent = bank.lamports(&stake);
+
+    bank.process(&instructions::reclaim_rent(&fund, &manager, &investor)).unwrap();
+
+    assert!(bank.account(&stake).is_none());
+    assert_eq!(bank.lamports(&investor), balance + rent);
+}
+
+#[test]
+fn reclaim_rent_rejects_a_stake_with_shares() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+
+    let result = bank.process(&instructions::reclaim_rent(&fund, &manager, &investor));
+
+    assert_fundr_error(result, FundrError::AccountNotEmpty);
+}
+
+#[test]
+fn reclaim_rent_rejects_other_signers() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+    bank.process(&instructions::withdraw(&fund, &investor, shares, 0)).unwrap();
+
+    let result = bank.process(&instructions::reclaim_rent(&fund, &investor, &investor));
+
+    assert_fundr_error(result, FundrError::UnauthorizedManager);
+}
+
+#[test]
+fn reclaim_rent_only_pays_the_stake_owner() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+    bank.process(&instructions::withdraw(&fund, &investor, shares, 0)).unwrap();
+
+    let mut ix = instructions::reclaim_rent(&fund, &manager, &investor);
+    ix.accounts[3].pubkey = manager;
+
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAccount);
+}
+
+#[test]
+fn reclaim_rent_rejects_stakes_of_other_funds() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let (_, other_fund) = create_fund(&mut bank, 0);
+    let investor = bank.wallet(SOL);
+    bank.process(&instructions::deposit(&other_fund, &investor, SOL / 2, 0)).unwrap();
+    let shares = bank.stake(&pda::stake(&other_fund, &investor).0).shares;
+    bank.process(&instructions::withdraw(&other_fund, &investor, shares, 0)).unwrap();
+
+    let mut ix = instructions::reclaim_rent(&fund, &manager, &investor);
+    ix.accounts[2].pubkey = pda::stake(&other_fund, &investor).0;
+
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAccount);
+}
+
+#[test]
+fn close_token_account_returns_rent_to_the_vault() {
+    for token_program in [spl_token::ID, spl_token_2022::ID] {
+        let mut bank = TestBank::new();
+        let (manager, fund) = create_fund(&mut bank, SOL);
+        let mint = bank.create_mint(&token_program);
+        let token_account = bank.create_token_account(&token_program, &mint, &fund, 0);
+        let rent = bank.lamports(&token_account);
+        let vault = bank.lamports(&pda::vault(&fund).0);
+
+        bank.process(&instructions::close_token_account(&fund, &manager, &token_account, &token_program))
+            .unwrap();
+
+        assert!(bank.account(&token_account).is_none());
+        assert_eq!(bank.lamports(&pda::vault(&fund).0), vault + rent);
+    }
+}
+
+#[test]
+fn close_token_account_rejects_invalid_accounts() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+
+    let funded = bank.create_token_account(&spl_token::ID, &mint, &fund, 1);
+    let ix = instructions::close_token_account(&fund, &manager, &funded, &spl_token::ID);
+    assert_fundr_error(bank.process(&ix), FundrError::TokenAccountNotEmpty);
+
+    let foreign = bank.create_token_account(&spl_token::ID, &mint, &manager, 0);
+    let ix = instructions::close_token_account(&fund, &manager, &foreign, &spl_token::ID);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAccount);
+
+    let empty = bank.create_token_account(&spl_token::ID, &mint, &fund, 0);
+    let attacker = bank.wallet(SOL);
+    let ix = instructions::close_token_account(&fund, &attacker, &empty, &spl_token::ID);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+    assert!(bank.account(&empty).is_some());
+}
+
+#[test]
+fn close_token_accounts_skips_accounts_holding_tokens() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    let empty = [
+        bank.create_token_account(&spl_token::ID, &mint, &fund, 0),
+        bank.create_token_account(&spl_token::ID, &mint, &fund, 0),
+    ];
+    let funded = bank.create_token_account(&spl_token::ID, &mint, &fund, 5);
+    let vault = bank.lamports(&pda::vault(&fund).0);
+
+    let ix = instructions::close_token_accounts(&fund, &manager, &spl_token::ID, &[empty[0], funded, empty[1]]);
+    bank.process(&ix).unwrap();
+
+    assert!(empty.iter().all(|account| bank.account(account).is_none()));
+    assert!(bank.account(&funded).is_some());
+    let rent = Rent::default().minimum_balance(spl_token::state::Account::LEN);
+    assert_eq!(bank.lamports(&pda::vault(&fund).0), vault + 2 * rent);
+}
+
+#[test]
+fn close_token_accounts_rejects_accounts_owned_by_others() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    let empty = bank.create_token_account(&spl_token::ID, &mint, &fund, 0);
+    let foreign = bank.create_token_account(&spl_token::ID, &mint, &manager, 0);
+
+    let ix = instructions::close_token_accounts(&fund, &manager, &spl_token::ID, &[empty, foreign]);
+
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAccount);
+    assert!(bank.account(&empty).is_some());
+}
+
+#[test]
+fn token_accounts_of_portfolio_positions_stay_open() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let held = bank.create_mint(&spl_token::ID);
+    let dropped = bank.create_mint(&spl_token::ID);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &held, 5_000)).unwrap();
+    let position = bank.create_token_account(&spl_token::ID, &held, &fund, 0);
+    let leftover = bank.create_token_account(&spl_token::ID, &dropped, &fund, 0);
+
+    let ix = instructions::close_token_account(&fund, &manager, &position, &spl_token::ID);
+    assert_fundr_error(bank.process(&ix), FundrError::PositionStillHeld);
+    // A batch cleanup just passes over it
+    let ix = instructions::close_token_accounts(&fund, &manager, &spl_token::ID, &[position, leftover]);
+    bank.process(&ix).unwrap();
+    assert!(bank.account(&leftover).is_none());
+    assert!(bank.account(&position).is_some());
+
+    // Once the position is removed its account can go
+    bank.process(&instructions::remove_position(&fund, &manager, &held)).unwrap();
+    bank.process(&instructions::close_token_accounts(&fund, &manager, &spl_token::ID, &[position])).unwrap();
+    assert!(bank.account(&position).is_none());
+}
@@ -0,0 +1,635 @@
+//! In-process harness for the Fundr integration tests.
+//!
+//! Runs the program natively: accounts are serialized into the same input buffer the
+//! loader hands an SBF program, so `AccountInfo::realloc` and `assign` behave as they do
+//! on-chain, and CPIs to the system and token programs are executed through syscall stubs.
+//! Everything stays in this process, so the suite runs offline with no validator.
+//!
+//! After each instruction the bank enforces the runtime's account rules: read-only
+//! accounts are unchanged, accounts the program doesn't own are neither debited nor
+//! written except through a CPI, writable accounts stay rent-exempt and no lamports are
+//! created or destroyed. CPIs can't escalate signer or writable privileges. Accounts
+//! passed writable to a CPI are left to the invoked program's own checks, so a direct
+//! write the program makes to one of them isn't caught. Compute units, transaction size
+//! and account locks are not modelled.
+
+#![allow(dead_code, unused_imports)]
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::mem::size_of;
+use std::sync::Once;
+
+use anchor_lang::prelude::{AccountInfo, ProgramError, Pubkey};
+use anchor_lang::solana_program::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::entrypoint::{
+    self, ProgramResult, BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER,
+};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::program_stubs::{self, SyscallStubs};
+use anchor_lang::solana_program::rent::Rent;
+use anchor_lang::solana_program::system_instruction::SystemError;
+use anchor_lang::{system_program, AccountDeserialize};
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::{TransferFee, TransferFeeConfig};
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    BaseStateWithExtensions, BaseStateWithExtensionsMut, ExtensionType, StateWithExtensions, StateWithExtensionsMut,
+};
+use fundr::{
+    Fund, FundMode, FundrError, ManagerProfile, Portfolio, PriceFeed, Proposal, ProtocolConfig, SignerSet, UserStake,
+    MIN_FEE_CLAIM_DELAY, MIN_FEE_PERIOD, PRICE_SCALE,
+};
+use fundr_client::instructions::InitializeFundArgs;
+
+pub use fundr_client::{instructions, pda, preview};
+
+/// 1 SOL
+pub const SOL: u64 = 1_000_000_000;
+
+/// Upgrade authority of the deployed program, allowed to create the protocol config
+pub const UPGRADE_AUTHORITY: Pubkey = Pubkey::new_from_array([7; 32]);
+
+thread_local! {
+    // Tests run on parallel threads, and each `TestBank` owns its thread's clock
+    static UNIX_TIMESTAMP: Cell<i64> = const { Cell::new(1_700_000_000) };
+    // Accounts the current instruction passed writable to a CPI
+    static CPI_WRITES: RefCell<HashSet<Pubkey>> = RefCell::new(HashSet::new());
+}
+
+/// State of a single account between instructions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestAccount {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+/// A minimal bank: account storage plus instruction execution against `fundr::entry`
+pub struct TestBank {
+    accounts: HashMap<Pubkey, TestAccount>,
+}
+
+impl TestBank {
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            program_stubs::set_syscall_stubs(Box::new(Stubs));
+        });
+        UNIX_TIMESTAMP.with(|timestamp| timestamp.set(1_700_000_000));
+
+        let mut bank = Self { accounts: HashMap::new() };
+        for program_id in [system_program::ID, spl_token::ID, spl_token_2022::ID] {
+            bank.set_account(
+                program_id,
+                TestAccount { lamports: 1, data: Vec::new(), owner: Pubkey::default(), executable: true },
+            );
+        }
+
+        // Deploy fundr as an upgradeable program so its upgrade authority can be checked
+        let programdata_address = pda::program_data();
+        let program = UpgradeableLoaderState::Program { programdata_address };
+        bank.set_account(
+            fundr::ID,
+            TestAccount {
+                lamports: 1,
+                data: bincode::serialize(&program).unwrap(),
+                owner: bpf_loader_upgradeable::ID,
+                executable: true,
+            },
+        );
+        let programdata = UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(UPGRADE_AUTHORITY),
+        };
+        bank.set_account(
+            programdata_address,
+            TestAccount {
+                lamports: 1,
+                data: bincode::serialize(&programdata).unwrap(),
+                owner: bpf_loader_upgradeable::ID,
+                executable: false,
+            },
+        );
+        bank.airdrop(&UPGRADE_AUTHORITY, SOL);
+        bank
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: TestAccount) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn remove_account(&mut self, key: &Pubkey) {
+        self.accounts.remove(key);
+    }
+
+    pub fn account(&self, key: &Pubkey) -> Option<&TestAccount> {
+        self.accounts.get(key)
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    /// Credit `lamports` to a (system-owned, if new) account
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        self.accounts
+            .entry(*key)
+            .or_insert_with(|| TestAccount {
+                lamports: 0,
+                data: Vec::new(),
+                owner: system_program::ID,
+                executable: false,
+            })
+            .lamports += lamports;
+    }
+
+    /// A new wallet holding `lamports`
+    pub fn wallet(&mut self, lamports: u64) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.airdrop(&key, lamports);
+        key
+    }
+
+    pub fn fund(&self, key: &Pubkey) -> Fund {
+        let account = self.accounts.get(key).expect("fund account missing");
+        Fund::try_deserialize(&mut &account.data[..]).expect("invalid fund account")
+    }
+
+    pub fn stake(&self, key: &Pubkey) -> UserStake {
+        let account = self.accounts.get(key).expect("stake account missing");
+        UserStake::try_deserialize(&mut &account.data[..]).expect("invalid stake account")
+    }
+
+    pub fn portfolio(&self, key: &Pubkey) -> Portfolio {
+        let account = self.accounts.get(key).expect("portfolio account missing");
+        Portfolio::try_deserialize(&mut &account.data[..]).expect("invalid portfolio account")
+    }
+
+    pub fn proposal(&self, key: &Pubkey) -> Proposal {
+        let account = self.accounts.get(key).expect("proposal account missing");
+        Proposal::try_deserialize(&mut &account.data[..]).expect("invalid proposal account")
+    }
+
+    pub fn signer_set(&self, fund: &Pubkey) -> SignerSet {
+        let account = self.accounts.get(&pda::signer_set(fund).0).expect("signer set missing");
+        SignerSet::try_deserialize(&mut &account.data[..]).expect("invalid signer set account")
+    }
+
+    pub fn manager_profile(&self, manager: &Pubkey) -> ManagerProfile {
+        let account = self.accounts.get(&pda::manager_profile(manager).0).expect("manager profile missing");
+        ManagerProfile::try_deserialize(&mut &account.data[..]).expect("invalid manager profile account")
+    }
+
+    pub fn config(&self) -> ProtocolConfig {
+        let account = self.accounts.get(&pda::config().0).expect("config missing");
+        ProtocolConfig::try_deserialize(&mut &account.data[..]).expect("invalid config account")
+    }
+
+    pub fn price_feed(&self, mint: &Pubkey) -> PriceFeed {
+        let account = self.accounts.get(&pda::price_feed(mint).0).expect("price feed missing");
+        PriceFeed::try_deserialize(&mut &account.data[..]).expect("invalid price feed account")
+    }
+
+    /// Move the clock to `unix_timestamp`
+    pub fn warp_to(&mut self, unix_timestamp: i64) {
+        UNIX_TIMESTAMP.with(|timestamp| timestamp.set(unix_timestamp));
+    }
+
+    pub fn now(&self) -> i64 {
+        UNIX_TIMESTAMP.with(Cell::get)
+    }
+
+    /// A mint owned by `token_program` with no authorities
+    pub fn create_mint(&mut self, token_program: &Pubkey) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.create_mint_at(&key, token_program);
+        key
+    }
+
+    /// A mint at a fixed address, e.g. the native mint
+    pub fn create_mint_at(&mut self, key: &Pubkey, token_program: &Pubkey) {
+        let mint = spl_token::state::Mint { decimals: 9, is_initialized: true, ..Default::default() };
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint::pack(mint, &mut data).unwrap();
+        self.set_account(*key, self.rent_exempt(data, token_program));
+    }
+
+    /// A Token-2022 mint charging `transfer_fee_bps` on every transfer, uncapped
+    pub fn create_mint_with_transfer_fee(&mut self, transfer_fee_bps: u16) -> Pubkey {
+        let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+            ExtensionType::TransferFeeConfig,
+        ])
+        .unwrap();
+        let mut data = vec![0; len];
+        let mut state = StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data).unwrap();
+        let fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: u64::MAX.into(),
+            transfer_fee_basis_points: transfer_fee_bps.into(),
+        };
+        let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        config.older_transfer_fee = fee;
+        config.newer_transfer_fee = fee;
+        state.base = spl_token_2022::state::Mint { decimals: 9, is_initialized: true, ..Default::default() };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        let key = Pubkey::new_unique();
+        self.set_account(key, self.rent_exempt(data, &spl_token_2022::ID));
+        key
+    }
+
+    /// The transfer fee a Token-2022 `mint` charges this epoch (the bank stays in epoch 0)
+    pub fn transfer_fee(&self, mint: &Pubkey) -> TransferFee {
+        let account = self.account(mint).expect("mint missing");
+        let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data).unwrap();
+        *state.get_extension::<TransferFeeConfig>().unwrap().get_epoch_fee(0)
+    }
+
+    /// An initialized token account of `mint` held by `owner` with `amount` tokens
+    pub fn create_token_account(&mut self, token_program: &Pubkey, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.create_token_account_at(&key, token_program, mint, owner, amount);
+        key
+    }
+
+    /// A token account at a fixed address, e.g. an associated token account
+    pub fn create_token_account_at(
+        &mut self,
+        key: &Pubkey,
+        token_program: &Pubkey,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) {
+        let account = spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account::pack(account, &mut data).unwrap();
+        self.set_account(*key, self.rent_exempt(data, token_program));
+    }
+
+    fn rent_exempt(&self, data: Vec<u8>, owner: &Pubkey) -> TestAccount {
+        TestAccount {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: *owner,
+            executable: false,
+        }
+    }
+
+    /// Execute `ix` atomically: on error no account changes are kept. Every account
+    /// marked as a signer in `ix` is treated as having signed the transaction.
+    pub fn process(&mut self, ix: &Instruction) -> ProgramResult {
+        assert_eq!(ix.program_id, fundr::ID, "the harness only executes fundr instructions");
+        let lamports_before: u128 = self.accounts.values().map(|account| account.lamports as u128).sum();
+
+        let mut input = self.serialize(ix);
+        CPI_WRITES.with(|writes| writes.borrow_mut().clear());
+        let result = {
+            let (program_id, accounts, data) = unsafe { entrypoint::deserialize(input.as_mut_ptr() as *mut u8) };
+            fundr::entry(program_id, &accounts, data)
+        };
+
+        if result.is_ok() {
+            let accounts = Self::read_back(ix, &input);
+            self.check_account_rules(ix, &accounts)?;
+            // Like the runtime, fail a transaction that leaves a writable account holding
+            // lamports below rent exemption, unless it was already short and didn't grow
+            let rent_paying = |account: &TestAccount| {
+                account.lamports > 0 && !Rent::default().is_exempt(account.lamports, account.data.len())
+            };
+            for (key, account) in &accounts {
+                let writable = ix.accounts.iter().any(|meta| meta.pubkey == *key && meta.is_writable);
+                let already_short = self.accounts.get(key).is_some_and(|before| {
+                    rent_paying(before) && before.data.len() == account.data.len() && account.lamports <= before.lamports
+                });
+                if writable && rent_paying(account) && !already_short {
+                    return Err(ProgramError::AccountNotRentExempt);
+                }
+            }
+            for (key, account) in accounts {
+                if account.lamports == 0 {
+                    // Accounts left without lamports are garbage collected at the end of a transaction
+                    self.accounts.remove(&key);
+                } else {
+                    self.accounts.insert(key, account);
+                }
+            }
+            let lamports_after: u128 = self.accounts.values().map(|account| account.lamports as u128).sum();
+            assert_eq!(lamports_before, lamports_after, "instruction created or destroyed lamports");
+        }
+        result
+    }
+
+    /// Fail like the runtime if the program changed a read-only account, or debited or
+    /// wrote an account it doesn't own other than through a CPI
+    fn check_account_rules(&self, ix: &Instruction, accounts: &[(Pubkey, TestAccount)]) -> ProgramResult {
+        let missing = TestAccount { lamports: 0, data: Vec::new(), owner: system_program::ID, executable: false };
+        for (key, after) in accounts {
+            let before = self.accounts.get(key).unwrap_or(&missing);
+            if before == after {
+                continue;
+            }
+            if !ix.accounts.iter().any(|meta| meta.pubkey == *key && meta.is_writable) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let via_cpi = CPI_WRITES.with(|writes| writes.borrow().contains(key));
+            let debited_or_written =
+                after.lamports < before.lamports || after.data != before.data || after.owner != before.owner;
+            if before.owner != fundr::ID && !via_cpi && debited_or_written {
+                return Err(ProgramError::IllegalOwner);
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize `ix`'s accounts in the loader's aligned input format. The buffer is
+    /// built from u64 words so every field is naturally aligned.
+    fn serialize(&self, ix: &Instruction) -> Vec<u64> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(ix.accounts.len() as u64).to_le_bytes());
+
+        for (index, meta) in ix.accounts.iter().enumerate() {
+            if let Some(first) = ix.accounts[..index].iter().position(|other| other.pubkey == meta.pubkey) {
+                bytes.push(first as u8);
+                bytes.extend_from_slice(&[0; 7]);
+                continue;
+            }
+
+            let account = self.accounts.get(&meta.pubkey).cloned().unwrap_or(TestAccount {
+                lamports: 0,
+                data: Vec::new(),
+                owner: system_program::ID,
+                executable: false,
+            });
+            // Like a transaction, a key repeated across metas gets the union of their privileges
+            let metas = || ix.accounts.iter().filter(|other| other.pubkey == meta.pubkey);
+            bytes.push(NON_DUP_MARKER);
+            bytes.push(metas().any(|other| other.is_signer) as u8);
+            bytes.push(metas().any(|other| other.is_writable) as u8);
+            bytes.push(account.executable as u8);
+            bytes.extend_from_slice(&[0; 4]);
+            bytes.extend_from_slice(meta.pubkey.as_ref());
+            bytes.extend_from_slice(account.owner.as_ref());
+            bytes.extend_from_slice(&account.lamports.to_le_bytes());
+            bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&account.data);
+            bytes.resize(align(bytes.len() + MAX_PERMITTED_DATA_INCREASE), 0);
+            bytes.extend_from_slice(&0u64.to_le_bytes()); // rent epoch
+        }
+
+        bytes.extend_from_slice(&(ix.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&ix.data);
+        bytes.extend_from_slice(ix.program_id.as_ref());
+
+        bytes.resize(align(bytes.len()), 0);
+        bytes
+            .chunks_exact(size_of::<u64>())
+            .map(|word| u64::from_ne_bytes(word.try_into().unwrap()))
+            .collect()
+    }
+
+    /// Read every distinct account back out of an input buffer after execution
+    fn read_back(ix: &Instruction, input: &[u64]) -> Vec<(Pubkey, TestAccount)> {
+        let bytes: Vec<u8> = input.iter().flat_map(|word| word.to_ne_bytes()).collect();
+        let read_u64 = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        let mut accounts = Vec::new();
+        let mut offset = size_of::<u64>();
+        for _ in &ix.accounts {
+            if bytes[offset] != NON_DUP_MARKER {
+                offset += 8;
+                continue;
+            }
+            let executable = bytes[offset + 3] != 0;
+            // `deserialize` records the serialized data length here; the data region was
+            // sized from it even if the program reallocated the account since
+            let original_data_len = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+            offset += 8;
+            let key = Pubkey::try_from(&bytes[offset..offset + 32]).unwrap();
+            let owner = Pubkey::try_from(&bytes[offset + 32..offset + 64]).unwrap();
+            offset += 64;
+            let lamports = read_u64(offset);
+            let data_len = read_u64(offset + 8) as usize;
+            offset += 16;
+            let data = bytes[offset..offset + data_len].to_vec();
+            offset = align(offset + original_data_len + MAX_PERMITTED_DATA_INCREASE) + size_of::<u64>();
+
+            accounts.push((key, TestAccount { lamports, data, owner, executable }));
+        }
+        accounts
+    }
+}
+
+fn align(len: usize) -> usize {
+    len.next_multiple_of(BPF_ALIGN_OF_U128)
+}
+
+/// Assert that an instruction failed with `error`
+#[track_caller]
+pub fn assert_fundr_error(result: ProgramResult, error: FundrError) {
+    assert_eq!(result, Err(ProgramError::Custom(error.into())));
+}
+
+/// Arguments for a valid fund with a 20% performance fee and a 0.1 SOL minimum deposit
+pub fn fund_args(initial_deposit: u64) -> InitializeFundArgs {
+    InitializeFundArgs {
+        name: "Alpha".to_string(),
+        description: "Blue chip Solana tokens".to_string(),
+        performance_fee: 2000,
+        min_deposit: SOL / 10,
+        fund_mode: FundMode::Manual,
+        initial_deposit,
+        fee_period: MIN_FEE_PERIOD,
+    }
+}
+
+/// Create the protocol config with the shortest fee claim delay. Returns the admin.
+pub fn create_config(bank: &mut TestBank) -> Pubkey {
+    let admin = bank.wallet(SOL);
+    bank.process(&instructions::initialize_config(&UPGRADE_AUTHORITY, &admin, MIN_FEE_CLAIM_DELAY))
+        .unwrap();
+    admin
+}
+
+/// Publish an oracle price of 1 lamport per base unit of `mint`, so a fair rebalance
+/// gets back as many units as it puts in
+pub fn publish_price(bank: &mut TestBank, admin: &Pubkey, mint: &Pubkey) {
+    bank.process(&instructions::publish_price(admin, mint, PRICE_SCALE)).unwrap();
+}
+
+/// Create a fund managed by a new wallet, seeded with `initial_deposit`.
+/// Returns (manager, fund).
+pub fn create_fund(bank: &mut TestBank, initial_deposit: u64) -> (Pubkey, Pubkey) {
+    create_fund_with(bank, fund_args(initial_deposit))
+}
+
+pub fn create_fund_with(bank: &mut TestBank, args: InitializeFundArgs) -> (Pubkey, Pubkey) {
+    let manager = bank.wallet(100 * SOL);
+    bank.process(&instructions::initialize_fund(&manager, 0, args)).unwrap();
+    (manager, pda::fund(&manager).0)
+}
+
+/// A new investor who deposits `amount` into `fund` (and keeps 1 SOL for rent)
+pub fn invest(bank: &mut TestBank, fund: &Pubkey, amount: u64) -> Pubkey {
+    let investor = bank.wallet(amount + SOL);
+    bank.process(&instructions::deposit(fund, &investor, amount, 0)).unwrap();
+    investor
+}
+
+/// Send `lamports` straight to the vault and book them with `sync_assets`, as if the
+/// fund's positions had gained that much
+pub fn simulate_gain(bank: &mut TestBank, fund: &Pubkey, lamports: u64) {
+    bank.airdrop(&pda::vault(fund).0, lamports);
+    bank.process(&instructions::sync_assets(fund, &[])).unwrap();
+}
+
+/// Take `lamports` out of the vault and book the loss with `sync_assets`
+pub fn simulate_loss(bank: &mut TestBank, fund: &Pubkey, lamports: u64) {
+    let vault = pda::vault(fund).0;
+    let mut account = bank.account(&vault).expect("vault missing").clone();
+    account.lamports -= lamports;
+    bank.set_account(vault, account);
+    bank.process(&instructions::sync_assets(fund, &[])).unwrap();
+}
+
+/// Syscall stubs: sysvars from the test thread's clock and CPIs to the system and token programs
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock { unix_timestamp: UNIX_TIMESTAMP.with(Cell::get), ..Clock::default() };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        entrypoint::SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        entrypoint::SUCCESS
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let pda_signers: HashSet<Pubkey> = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &fundr::ID))
+            .collect::<Result<_, _>>()
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+
+        let mut accounts = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let mut info = account_infos
+                .iter()
+                .find(|info| *info.key == meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?
+                .clone();
+            if meta.is_signer && !info.is_signer && !pda_signers.contains(&meta.pubkey) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !info.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            if meta.is_writable {
+                CPI_WRITES.with(|writes| writes.borrow_mut().insert(meta.pubkey));
+            }
+            info.is_signer = meta.is_signer;
+            info.is_writable = meta.is_writable;
+            accounts.push(info);
+        }
+
+        match instruction.program_id {
+            id if id == system_program::ID => process_system_instruction(&accounts, &instruction.data),
+            id if id == spl_token::ID => spl_token::processor::Processor::process(&id, &accounts, &instruction.data),
+            id if id == spl_token_2022::ID => {
+                spl_token_2022::processor::Processor::process(&id, &accounts, &instruction.data)
+            }
+            _ => Err(ProgramError::IncorrectProgramId),
+        }
+    }
+}
+
+/// The subset of the system program Anchor's `init`, `realloc` and transfer CPIs use
+fn process_system_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    let read_pubkey = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).unwrap();
+
+    match u32::from_le_bytes(data[..4].try_into().unwrap()) {
+        // CreateAccount { lamports, space, owner }
+        0 => {
+            let (from, to) = (&accounts[0], &accounts[1]);
+            require_signer(to)?;
+            if to.lamports() > 0 {
+                return Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32));
+            }
+            require_unused(to)?;
+            transfer(from, to, read_u64(4))?;
+            to.realloc(read_u64(12) as usize, true)?;
+            to.assign(&read_pubkey(20));
+            Ok(())
+        }
+        // Assign { owner }
+        1 => {
+            require_signer(&accounts[0])?;
+            // The runtime only lets a system account change owner while its data is zeroed
+            if *accounts[0].owner != read_pubkey(4) {
+                let zeroed = accounts[0].data.borrow().iter().all(|byte| *byte == 0);
+                if *accounts[0].owner != system_program::ID || !zeroed {
+                    return Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32));
+                }
+                accounts[0].assign(&read_pubkey(4));
+            }
+            Ok(())
+        }
+        // Transfer { lamports }
+        2 => transfer(&accounts[0], &accounts[1], read_u64(4)),
+        // Allocate { space }
+        8 => {
+            require_signer(&accounts[0])?;
+            require_unused(&accounts[0])?;
+            accounts[0].realloc(read_u64(4) as usize, true)
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn require_signer(account: &AccountInfo) -> ProgramResult {
+    if account.is_signer {
+        Ok(())
+    } else {
+        Err(ProgramError::MissingRequiredSignature)
+    }
+}
+
+/// Only empty, system-owned accounts may be allocated or handed to a new owner
+fn require_unused(account: &AccountInfo) -> ProgramResult {
+    if !account.data_is_empty() || *account.owner != system_program::ID {
+        return Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32));
+    }
+    Ok(())
+}
+
+fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    require_signer(from)?;
+    if !from.data_is_empty() || *from.owner != system_program::ID {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if from.lamports() < lamports {
+        return Err(ProgramError::Custom(SystemError::ResultWithNegativeLamports as u32));
+    }
+    **from.try_borrow_mut_lamports()? -= lamports;
+    **to.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use anchor_lang::solana_program::rent::Rent;
+use common::*;
+use fundr::FundrError;
+use fundr_client::instructions::InitializeFundArgs;
+
+#[test]
+fn deposit_mints_the_previewed_shares() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = bank.wallet(5 * SOL);
+    let before = bank.fund(&fund);
+    let expected = preview::deposit(&before, 2 * SOL).unwrap();
+
+    bank.process(&instructions::deposit(&fund, &investor, 2 * SOL, expected.shares)).unwrap();
+
+    let after = bank.fund(&fund);
+    let stake = bank.stake(&pda::stake(&fund, &investor).0);
+    assert_eq!(stake.shares, expected.shares);
+    assert_eq!(stake.total_deposited, expected.net_deposit);
+    assert_eq!(after.total_shares, before.total_shares + expected.shares);
+    assert_eq!(after.total_assets, before.total_assets + expected.net_deposit);
+    assert_eq!(after.platform_fees, before.platform_fees + expected.platform_fee);
+    assert_eq!(after.investor_count, 2);
+    assert_eq!(bank.lamports(&pda::vault(&fund).0), 3 * SOL + Rent::default().minimum_balance(0));
+}
+
+#[test]
+fn repeat_deposits_count_the_investor_once() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    bank.airdrop(&investor, SOL);
+
+    bank.process(&instructions::deposit(&fund, &investor, SOL, 0)).unwrap();
+
+    assert_eq!(bank.fund(&fund).investor_count, 2);
+}
+
+#[test]
+fn deposit_succeeds_when_the_stake_address_was_prefunded() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = bank.wallet(5 * SOL);
+    // Lamports sent to the stake PDA ahead of time must not block the investor
+    bank.airdrop(&pda::stake(&fund, &investor).0, 1_000);
+
+    bank.process(&instructions::deposit(&fund, &investor, SOL, 0)).unwrap();
+
+    assert!(bank.stake(&pda::stake(&fund, &investor).0).shares > 0);
+}
+
+#[test]
+fn deposit_rejects_amounts_below_the_minimum() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = bank.wallet(5 * SOL);
+
+    let result = bank.process(&instructions::deposit(&fund, &investor, SOL / 10 - 1, 0));
+
+    assert_fundr_error(result, FundrError::AmountTooSmall);
+}
+
+#[test]
+fn deposit_enforces_min_shares_out() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = bank.wallet(5 * SOL);
+    let expected = preview::deposit(&bank.fund(&fund), SOL).unwrap();
+
+    let result = bank.process(&instructions::deposit(&fund, &investor, SOL, expected.shares + 1));
+
+    assert_fundr_error(result, FundrError::SlippageExceeded);
+    assert_eq!(bank.lamports(&investor), 5 * SOL);
+}
+
+#[test]
+fn deposit_rejects_deposits_too_small_to_mint_a_share() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund_with(&mut bank, InitializeFundArgs { min_deposit: 1, ..fund_args(0) });
+    // 10 SOL booked against no shares prices a share above 100 lamports
+    simulate_gain(&mut bank, &fund, 10 * SOL);
+    let investor = bank.wallet(SOL);
+
+    let result = bank.process(&instructions::deposit(&fund, &investor, 100, 0));
+
+    assert_fundr_error(result, FundrError::ZeroSharesMinted);
+}
+
+#[test]
+fn deposit_rejects_share_counts_that_overflow() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund_with(&mut bank, InitializeFundArgs { min_deposit: 1, ..fund_args(0) });
+    let investor = bank.wallet(SOL);
+
+    let result = bank.process(&instructions::deposit(&fund, &investor, u64::MAX, 0));
+
+    assert_fundr_error(result, FundrError::MathOverflow);
+}
+
+#[test]
+fn deposit_fails_without_the_lamports_to_pay() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = bank.wallet(SOL);
+
+    assert!(bank.process(&instructions::deposit(&fund, &investor, 2 * SOL, 0)).is_err());
+    assert!(bank.account(&pda::stake(&fund, &investor).0).is_none());
+}
+
+#[test]
+fn withdraw_pays_out_the_previewed_amount() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, 2 * SOL);
+    let stake = bank.stake(&pda::stake(&fund, &investor).0);
+    let before = bank.fund(&fund);
+    let balance = bank.lamports(&investor);
+    let expected = preview::withdraw(&before, stake.shares / 2).unwrap();
+
+    bank.process(&instructions::withdraw(&fund, &investor, stake.shares / 2, expected.net)).unwrap();
+
+    let after = bank.fund(&fund);
+    assert_eq!(bank.lamports(&investor), balance + expected.net);
+    assert_eq!(after.total_shares, before.total_shares - stake.shares / 2);
+    assert_eq!(after.total_assets, before.total_assets - expected.gross);
+    assert_eq!(after.platform_fees, before.platform_fees + expected.fee);
+    assert_eq!(bank.stake(&pda::stake(&fund, &investor).0).shares, stake.shares - stake.shares / 2);
+}
+
+#[test]
+fn withdrawing_everything_removes_the_investor() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, 2 * SOL);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+
+    bank.process(&instructions::withdraw(&fund, &investor, shares, 0)).unwrap();
+
+    assert_eq!(bank.fund(&fund).investor_count, 1);
+    assert_eq!(bank.stake(&pda::stake(&fund, &investor).0).shares, 0);
+}
+
+#[test]
+fn the_last_withdrawal_from_a_small_fund_leaves_the_vault_rent_exempt() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund_with(&mut bank, InitializeFundArgs { min_deposit: 1, ..fund_args(0) });
+    let investor = invest(&mut bank, &fund, 100_000);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+
+    // Only the fees stay behind, well short of rent exemption on their own
+    bank.process(&instructions::withdraw(&fund, &investor, shares, 0)).unwrap();
+
+    let state = bank.fund(&fund);
+    assert_eq!(state.total_assets, 0);
+    assert_eq!(bank.lamports(&pda::vault(&fund).0), Rent::default().minimum_balance(0) + state.platform_fees);
+}
+
+#[test]
+fn empty_withdrawals_do_not_remove_the_investor_again() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+    bank.process(&instructions::withdraw(&fund, &investor, shares, 0)).unwrap();
+
+    bank.process(&instructions::withdraw(&fund, &investor, 0, 0)).unwrap();
+
+    assert_eq!(bank.fund(&fund).investor_count, 1);
+}
+
+#[test]
+fn withdraw_rejects_more_shares_than_held() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+
+    let result = bank.process(&instructions::withdraw(&fund, &investor, shares + 1, 0));
+
+    assert_fundr_error(result, FundrError::InsufficientShares);
+}
+
+#[test]
+fn withdraw_enforces_min_lamports_out() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+    let expected = preview::withdraw(&bank.fund(&fund), shares).unwrap();
+
+    let result = bank.process(&instructions::withdraw(&fund, &investor, shares, expected.net + 1));
+
+    assert_fundr_error(result, FundrError::SlippageExceeded);
+}
+
+#[test]
+fn withdraw_exact_pays_exactly_the_requested_lamports() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, 2 * SOL);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+    let balance = bank.lamports(&investor);
+    let expected = preview::withdraw_exact(&bank.fund(&fund), SOL / 2).unwrap();
+
+    bank.process(&instructions::withdraw_exact(&fund, &investor, SOL / 2, expected.shares)).unwrap();
+
+    assert_eq!(bank.lamports(&investor), balance + SOL / 2);
+    assert_eq!(bank.stake(&pda::stake(&fund, &investor).0).shares, shares - expected.shares);
+}
+
+#[test]
+fn withdraw_exact_enforces_max_shares_in() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, 2 * SOL);
+    let expected = preview::withdraw_exact(&bank.fund(&fund), SOL / 2).unwrap();
+
+    let result = bank.process(&instructions::withdraw_exact(&fund, &investor, SOL / 2, expected.shares - 1));
+
+    assert_fundr_error(result, FundrError::SlippageExceeded);
+}
+
+#[test]
+fn withdraw_exact_rejects_more_than_the_position() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+
+    let result = bank.process(&instructions::withdraw_exact(&fund, &investor, SOL, u64::MAX));
+
+    assert_fundr_error(result, FundrError::InsufficientShares);
+}
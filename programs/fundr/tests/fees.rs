@@ -0,0 +1,223 @@
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::{ProgramError, Pubkey};
+use anchor_lang::AccountDeserialize;
+use common::*;
+use fundr_client::instructions::InitializeFundArgs;
+use fundr::{FundrError, ProtocolConfig, MAX_FEE_CLAIM_DELAY, MIN_FEE_CLAIM_DELAY, MIN_FEE_PERIOD};
+
+fn config(bank: &TestBank) -> ProtocolConfig {
+    let account = bank.account(&pda::config().0).expect("config missing");
+    ProtocolConfig::try_deserialize(&mut &account.data[..]).unwrap()
+}
+
+/// A fund with an investor and a crystallized performance fee.
+/// Returns (manager, fund, investor, fee).
+fn fund_with_pending_fees(bank: &mut TestBank) -> (Pubkey, Pubkey, Pubkey, u64) {
+    let (manager, fund) = create_fund(bank, SOL);
+    let investor = invest(bank, &fund, 9 * SOL);
+    simulate_gain(bank, &fund, 5 * SOL);
+    bank.warp_to(bank.now() + MIN_FEE_PERIOD);
+    bank.process(&instructions::collect_fees(&fund, &manager)).unwrap();
+    let fee = bank.fund(&fund).pending_fees;
+    assert!(fee > 0);
+    (manager, fund, investor, fee)
+}
+
+#[test]
+fn initialize_config_requires_the_upgrade_authority() {
+    let mut bank = TestBank::new();
+    let admin = bank.wallet(SOL);
+
+    let ix = instructions::initialize_config(&admin, &admin, MIN_FEE_CLAIM_DELAY);
+    assert_fundr_error(bank.process(&ix), FundrError::Unauthorized);
+
+    for delay in [MIN_FEE_CLAIM_DELAY - 1, MAX_FEE_CLAIM_DELAY + 1] {
+        let ix = instructions::initialize_config(&UPGRADE_AUTHORITY, &admin, delay);
+        assert_fundr_error(bank.process(&ix), FundrError::InvalidFeeClaimDelay);
+    }
+
+    bank.process(&instructions::initialize_config(&UPGRADE_AUTHORITY, &admin, 7 * 86_400)).unwrap();
+    let config = config(&bank);
+    assert_eq!(config.admin, admin);
+    assert_eq!(config.fee_claim_delay, 7 * 86_400);
+}
+
+#[test]
+fn update_config_is_admin_only() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let successor = bank.wallet(SOL);
+
+    let ix = instructions::update_config(&successor, Some(successor), None);
+    assert_fundr_error(bank.process(&ix), FundrError::Unauthorized);
+    let ix = instructions::update_config(&admin, None, Some(0));
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidFeeClaimDelay);
+
+    bank.process(&instructions::update_config(&admin, Some(successor), Some(MAX_FEE_CLAIM_DELAY))).unwrap();
+    assert_eq!(config(&bank).admin, successor);
+    assert_eq!(config(&bank).fee_claim_delay, MAX_FEE_CLAIM_DELAY);
+
+    // The old admin has handed over the role
+    let ix = instructions::update_config(&admin, None, Some(MIN_FEE_CLAIM_DELAY));
+    assert_fundr_error(bank.process(&ix), FundrError::Unauthorized);
+}
+
+#[test]
+fn collect_fees_requires_the_config() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+
+    let uninitialized: ProgramError = anchor_lang::error::Error::from(ErrorCode::AccountNotInitialized).into();
+    assert_eq!(bank.process(&instructions::collect_fees(&fund, &manager)), Err(uninitialized));
+}
+
+#[test]
+fn collect_fees_waits_for_the_fee_period() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund, _, fee) = fund_with_pending_fees(&mut bank);
+    let collected_at = bank.fund(&fund).last_fee_collection;
+    simulate_gain(&mut bank, &fund, 5 * SOL);
+
+    // A second crystallization inside the period would reset the high water mark early
+    bank.warp_to(collected_at + MIN_FEE_PERIOD - 1);
+    let result = bank.process(&instructions::collect_fees(&fund, &manager));
+    assert_fundr_error(result, FundrError::FeeCooldown);
+    assert_eq!(bank.fund(&fund).pending_fees, fee);
+
+    bank.warp_to(collected_at + MIN_FEE_PERIOD);
+    bank.process(&instructions::collect_fees(&fund, &manager)).unwrap();
+    assert!(bank.fund(&fund).pending_fees > fee);
+}
+
+#[test]
+fn anyone_can_crank_fees_on_schedule() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund) = create_fund_with(&mut bank, InitializeFundArgs { fee_period: 90 * 86_400, ..fund_args(SOL) });
+    invest(&mut bank, &fund, 9 * SOL);
+    simulate_gain(&mut bank, &fund, 5 * SOL);
+    let fee = preview::performance_fee(&bank.fund(&fund)).unwrap();
+    let cranker = bank.wallet(SOL);
+
+    bank.warp_to(bank.now() + MIN_FEE_PERIOD);
+    let result = bank.process(&instructions::crank_fees(&fund, &cranker));
+    assert_fundr_error(result, FundrError::FeeCooldown);
+
+    bank.warp_to(bank.fund(&fund).next_fee_collection());
+    bank.process(&instructions::crank_fees(&fund, &cranker)).unwrap();
+
+    // Cranking only crystallizes; the fee is still the manager's to claim
+    let state = bank.fund(&fund);
+    assert_eq!(state.pending_fees, fee);
+    assert_eq!(state.last_fee_collection, bank.now());
+    assert_eq!(bank.lamports(&cranker), SOL);
+    let result = bank.process(&instructions::claim_fees(&fund, &cranker, None));
+    assert_fundr_error(result, FundrError::UnauthorizedManager);
+    bank.warp_to(state.fees_claimable_at);
+    bank.process(&instructions::claim_fees(&fund, &manager, None)).unwrap();
+}
+
+#[test]
+fn claim_fees_pays_the_manager_after_the_delay() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund, _, fee) = fund_with_pending_fees(&mut bank);
+    let balance = bank.lamports(&manager);
+
+    bank.warp_to(bank.fund(&fund).fees_claimable_at - 1);
+    let result = bank.process(&instructions::claim_fees(&fund, &manager, None));
+    assert_fundr_error(result, FundrError::FeesLocked);
+
+    let attacker = bank.wallet(SOL);
+    bank.warp_to(bank.now() + 1);
+    let result = bank.process(&instructions::claim_fees(&fund, &attacker, None));
+    assert_fundr_error(result, FundrError::UnauthorizedManager);
+
+    bank.process(&instructions::claim_fees(&fund, &manager, None)).unwrap();
+    assert_eq!(bank.lamports(&manager), balance + fee);
+    assert_eq!(bank.fund(&fund).pending_fees, 0);
+}
+
+#[test]
+fn investors_can_exit_before_fees_are_claimable() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (_, fund, investor, pending) = fund_with_pending_fees(&mut bank);
+
+    // The escrowed fee is excluded from investor assets, so exiting neither pays it out
+    // nor leaves the fund unable to cover it
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+    bank.process(&instructions::withdraw(&fund, &investor, shares, 0)).unwrap();
+    bank.process(&instructions::sync_assets(&fund, &[])).unwrap();
+
+    let state = bank.fund(&fund);
+    assert_eq!(state.pending_fees, pending);
+    let vault = bank.lamports(&pda::vault(&fund).0);
+    assert!(vault >= state.total_assets + state.platform_fees + state.pending_fees);
+}
+
+#[test]
+fn flagged_funds_cannot_claim_and_can_have_fees_voided() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund, _, fee) = fund_with_pending_fees(&mut bank);
+    let before = bank.fund(&fund);
+
+    let result = bank.process(&instructions::void_fees(&fund, &admin));
+    assert_fundr_error(result, FundrError::FundNotFlagged);
+
+    bank.process(&instructions::set_fund_flag(&fund, &admin, true)).unwrap();
+    bank.warp_to(before.fees_claimable_at);
+    let result = bank.process(&instructions::claim_fees(&fund, &manager, None));
+    assert_fundr_error(result, FundrError::FundFlagged);
+
+    bank.process(&instructions::void_fees(&fund, &admin)).unwrap();
+
+    // The fee goes back to the investors it was taken from
+    let after = bank.fund(&fund);
+    assert_eq!(after.pending_fees, 0);
+    assert_eq!(after.total_assets, before.total_assets + fee);
+    assert!(after.flagged);
+}
+
+#[test]
+fn admin_actions_reject_other_signers() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund, _, _) = fund_with_pending_fees(&mut bank);
+
+    let result = bank.process(&instructions::set_fund_flag(&fund, &manager, true));
+    assert_fundr_error(result, FundrError::Unauthorized);
+
+    bank.process(&instructions::set_fund_flag(&fund, &admin, true)).unwrap();
+    let result = bank.process(&instructions::void_fees(&fund, &manager));
+    assert_fundr_error(result, FundrError::Unauthorized);
+
+    // Clearing the flag lets the manager claim again
+    bank.process(&instructions::set_fund_flag(&fund, &admin, false)).unwrap();
+    bank.warp_to(bank.fund(&fund).fees_claimable_at);
+    bank.process(&instructions::claim_fees(&fund, &manager, None)).unwrap();
+}
+
+#[test]
+fn close_fund_requires_fees_to_be_settled() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund, investor, fee) = fund_with_pending_fees(&mut bank);
+    let cranker = bank.wallet(SOL);
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    bank.process(&instructions::force_distribute(&fund, &investor, &cranker)).unwrap();
+    bank.process(&instructions::force_distribute(&fund, &manager, &cranker)).unwrap();
+
+    let close = instructions::close_fund(&fund, &manager, &manager, &admin, false, None, &spl_token::ID, &[]);
+    assert_fundr_error(bank.process(&close), FundrError::PendingFeesOutstanding);
+
+    bank.warp_to(bank.fund(&fund).fees_claimable_at);
+    let balance = bank.lamports(&manager);
+    bank.process(&instructions::claim_fees(&fund, &manager, None)).unwrap();
+    assert_eq!(bank.lamports(&manager), balance + fee);
+    bank.process(&close).unwrap();
+}
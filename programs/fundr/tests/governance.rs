@@ -0,0 +1,305 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token::spl_token::{self, native_mint};
+use common::*;
+use fundr::{FundStatus, FundrError, ManagerAction, ProposalAction, DEFAULT_VOTING_PERIOD, MAX_VOTING_PERIOD, MIN_VOTING_PERIOD};
+
+/// A fund seeded with 1 SOL by its manager and two investors holding 6 and 3 SOL, so
+/// the large investor alone has a majority. Returns (manager, fund, large, small).
+fn fund_with_investors(bank: &mut TestBank) -> (Pubkey, Pubkey, Pubkey, Pubkey) {
+    let (manager, fund) = create_fund(bank, SOL);
+    let large = invest(bank, &fund, 6 * SOL);
+    let small = invest(bank, &fund, 3 * SOL);
+    // Shares only vote on proposals opened after they were deposited
+    bank.warp_to(bank.now() + 1);
+    (manager, fund, large, small)
+}
+
+/// Open the fund's next proposal. Returns its address.
+fn propose(bank: &mut TestBank, fund: &Pubkey, proposer: &Pubkey, action: ProposalAction) -> Pubkey {
+    let id = bank.fund(fund).proposal_count;
+    bank.process(&instructions::create_proposal(fund, proposer, id, action)).unwrap();
+    pda::proposal(fund, id).0
+}
+
+/// Vote `voters` in favor of `action` and execute it once voting ends
+fn pass(bank: &mut TestBank, fund: &Pubkey, voters: &[Pubkey], action: ProposalAction) {
+    let proposal = propose(bank, fund, &voters[0], action);
+    for voter in voters {
+        bank.process(&instructions::cast_vote(fund, &proposal, voter, true)).unwrap();
+    }
+    bank.warp_to(bank.proposal(&proposal).voting_ends_at);
+    let cranker = bank.wallet(SOL);
+    let multisig = bank.fund(fund).multisig;
+    bank.process(&instructions::execute_proposal(fund, &proposal, &cranker, multisig)).unwrap();
+}
+
+#[test]
+fn investors_can_replace_the_manager() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund, large, small) = fund_with_investors(&mut bank);
+    let successor = bank.wallet(SOL);
+
+    let action = ProposalAction::ReplaceAuthority { new_authority: successor };
+    let proposal = propose(&mut bank, &fund, &large, action);
+    let state = bank.proposal(&proposal);
+    assert_eq!(state.snapshot_shares, bank.fund(&fund).total_shares);
+    assert_eq!(state.voting_ends_at, bank.now() + DEFAULT_VOTING_PERIOD);
+    assert_eq!(bank.fund(&fund).proposal_count, 1);
+
+    bank.process(&instructions::cast_vote(&fund, &proposal, &large, true)).unwrap();
+    bank.process(&instructions::cast_vote(&fund, &proposal, &small, false)).unwrap();
+    let state = bank.proposal(&proposal);
+    assert_eq!(state.votes_for, bank.stake(&pda::stake(&fund, &large).0).shares);
+    assert!(state.votes_against > 0);
+
+    let execute = instructions::execute_proposal(&fund, &proposal, &small, false);
+    assert_fundr_error(bank.process(&execute), FundrError::VotingOpen);
+
+    bank.warp_to(state.voting_ends_at);
+    let result = bank.process(&instructions::cast_vote(&fund, &proposal, &manager, false));
+    assert_fundr_error(result, FundrError::VotingClosed);
+
+    bank.process(&execute).unwrap();
+    assert!(bank.proposal(&proposal).executed);
+    assert_eq!(bank.fund(&fund).authority, successor);
+    assert_fundr_error(bank.process(&execute), FundrError::ProposalAlreadyExecuted);
+
+    // The fund keeps its address and can still sign for its token accounts
+    assert_eq!(bank.fund(&fund).seed_key(), manager);
+    let mint = bank.create_mint(&spl_token::ID);
+    let token_account = bank.create_token_account(&spl_token::ID, &mint, &fund, 0);
+    let ix = instructions::close_token_account(&fund, &manager, &token_account, &spl_token::ID);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+    let ix = instructions::close_token_account(&fund, &successor, &token_account, &spl_token::ID);
+    bank.process(&ix).unwrap();
+}
+
+#[test]
+fn replacing_the_manager_voids_their_signer_set() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund, large, _) = fund_with_investors(&mut bank);
+    let [a, b, successor] = [bank.wallet(SOL), bank.wallet(SOL), bank.wallet(SOL)];
+    bank.process(&instructions::create_signer_set(&fund, &manager, vec![a, b], 2)).unwrap();
+
+    // The old signers line up an action to take the fund back
+    let grab = ManagerAction::SetAuthority { new_authority: a };
+    bank.process(&instructions::propose_action(&fund, &a, 0, grab)).unwrap();
+    bank.process(&instructions::approve_action(&fund, 0, &b)).unwrap();
+
+    let proposal = propose(&mut bank, &fund, &large, ProposalAction::ReplaceAuthority { new_authority: successor });
+    bank.process(&instructions::cast_vote(&fund, &proposal, &large, true)).unwrap();
+    bank.warp_to(bank.proposal(&proposal).voting_ends_at);
+    let ix = instructions::execute_proposal(&fund, &proposal, &large, false);
+    assert_fundr_error(bank.process(&ix), FundrError::MissingActionAccount);
+    bank.process(&instructions::execute_proposal(&fund, &proposal, &large, true)).unwrap();
+
+    let state = bank.fund(&fund);
+    assert_eq!(state.authority, successor);
+    assert!(!state.multisig);
+    assert_eq!(bank.signer_set(&fund).nonce, 1);
+    let result = bank.process(&instructions::execute_action(&fund, 0, &grab, &a));
+    assert_fundr_error(result, FundrError::MultisigDisabled);
+
+    // Proposing to the old set afterwards doesn't help either
+    bank.process(&instructions::propose_action(&fund, &a, 1, grab)).unwrap();
+    bank.process(&instructions::approve_action(&fund, 1, &b)).unwrap();
+    let result = bank.process(&instructions::execute_action(&fund, 1, &grab, &a));
+    assert_fundr_error(result, FundrError::MultisigDisabled);
+
+    // Nor once the new owner sets up signers of their own
+    bank.process(&instructions::create_signer_set(&fund, &successor, vec![successor], 1)).unwrap();
+    let set = bank.signer_set(&fund);
+    assert_eq!((set.signers, set.nonce, set.action_count), (vec![successor], 2, 2));
+    let result = bank.process(&instructions::execute_action(&fund, 1, &grab, &a));
+    assert_fundr_error(result, FundrError::StaleAction);
+    assert_eq!(bank.fund(&fund).authority, successor);
+}
+
+#[test]
+fn a_reopened_fund_starts_with_no_proposals() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund, large, small) = fund_with_investors(&mut bank);
+    let successor = bank.wallet(SOL);
+    let action = ProposalAction::ReplaceAuthority { new_authority: successor };
+    let proposal = propose(&mut bank, &fund, &large, action);
+    bank.process(&instructions::cast_vote(&fund, &proposal, &large, true)).unwrap();
+
+    let cranker = bank.wallet(SOL);
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    for investor in [large, small, manager] {
+        bank.process(&instructions::force_distribute(&fund, &investor, &cranker)).unwrap();
+    }
+    bank.process(&instructions::close_fund(&fund, &manager, &manager, &admin, false, None, &spl_token::ID, &[])).unwrap();
+    bank.process(&instructions::initialize_fund(&manager, 1, fund_args(SOL))).unwrap();
+    let next = pda::fund_at(&manager, 1).0;
+
+    // The passed proposal can't be carried over to the new fund
+    bank.warp_to(bank.proposal(&proposal).voting_ends_at);
+    let result = bank.process(&instructions::execute_proposal(&next, &proposal, &large, false));
+    assert_fundr_error(result, FundrError::InvalidAccount);
+    assert_eq!(bank.fund(&next).authority, manager);
+
+    // and the new fund numbers its own proposals from zero
+    let investor = invest(&mut bank, &next, SOL);
+    bank.warp_to(bank.now() + 1);
+    assert_eq!(propose(&mut bank, &next, &investor, action), pda::proposal(&next, 0).0);
+}
+
+#[test]
+fn only_shares_held_at_the_snapshot_vote() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund, large, small) = fund_with_investors(&mut bank);
+    let outsider = bank.wallet(10 * SOL);
+
+    // Proposing and voting require a stake in the fund
+    let ix = instructions::create_proposal(&fund, &outsider, 0, ProposalAction::Pause);
+    assert!(bank.process(&ix).is_err());
+    let proposal = propose(&mut bank, &fund, &small, ProposalAction::Pause);
+    let small_shares = bank.stake(&pda::stake(&fund, &small).0).shares;
+
+    // Buying in after the proposal opened doesn't buy votes
+    bank.process(&instructions::deposit(&fund, &outsider, 5 * SOL, 0)).unwrap();
+    let result = bank.process(&instructions::cast_vote(&fund, &proposal, &outsider, true));
+    assert_fundr_error(result, FundrError::NoVotingPower);
+    // and topping up votes only with the shares held before
+    bank.process(&instructions::deposit(&fund, &small, SOL / 2, 0)).unwrap();
+    bank.process(&instructions::cast_vote(&fund, &proposal, &small, true)).unwrap();
+    assert_eq!(bank.proposal(&proposal).votes_for, small_shares);
+
+    // A stake topped up again after a later proposal no longer knows what it held earlier
+    bank.process(&instructions::deposit(&fund, &manager, SOL, 0)).unwrap();
+    let later = propose(&mut bank, &fund, &large, ProposalAction::Pause);
+    let manager_shares = bank.stake(&pda::stake(&fund, &manager).0).shares;
+    bank.process(&instructions::deposit(&fund, &manager, SOL, 0)).unwrap();
+    let result = bank.process(&instructions::cast_vote(&fund, &proposal, &manager, true));
+    assert_fundr_error(result, FundrError::SharesAcquiredAfterSnapshot);
+    bank.process(&instructions::cast_vote(&fund, &later, &manager, true)).unwrap();
+    assert_eq!(bank.proposal(&later).votes_for, manager_shares);
+
+    // Each investor votes once, and exiting afterwards leaves the vote counted
+    bank.process(&instructions::cast_vote(&fund, &proposal, &large, true)).unwrap();
+    assert!(bank.process(&instructions::cast_vote(&fund, &proposal, &large, false)).is_err());
+    let votes = bank.proposal(&proposal).votes_for;
+    let shares = bank.stake(&pda::stake(&fund, &large).0).shares;
+    bank.process(&instructions::withdraw(&fund, &large, shares, 0)).unwrap();
+    assert_eq!(bank.proposal(&proposal).votes_for, votes);
+
+    // An emptied stake has nothing left to vote with
+    let proposal = propose(&mut bank, &fund, &small, ProposalAction::Pause);
+    let result = bank.process(&instructions::cast_vote(&fund, &proposal, &large, true));
+    assert_fundr_error(result, FundrError::NoVotingPower);
+}
+
+#[test]
+fn proposals_need_quorum_and_approval() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund, large, small) = fund_with_investors(&mut bank);
+    let cranker = bank.wallet(SOL);
+
+    // The manager's 10% of the shares is below the 20% quorum
+    let proposal = propose(&mut bank, &fund, &manager, ProposalAction::Pause);
+    bank.process(&instructions::cast_vote(&fund, &proposal, &manager, true)).unwrap();
+    bank.warp_to(bank.proposal(&proposal).voting_ends_at);
+    let result = bank.process(&instructions::execute_proposal(&fund, &proposal, &cranker, false));
+    assert_fundr_error(result, FundrError::ProposalNotPassed);
+
+    // Quorum is reached, but the majority votes against
+    let proposal = propose(&mut bank, &fund, &small, ProposalAction::Pause);
+    bank.process(&instructions::cast_vote(&fund, &proposal, &small, true)).unwrap();
+    bank.process(&instructions::cast_vote(&fund, &proposal, &large, false)).unwrap();
+    bank.warp_to(bank.proposal(&proposal).voting_ends_at);
+    let result = bank.process(&instructions::execute_proposal(&fund, &proposal, &cranker, false));
+    assert_fundr_error(result, FundrError::ProposalNotPassed);
+    assert_eq!(bank.fund(&fund).status, FundStatus::Active);
+
+    // Replacing the manager with themselves is rejected up front
+    let action = ProposalAction::ReplaceAuthority { new_authority: manager };
+    let ix = instructions::create_proposal(&fund, &small, 2, action);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidProposal);
+}
+
+#[test]
+fn investors_can_pause_resume_and_wind_down() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund, large, small) = fund_with_investors(&mut bank);
+    let mint = bank.create_mint(&spl_token::ID);
+    publish_price(&mut bank, &admin, &mint);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &mint, 5_000)).unwrap();
+
+    pass(&mut bank, &fund, &[large], ProposalAction::Pause);
+    assert_eq!(bank.fund(&fund).status, FundStatus::Paused);
+
+    // Paused funds take no deposits and no trades, but investors can still leave
+    let rebalance = instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &mint, SOL);
+    assert_fundr_error(bank.process(&rebalance), FundrError::FundPaused);
+    let result = bank.process(&instructions::deposit(&fund, &large, SOL, 0));
+    assert_fundr_error(result, FundrError::FundNotActive);
+    let shares = bank.stake(&pda::stake(&fund, &small).0).shares;
+    bank.process(&instructions::withdraw(&fund, &small, shares / 2, 0)).unwrap();
+
+    pass(&mut bank, &fund, &[large], ProposalAction::Resume);
+    assert_eq!(bank.fund(&fund).status, FundStatus::Active);
+
+    // Resuming a fund that isn't paused fails even once passed
+    let proposal = propose(&mut bank, &fund, &large, ProposalAction::Resume);
+    bank.process(&instructions::cast_vote(&fund, &proposal, &large, true)).unwrap();
+    bank.warp_to(bank.proposal(&proposal).voting_ends_at);
+    let result = bank.process(&instructions::execute_proposal(&fund, &proposal, &small, false));
+    assert_fundr_error(result, FundrError::FundNotPaused);
+    publish_price(&mut bank, &admin, &mint);
+    bank.process(&rebalance).unwrap();
+
+    pass(&mut bank, &fund, &[large], ProposalAction::WindDown);
+    let state = bank.fund(&fund);
+    assert_eq!(state.status, FundStatus::WindingDown);
+    assert_eq!(state.wind_down_started_at, bank.now());
+    bank.process(&instructions::force_distribute(&fund, &small, &large)).unwrap();
+
+    // Nothing more to vote on once the fund is closing
+    let ix = instructions::create_proposal(&fund, &large, 4, ProposalAction::Resume);
+    assert_fundr_error(bank.process(&ix), FundrError::FundNotActive);
+}
+
+#[test]
+fn governance_params_are_admin_only_and_snapshotted() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund, large, _) = fund_with_investors(&mut bank);
+
+    let ix = instructions::set_governance_params(&manager, 1_000, 6_000, MAX_VOTING_PERIOD);
+    assert_fundr_error(bank.process(&ix), FundrError::Unauthorized);
+    for (quorum, threshold, period) in [
+        (0, 6_000, MAX_VOTING_PERIOD),
+        (10_001, 6_000, MAX_VOTING_PERIOD),
+        (1_000, 4_999, MAX_VOTING_PERIOD),
+        (1_000, 10_001, MAX_VOTING_PERIOD),
+        (1_000, 6_000, MIN_VOTING_PERIOD - 1),
+        (1_000, 6_000, MAX_VOTING_PERIOD + 1),
+    ] {
+        let ix = instructions::set_governance_params(&admin, quorum, threshold, period);
+        assert_fundr_error(bank.process(&ix), FundrError::InvalidGovernanceParams);
+    }
+
+    // Raising the bar doesn't affect a proposal that is already open
+    let open = propose(&mut bank, &fund, &large, ProposalAction::Pause);
+    let ix = instructions::set_governance_params(&admin, 10_000, 10_000, MAX_VOTING_PERIOD);
+    bank.process(&ix).unwrap();
+    let later = propose(&mut bank, &fund, &large, ProposalAction::Pause);
+    let state = bank.proposal(&later);
+    assert_eq!((state.quorum_bps, state.approval_threshold_bps), (10_000, 10_000));
+    assert_eq!(state.voting_ends_at, bank.now() + MAX_VOTING_PERIOD);
+
+    bank.process(&instructions::cast_vote(&fund, &open, &large, true)).unwrap();
+    bank.warp_to(bank.proposal(&open).voting_ends_at);
+    bank.process(&instructions::execute_proposal(&fund, &open, &manager, false)).unwrap();
+    assert_eq!(bank.fund(&fund).status, FundStatus::Paused);
+}
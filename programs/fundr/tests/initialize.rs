@@ -0,0 +1,146 @@
+mod common;
+
+use anchor_lang::solana_program::instruction::AccountMeta;
+use anchor_lang::solana_program::rent::Rent;
+use common::*;
+use fundr::{FundMode, FundStatus, FundrError, MAX_DESCRIPTION_LEN, MAX_NAME_LEN, MAX_FEE_PERIOD, MIN_FEE_PERIOD, MIN_SEED_DEPOSIT, NAV_SCALE};
+use fundr_client::instructions::InitializeFundArgs;
+
+#[test]
+fn initialize_fund_seeds_the_manager_stake() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+
+    let state = bank.fund(&fund);
+    assert_eq!(state.authority, manager);
+    assert_eq!(state.name, "Alpha");
+    assert_eq!(state.performance_fee, 2000);
+    assert_eq!(state.fund_mode, FundMode::Manual);
+    assert_eq!(state.status, FundStatus::Active);
+    assert_eq!(state.high_water_mark, NAV_SCALE);
+    assert_eq!(state.created_at, bank.now());
+
+    // 1% platform fee and the vault's rent reserve stay outside of the fund's assets
+    assert_eq!(state.total_assets, SOL / 100 * 99);
+    assert_eq!(state.platform_fees, SOL / 100);
+    assert_eq!(bank.lamports(&pda::vault(&fund).0), SOL + Rent::default().minimum_balance(0));
+
+    let stake = bank.stake(&pda::stake(&fund, &manager).0);
+    assert_eq!(stake.user, manager);
+    assert_eq!(stake.shares, state.total_shares);
+    assert_eq!(state.investor_count, 1);
+}
+
+#[test]
+fn initialize_fund_without_seed_creates_no_stake() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, 0);
+
+    let state = bank.fund(&fund);
+    assert_eq!(state.total_shares, 0);
+    assert_eq!(state.total_assets, 0);
+    assert_eq!(state.investor_count, 0);
+    assert_eq!(bank.lamports(&pda::vault(&fund).0), Rent::default().minimum_balance(0));
+    assert!(bank.account(&pda::stake(&fund, &manager).0).is_none());
+
+    // The manager's first deposit creates the stake like any investor's
+    bank.process(&instructions::deposit(&fund, &manager, SOL, 1)).unwrap();
+    assert_eq!(bank.stake(&pda::stake(&fund, &manager).0).user, manager);
+}
+
+#[test]
+fn initialize_fund_passes_the_manager_stake_only_with_a_seed() {
+    let mut bank = TestBank::new();
+    let manager = bank.wallet(10 * SOL);
+    let stake = pda::stake(&pda::fund(&manager).0, &manager).0;
+
+    let mut ix = instructions::initialize_fund(&manager, 0, fund_args(0));
+    ix.accounts[3] = AccountMeta::new(stake, false);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAccount);
+
+    let mut ix = instructions::initialize_fund(&manager, 0, fund_args(SOL));
+    ix.accounts[3] = AccountMeta::new_readonly(fundr::ID, false);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAccount);
+}
+
+#[test]
+fn initialize_fund_rejects_invalid_arguments() {
+    let cases = [
+        (InitializeFundArgs { name: "   ".to_string(), ..fund_args(0) }, FundrError::NameEmpty),
+        (
+            InitializeFundArgs { name: "x".repeat(MAX_NAME_LEN + 1), ..fund_args(0) },
+            FundrError::NameTooLong,
+        ),
+        (
+            InitializeFundArgs { description: "x".repeat(MAX_DESCRIPTION_LEN + 1), ..fund_args(0) },
+            FundrError::DescriptionTooLong,
+        ),
+        (InitializeFundArgs { min_deposit: 0, ..fund_args(0) }, FundrError::InvalidMinDeposit),
+        (InitializeFundArgs { performance_fee: 2001, ..fund_args(0) }, FundrError::ExcessiveFees),
+        (InitializeFundArgs { fee_period: MIN_FEE_PERIOD - 1, ..fund_args(0) }, FundrError::InvalidFeePeriod),
+        (InitializeFundArgs { fee_period: MAX_FEE_PERIOD + 1, ..fund_args(0) }, FundrError::InvalidFeePeriod),
+        (fund_args(MIN_SEED_DEPOSIT - 1), FundrError::InsufficientSeedDeposit),
+        (
+            InitializeFundArgs { min_deposit: SOL, ..fund_args(SOL / 2) },
+            FundrError::InsufficientSeedDeposit,
+        ),
+    ];
+
+    for (args, error) in cases {
+        let mut bank = TestBank::new();
+        let manager = bank.wallet(10 * SOL);
+
+        assert_fundr_error(bank.process(&instructions::initialize_fund(&manager, 0, args)), error);
+        assert!(bank.account(&pda::fund(&manager).0).is_none());
+        assert_eq!(bank.lamports(&manager), 10 * SOL);
+    }
+}
+
+#[test]
+fn initialize_fund_returns_lamports_sent_to_the_vault_early() {
+    let reserve = Rent::default().minimum_balance(0);
+    for prefunded in [reserve / 2, reserve, 2 * SOL] {
+        let mut bank = TestBank::new();
+        let manager = bank.wallet(10 * SOL);
+        let fund = pda::fund(&manager).0;
+        let vault = pda::vault(&fund).0;
+        bank.airdrop(&vault, prefunded);
+
+        bank.process(&instructions::initialize_fund(&manager, 0, fund_args(SOL))).unwrap();
+
+        // Whatever was there only offsets the reserve the manager would otherwise pay
+        let rent = bank.lamports(&fund)
+            + bank.lamports(&pda::stake(&fund, &manager).0)
+            + bank.lamports(&pda::manager_profile(&manager).0);
+        assert_eq!(bank.lamports(&vault), SOL + reserve);
+        assert_eq!(bank.lamports(&manager), 10 * SOL + prefunded - SOL - reserve - rent);
+        assert_eq!(bank.fund(&fund).total_assets, SOL / 100 * 99);
+    }
+}
+
+#[test]
+fn initialize_fund_rejects_a_claimed_vault() {
+    let mut bank = TestBank::new();
+    let manager = bank.wallet(10 * SOL);
+    let fund = pda::fund(&manager).0;
+    let vault = pda::vault(&fund).0;
+    let account = TestAccount { lamports: SOL, data: vec![0; 8], owner: fundr::ID, executable: false };
+    bank.set_account(vault, account);
+
+    let result = bank.process(&instructions::initialize_fund(&manager, 0, fund_args(SOL)));
+
+    assert_fundr_error(result, FundrError::VaultNotFresh);
+}
+
+#[test]
+fn initialize_fund_cannot_run_twice() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, 0);
+    let before = bank.fund(&fund);
+
+    let result = bank.process(&instructions::initialize_fund(&manager, 0, fund_args(SOL)));
+
+    assert!(result.is_err());
+    assert_eq!(bank.fund(&fund).created_at, before.created_at);
+    assert_eq!(bank.fund(&fund).total_shares, 0);
+}
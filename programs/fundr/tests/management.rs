@@ -0,0 +1,200 @@
+mod common;
+
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use fundr::{FundMode, FundrError, MAX_URI_LEN, MIN_FEE_CLAIM_DELAY, MIN_FEE_PERIOD, NAV_SCALE};
+
+#[test]
+fn collect_fees_escrows_the_performance_fee_on_gains() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    invest(&mut bank, &fund, 9 * SOL);
+    simulate_gain(&mut bank, &fund, 5 * SOL);
+    let before = bank.fund(&fund);
+    let fee = preview::performance_fee(&before).unwrap();
+    let nav = preview::nav_per_share(&before).unwrap();
+    let balance = bank.lamports(&manager);
+    let vault = bank.lamports(&pda::vault(&fund).0);
+    assert!(fee > 0);
+
+    bank.warp_to(bank.now() + MIN_FEE_PERIOD);
+    bank.process(&instructions::collect_fees(&fund, &manager)).unwrap();
+
+    // The fee leaves investor assets but stays in the vault until claimed
+    let after = bank.fund(&fund);
+    assert_eq!(bank.lamports(&manager), balance);
+    assert_eq!(bank.lamports(&pda::vault(&fund).0), vault);
+    assert_eq!(after.total_assets, before.total_assets - fee);
+    assert_eq!(after.pending_fees, fee);
+    assert_eq!(after.fees_claimable_at, bank.now() + MIN_FEE_CLAIM_DELAY);
+    assert_eq!(after.high_water_mark, nav);
+    assert_eq!(after.last_fee_collection, bank.now());
+
+    // Nothing is owed again until NAV climbs back above the new high water mark
+    assert_eq!(preview::performance_fee(&after).unwrap(), 0);
+    bank.warp_to(bank.now() + MIN_FEE_PERIOD);
+    bank.process(&instructions::collect_fees(&fund, &manager)).unwrap();
+    assert_eq!(bank.fund(&fund).pending_fees, fee);
+}
+
+#[test]
+fn collect_fees_without_gains_escrows_nothing() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    invest(&mut bank, &fund, 9 * SOL);
+
+    bank.warp_to(bank.now() + MIN_FEE_PERIOD);
+    bank.process(&instructions::collect_fees(&fund, &manager)).unwrap();
+
+    let state = bank.fund(&fund);
+    assert_eq!(state.pending_fees, 0);
+    assert_eq!(state.fees_claimable_at, 0);
+    assert_eq!(state.high_water_mark, NAV_SCALE);
+}
+
+#[test]
+fn collect_fees_rejects_other_signers() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (_, fund) = create_fund(&mut bank, SOL);
+    simulate_gain(&mut bank, &fund, SOL);
+    let attacker = bank.wallet(SOL);
+
+    let result = bank.process(&instructions::collect_fees(&fund, &attacker));
+
+    assert_fundr_error(result, FundrError::UnauthorizedManager);
+    assert_eq!(bank.fund(&fund).pending_fees, 0);
+}
+
+#[test]
+fn update_fund_mode_switches_allocation_mode() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+
+    bank.process(&instructions::update_fund_mode(&fund, &manager, FundMode::Auto)).unwrap();
+    assert_eq!(bank.fund(&fund).fund_mode, FundMode::Auto);
+
+    bank.process(&instructions::update_fund_mode(&fund, &manager, FundMode::Manual)).unwrap();
+    assert_eq!(bank.fund(&fund).fund_mode, FundMode::Manual);
+}
+
+#[test]
+fn update_fund_mode_rejects_other_signers() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let attacker = bank.wallet(SOL);
+
+    let result = bank.process(&instructions::update_fund_mode(&fund, &attacker, FundMode::Auto));
+
+    assert_fundr_error(result, FundrError::Unauthorized);
+    assert_eq!(bank.fund(&fund).fund_mode, FundMode::Manual);
+}
+
+#[test]
+fn update_fund_metadata_sets_only_given_fields() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let uri = "https://fundr.example/alpha.json".to_string();
+
+    bank.process(&instructions::update_fund_metadata(&fund, &manager, None, None, Some(uri.clone())))
+        .unwrap();
+
+    let state = bank.fund(&fund);
+    assert_eq!(state.uri, uri);
+    assert_eq!(state.name, "Alpha");
+    assert_eq!(state.description, "Blue chip Solana tokens");
+}
+
+#[test]
+fn update_fund_metadata_rejects_invalid_fields() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let cases = [
+        (Some("x".repeat(fundr::MAX_NAME_LEN + 1)), None, None, FundrError::NameTooLong),
+        (Some("   ".to_string()), None, None, FundrError::NameEmpty),
+        (None, Some("x".repeat(fundr::MAX_DESCRIPTION_LEN + 1)), None, FundrError::DescriptionTooLong),
+        (None, None, Some("x".repeat(MAX_URI_LEN + 1)), FundrError::UriTooLong),
+    ];
+
+    for (name, description, uri, error) in cases {
+        let ix = instructions::update_fund_metadata(&fund, &manager, name, description, uri);
+        assert_fundr_error(bank.process(&ix), error);
+    }
+
+    let attacker = bank.wallet(SOL);
+    let ix = instructions::update_fund_metadata(&fund, &attacker, Some("Mine".to_string()), None, None);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+}
+
+#[test]
+fn rebalance_checks_the_output_mint() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    let other = bank.create_mint(&spl_token::ID);
+    publish_price(&mut bank, &admin, &mint);
+    publish_price(&mut bank, &admin, &other);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &mint, 5_000)).unwrap();
+
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &mint, SOL);
+    bank.process(&ix).unwrap();
+    assert_eq!(bank.portfolio(&pda::portfolio(&fund).0).positions()[0].last_rebalanced_at, bank.now());
+
+    // The mint account passed must be the one named in the arguments
+    let mut mismatched = ix.clone();
+    mismatched.accounts[2].pubkey = other;
+    assert_fundr_error(bank.process(&mismatched), FundrError::InvalidTokenMint);
+
+    // Only tokens held in the portfolio can be bought
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &other, SOL);
+    assert_fundr_error(bank.process(&ix), FundrError::PositionNotFound);
+
+    let attacker = bank.wallet(SOL);
+    let ix = instructions::rebalance(&fund, &attacker, &spl_token::ID, &native_mint::ID, SOL, &mint, SOL);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+}
+
+#[test]
+fn rebalance_needs_a_minimum_left_after_transfer_fees() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    let taxed = bank.create_mint_with_transfer_fee(100);
+    publish_price(&mut bank, &admin, &mint);
+    publish_price(&mut bank, &admin, &taxed);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &mint, 5_000)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &taxed, 5_000)).unwrap();
+
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &mint, 0);
+    assert_fundr_error(bank.process(&ix), FundrError::SlippageExceeded);
+
+    // The 1% fee on a single unit rounds up to the whole unit
+    let ix = instructions::rebalance(&fund, &manager, &spl_token_2022::ID, &native_mint::ID, SOL, &taxed, 1);
+    assert_fundr_error(bank.process(&ix), FundrError::SlippageExceeded);
+}
+
+#[test]
+fn rebalance_only_sells_into_sol_while_winding_down() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    bank.create_mint_at(&native_mint::ID, &spl_token::ID);
+    publish_price(&mut bank, &admin, &mint);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &mint, 5_000)).unwrap();
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &mint, SOL);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidWindDownSwap);
+
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &mint, SOL, &native_mint::ID, SOL);
+    bank.process(&ix).unwrap();
+}
@@ -0,0 +1,236 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::rent::Rent;
+use anchor_lang::{AccountSerialize, AnchorSerialize, Discriminator, Space};
+use common::*;
+use fundr::legacy::{FundV0, UserStakeV0};
+use fundr::{Fund, FundrError, UserStake, FUND_VERSION, USER_STAKE_VERSION};
+
+/// Rewrite an account as the old program left it: discriminator, legacy fields, zero
+/// padding up to the legacy size, and rent for exactly that size
+fn write_legacy(bank: &mut TestBank, key: &Pubkey, discriminator: [u8; 8], body: impl AnchorSerialize, space: usize) {
+    let mut data = discriminator.to_vec();
+    body.serialize(&mut data).unwrap();
+    data.resize(space, 0);
+
+    let mut account = bank.account(key).unwrap().clone();
+    account.lamports = Rent::default().minimum_balance(space);
+    account.data = data;
+    bank.set_account(*key, account);
+}
+
+fn downgrade_fund(bank: &mut TestBank, key: &Pubkey) {
+    let fund = bank.fund(key);
+    let legacy = FundV0 {
+        authority: fund.authority,
+        name: fund.name,
+        description: fund.description,
+        performance_fee: fund.performance_fee,
+        min_deposit: fund.min_deposit,
+        fund_mode: fund.fund_mode,
+        total_shares: fund.total_shares,
+        total_assets: fund.total_assets,
+        investor_count: fund.investor_count,
+        bump: fund.bump,
+        created_at: fund.created_at,
+        last_fee_collection: fund.last_fee_collection,
+        high_water_mark: fund.high_water_mark,
+    };
+    write_legacy(bank, key, Fund::DISCRIMINATOR, legacy, 8 + FundV0::INIT_SPACE);
+}
+
+fn downgrade_stake(bank: &mut TestBank, key: &Pubkey) {
+    let stake = bank.stake(key);
+    let legacy = UserStakeV0 {
+        user: stake.user,
+        fund: stake.fund,
+        shares: stake.shares,
+        total_deposited: stake.total_deposited,
+        last_deposit: stake.last_deposit,
+        last_withdrawal: stake.last_withdrawal,
+    };
+    write_legacy(bank, key, UserStake::DISCRIMINATOR, legacy, 8 + UserStakeV0::INIT_SPACE);
+}
+
+#[test]
+fn migrate_fund_upgrades_a_legacy_fund_in_place() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    invest(&mut bank, &fund, 2 * SOL);
+    let before = bank.fund(&fund);
+    downgrade_fund(&mut bank, &fund);
+    let legacy_rent = bank.lamports(&fund);
+    let investor = bank.wallet(10 * SOL);
+
+    // The old account is too small to decode until migrated
+    assert!(bank.process(&instructions::deposit(&fund, &investor, SOL, 0)).is_err());
+
+    let payer = bank.wallet(SOL);
+    bank.process(&instructions::migrate_fund(&fund, &payer)).unwrap();
+
+    let space = 8 + Fund::INIT_SPACE;
+    let rent = Rent::default().minimum_balance(space);
+    assert_eq!(bank.account(&fund).unwrap().data.len(), space);
+    assert_eq!(bank.lamports(&fund), rent);
+    assert_eq!(bank.lamports(&payer), SOL - (rent - legacy_rent));
+
+    let after = bank.fund(&fund);
+    assert_eq!(after.version, FUND_VERSION);
+    assert_eq!(after.authority, before.authority);
+    assert_eq!(after.name, before.name);
+    assert_eq!(after.total_shares, before.total_shares);
+    assert_eq!(after.total_assets, before.total_assets);
+    assert_eq!(after.investor_count, before.investor_count);
+    assert_eq!(after.high_water_mark, before.high_water_mark);
+
+    bank.process(&instructions::deposit(&fund, &investor, SOL, 0)).unwrap();
+    assert_eq!(bank.fund(&fund).investor_count, before.investor_count + 1);
+}
+
+#[test]
+fn migrate_stake_upgrades_a_legacy_stake_in_place() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, 2 * SOL);
+    let stake = pda::stake(&fund, &investor).0;
+    let before = bank.stake(&stake);
+    downgrade_stake(&mut bank, &stake);
+
+    let result = bank.process(&instructions::withdraw(&fund, &investor, before.shares, 0));
+    assert!(result.is_err());
+
+    bank.process(&instructions::migrate_stake(&fund, &investor, &investor)).unwrap();
+
+    let space = 8 + UserStake::INIT_SPACE;
+    assert_eq!(bank.account(&stake).unwrap().data.len(), space);
+    assert_eq!(bank.lamports(&stake), Rent::default().minimum_balance(space));
+    let after = bank.stake(&stake);
+    assert_eq!(after.version, USER_STAKE_VERSION);
+    assert_eq!((after.user, after.fund), (investor, fund));
+    assert_eq!(after.shares, before.shares);
+    assert_eq!(after.total_deposited, before.total_deposited);
+    assert_eq!(after.last_deposit, before.last_deposit);
+
+    bank.process(&instructions::withdraw(&fund, &investor, before.shares, 0)).unwrap();
+    assert_eq!(bank.stake(&stake).shares, 0);
+}
+
+#[test]
+fn new_accounts_start_at_the_current_version() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+
+    assert_eq!(bank.fund(&fund).version, FUND_VERSION);
+    assert_eq!(bank.stake(&pda::stake(&fund, &manager).0).version, USER_STAKE_VERSION);
+    assert_eq!(bank.stake(&pda::stake(&fund, &investor).0).version, USER_STAKE_VERSION);
+
+    let result = bank.process(&instructions::migrate_fund(&fund, &manager));
+    assert_fundr_error(result, FundrError::AlreadyMigrated);
+    let result = bank.process(&instructions::migrate_stake(&fund, &investor, &investor));
+    assert_fundr_error(result, FundrError::AlreadyMigrated);
+}
+
+/// Overwrite `key` with `state`, keeping the account's size and rent
+fn rewrite(bank: &mut TestBank, key: &Pubkey, state: impl AccountSerialize) {
+    let mut account = bank.account(key).unwrap().clone();
+    account.data.clear();
+    state.try_serialize(&mut account.data).unwrap();
+    bank.set_account(*key, account);
+}
+
+#[test]
+fn funds_at_an_older_version_are_rejected() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let investor = bank.wallet(10 * SOL);
+
+    let mut stale = bank.fund(&fund);
+    stale.version = 0;
+    rewrite(&mut bank, &fund, stale);
+
+    let result = bank.process(&instructions::deposit(&fund, &investor, SOL, 0));
+    assert_fundr_error(result, FundrError::AccountNotMigrated);
+    let result = bank.process(&instructions::update_fund_mode(&fund, &manager, fundr::FundMode::Auto));
+    assert_fundr_error(result, FundrError::AccountNotMigrated);
+}
+
+#[test]
+fn stakes_at_an_older_version_are_rejected() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    let stake = pda::stake(&fund, &investor).0;
+
+    let mut stale = bank.stake(&stake);
+    stale.version = 0;
+    let shares = stale.shares;
+    rewrite(&mut bank, &stake, stale);
+
+    let result = bank.process(&instructions::deposit(&fund, &investor, SOL / 2, 0));
+    assert_fundr_error(result, FundrError::AccountNotMigrated);
+    let result = bank.process(&instructions::withdraw(&fund, &investor, shares, 0));
+    assert_fundr_error(result, FundrError::AccountNotMigrated);
+    let result = bank.process(&instructions::create_proposal(&fund, &investor, 0, fundr::ProposalAction::Pause));
+    assert_fundr_error(result, FundrError::AccountNotMigrated);
+}
+
+#[test]
+fn accounts_from_a_newer_program_are_not_migrated() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let stake = pda::stake(&fund, &manager).0;
+
+    let mut newer = bank.fund(&fund);
+    newer.version = FUND_VERSION + 1;
+    rewrite(&mut bank, &fund, newer);
+    let mut newer = bank.stake(&stake);
+    newer.version = USER_STAKE_VERSION + 1;
+    rewrite(&mut bank, &stake, newer);
+
+    let result = bank.process(&instructions::migrate_fund(&fund, &manager));
+    assert_fundr_error(result, FundrError::UnsupportedAccountVersion);
+    let result = bank.process(&instructions::migrate_stake(&fund, &manager, &manager));
+    assert_fundr_error(result, FundrError::UnsupportedAccountVersion);
+    let result = bank.process(&instructions::update_fund_mode(&fund, &manager, fundr::FundMode::Auto));
+    assert_fundr_error(result, FundrError::AccountNotMigrated);
+}
+
+#[test]
+fn migration_rejects_other_accounts() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let stake = pda::stake(&fund, &manager).0;
+    downgrade_stake(&mut bank, &stake);
+
+    // A stake is never reinterpreted as a fund, or the other way around
+    let result = bank.process(&instructions::migrate_fund(&stake, &manager));
+    assert_fundr_error(result, FundrError::InvalidAccount);
+    let mut ix = instructions::migrate_stake(&fund, &manager, &manager);
+    ix.accounts[0].pubkey = fund;
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAccount);
+
+    // Only accounts owned by the program can be rewritten
+    let result = bank.process(&instructions::migrate_fund(&manager, &manager));
+    assert_fundr_error(result, FundrError::InvalidAccount);
+}
+
+#[test]
+fn closing_a_fund_opened_before_profiles_burns_its_index() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, 0);
+    let payer = bank.wallet(SOL);
+    downgrade_fund(&mut bank, &fund);
+    // The old program kept no count of the funds a manager opened
+    bank.remove_account(&pda::manager_profile(&manager).0);
+    bank.process(&instructions::migrate_fund(&fund, &payer)).unwrap();
+
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    bank.process(&instructions::close_fund(&fund, &manager, &manager, &admin, false, None, &spl_token::ID, &[])).unwrap();
+
+    assert_eq!(bank.manager_profile(&manager).fund_count, 1);
+    assert!(bank.process(&instructions::initialize_fund(&manager, 0, fund_args(SOL))).is_err());
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_spl::token::spl_token;
+use common::*;
+use fundr::{FundMode, FundStatus, FundrError, ManagerAction, MAX_SIGNERS, MIN_FEE_PERIOD};
+
+/// A fund whose sensitive actions need 2 of 3 signers. Returns (manager, fund, signers).
+fn fund_with_signers(bank: &mut TestBank) -> (Pubkey, Pubkey, Vec<Pubkey>) {
+    let (manager, fund) = create_fund(bank, SOL);
+    let signers: Vec<Pubkey> = (0..3).map(|_| bank.wallet(SOL)).collect();
+    bank.process(&instructions::create_signer_set(&fund, &manager, signers.clone(), 2)).unwrap();
+    (manager, fund, signers)
+}
+
+/// Propose `action` and approve it by `approvers` (the first of whom proposes).
+/// Returns the action number.
+fn approved(bank: &mut TestBank, fund: &Pubkey, approvers: &[Pubkey], action: ManagerAction) -> u64 {
+    let id = bank.signer_set(fund).action_count;
+    bank.process(&instructions::propose_action(fund, &approvers[0], id, action)).unwrap();
+    for signer in &approvers[1..] {
+        bank.process(&instructions::approve_action(fund, id, signer)).unwrap();
+    }
+    id
+}
+
+/// Execute action `id` from a fresh wallet, passing the accounts `action` needs
+fn execute(bank: &mut TestBank, fund: &Pubkey, id: u64, action: ManagerAction) -> ProgramResult {
+    let executor = bank.wallet(SOL);
+    bank.process(&instructions::execute_action(fund, id, &action, &executor))
+}
+
+#[test]
+fn create_signer_set_validates_the_set() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let [a, b] = [bank.wallet(SOL), bank.wallet(SOL)];
+
+    let ix = instructions::create_signer_set(&fund, &a, vec![a, b], 1);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+
+    let too_many: Vec<Pubkey> = (0..=MAX_SIGNERS).map(|_| Pubkey::new_unique()).collect();
+    for (signers, threshold) in [(vec![a, b], 0), (vec![a, b], 3), (vec![a, a], 2), (vec![], 0), (too_many, 1)] {
+        let ix = instructions::create_signer_set(&fund, &manager, signers, threshold);
+        assert_fundr_error(bank.process(&ix), FundrError::InvalidSignerSet);
+    }
+    assert!(!bank.fund(&fund).multisig);
+
+    bank.process(&instructions::create_signer_set(&fund, &manager, vec![a, b], 2)).unwrap();
+    let set = bank.signer_set(&fund);
+    assert_eq!((set.signers, set.threshold), (vec![a, b], 2));
+    assert!(bank.fund(&fund).multisig);
+
+    // There is only ever one set; later changes go through actions
+    let ix = instructions::create_signer_set(&fund, &manager, vec![manager], 1);
+    assert_fundr_error(bank.process(&ix), FundrError::MultisigRequired);
+}
+
+#[test]
+fn sensitive_actions_need_the_signer_set() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund, _) = fund_with_signers(&mut bank);
+    let mint = bank.create_mint(&spl_token::ID);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+
+    let result = bank.process(&instructions::collect_fees(&fund, &manager));
+    assert_fundr_error(result, FundrError::MultisigRequired);
+    let result = bank.process(&instructions::update_fund_mode(&fund, &manager, FundMode::Auto));
+    assert_fundr_error(result, FundrError::MultisigRequired);
+    let result = bank.process(&instructions::set_position(&fund, &manager, &mint, 5_000));
+    assert_fundr_error(result, FundrError::MultisigRequired);
+    let result = bank.process(&instructions::remove_position(&fund, &manager, &mint));
+    assert_fundr_error(result, FundrError::MultisigRequired);
+    let result = bank.process(&instructions::initiate_wind_down(&fund, &manager));
+    assert_fundr_error(result, FundrError::MultisigRequired);
+    let result = bank.process(&instructions::claim_fees(&fund, &manager, None));
+    assert_fundr_error(result, FundrError::MultisigRequired);
+    // The owner's key alone can't trade either
+    assert!(!bank.fund(&fund).can_trade(&manager));
+}
+
+#[test]
+fn actions_execute_once_approved() {
+    let mut bank = TestBank::new();
+    let (_, fund, signers) = fund_with_signers(&mut bank);
+    let outsider = bank.wallet(SOL);
+    let action = ManagerAction::SetFundMode { mode: FundMode::Auto };
+
+    let result = bank.process(&instructions::propose_action(&fund, &outsider, 0, action));
+    assert_fundr_error(result, FundrError::NotASigner);
+
+    let id = approved(&mut bank, &fund, &signers[..1], action);
+    assert_fundr_error(execute(&mut bank, &fund, id, action), FundrError::ActionNotApproved);
+    let result = bank.process(&instructions::approve_action(&fund, id, &outsider));
+    assert_fundr_error(result, FundrError::NotASigner);
+    let result = bank.process(&instructions::approve_action(&fund, id, &signers[0]));
+    assert_fundr_error(result, FundrError::AlreadyApproved);
+
+    bank.process(&instructions::approve_action(&fund, id, &signers[2])).unwrap();
+    execute(&mut bank, &fund, id, action).unwrap();
+    assert_eq!(bank.fund(&fund).fund_mode, FundMode::Auto);
+
+    assert_fundr_error(execute(&mut bank, &fund, id, action), FundrError::ActionAlreadyExecuted);
+    let result = bank.process(&instructions::approve_action(&fund, id, &signers[1]));
+    assert_fundr_error(result, FundrError::ActionAlreadyExecuted);
+}
+
+#[test]
+fn allocation_and_fee_actions() {
+    let mut bank = TestBank::new();
+    create_config(&mut bank);
+    let (manager, fund, signers) = fund_with_signers(&mut bank);
+    let mint = bank.create_mint(&spl_token::ID);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+
+    let add = ManagerAction::SetPosition { mint, target_weight_bps: 5_000 };
+    let id = approved(&mut bank, &fund, &signers[..2], add);
+    // The accounts the action touches must be passed along
+    let result = execute(&mut bank, &fund, id, ManagerAction::SetFundMode { mode: FundMode::Auto });
+    assert_fundr_error(result, FundrError::MissingActionAccount);
+    execute(&mut bank, &fund, id, add).unwrap();
+    let portfolio = bank.portfolio(&pda::portfolio(&fund).0);
+    assert_eq!(portfolio.positions()[0].mint, mint);
+    assert_eq!(portfolio.positions()[0].target_weight_bps, 5_000);
+
+    let remove = ManagerAction::RemovePosition { mint };
+    let id = approved(&mut bank, &fund, &signers[1..], remove);
+    execute(&mut bank, &fund, id, remove).unwrap();
+    assert!(bank.portfolio(&pda::portfolio(&fund).0).positions().is_empty());
+
+    invest(&mut bank, &fund, 9 * SOL);
+    simulate_gain(&mut bank, &fund, 5 * SOL);
+    let fee = preview::performance_fee(&bank.fund(&fund)).unwrap();
+    let id = approved(&mut bank, &fund, &signers[..2], ManagerAction::CollectFees);
+    bank.warp_to(bank.now() + MIN_FEE_PERIOD);
+    execute(&mut bank, &fund, id, ManagerAction::CollectFees).unwrap();
+    assert_eq!(bank.fund(&fund).pending_fees, fee);
+}
+
+#[test]
+fn changing_the_signer_set_voids_pending_approvals() {
+    let mut bank = TestBank::new();
+    let (manager, fund, signers) = fund_with_signers(&mut bank);
+    let newcomer = bank.wallet(SOL);
+    let successor = bank.wallet(SOL);
+
+    let handover = ManagerAction::SetAuthority { new_authority: successor };
+    let pending = approved(&mut bank, &fund, &signers[..1], handover);
+
+    let add = ManagerAction::AddSigner { signer: newcomer };
+    let id = approved(&mut bank, &fund, &signers[1..], add);
+    execute(&mut bank, &fund, id, add).unwrap();
+    let set = bank.signer_set(&fund);
+    assert_eq!(set.signers.len(), 4);
+    assert_eq!(set.nonce, 1);
+
+    // Approvals given under the old set no longer count
+    let result = bank.process(&instructions::approve_action(&fund, pending, &newcomer));
+    assert_fundr_error(result, FundrError::StaleAction);
+    assert_fundr_error(execute(&mut bank, &fund, pending, handover), FundrError::StaleAction);
+
+    // The set can't be left unable to reach its threshold
+    let raise = ManagerAction::SetThreshold { threshold: 5 };
+    let id = approved(&mut bank, &fund, &[newcomer, signers[0]], raise);
+    assert_fundr_error(execute(&mut bank, &fund, id, raise), FundrError::InvalidSignerSet);
+
+    let remove = ManagerAction::RemoveSigner { signer: signers[2] };
+    let id = approved(&mut bank, &fund, &[newcomer, signers[0]], remove);
+    execute(&mut bank, &fund, id, remove).unwrap();
+    assert_eq!(bank.signer_set(&fund).signers, vec![signers[0], signers[1], newcomer]);
+
+    let id = approved(&mut bank, &fund, &[newcomer, signers[1]], handover);
+    execute(&mut bank, &fund, id, handover).unwrap();
+    let state = bank.fund(&fund);
+    assert_eq!(state.authority, successor);
+    assert_eq!(state.seed_key(), manager);
+}
+
+#[test]
+fn winding_down_claiming_and_closing_need_approval() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund, signers) = fund_with_signers(&mut bank);
+    let investor = invest(&mut bank, &fund, 9 * SOL);
+    simulate_gain(&mut bank, &fund, 5 * SOL);
+    bank.warp_to(bank.now() + MIN_FEE_PERIOD);
+    let id = approved(&mut bank, &fund, &signers[..2], ManagerAction::CollectFees);
+    execute(&mut bank, &fund, id, ManagerAction::CollectFees).unwrap();
+
+    let id = approved(&mut bank, &fund, &signers[..2], ManagerAction::WindDown);
+    execute(&mut bank, &fund, id, ManagerAction::WindDown).unwrap();
+    assert_eq!(bank.fund(&fund).status, FundStatus::WindingDown);
+
+    // Claiming pays the manager, so the manager redeems the approval itself
+    let id = approved(&mut bank, &fund, &signers[..1], ManagerAction::ClaimFees);
+    bank.warp_to(bank.fund(&fund).fees_claimable_at);
+    let result = bank.process(&instructions::claim_fees(&fund, &manager, Some(id)));
+    assert_fundr_error(result, FundrError::ActionNotApproved);
+    bank.process(&instructions::approve_action(&fund, id, &signers[2])).unwrap();
+    assert_fundr_error(execute(&mut bank, &fund, id, ManagerAction::ClaimFees), FundrError::ExecutedByManager);
+    let close = approved(&mut bank, &fund, &signers[..2], ManagerAction::CloseFund);
+    let result = bank.process(&instructions::claim_fees(&fund, &manager, Some(close)));
+    assert_fundr_error(result, FundrError::InvalidAccount);
+    bank.process(&instructions::claim_fees(&fund, &manager, Some(id))).unwrap();
+    assert_eq!(bank.fund(&fund).pending_fees, 0);
+    let result = bank.process(&instructions::claim_fees(&fund, &manager, Some(id)));
+    assert_fundr_error(result, FundrError::ActionAlreadyExecuted);
+
+    let cranker = bank.wallet(SOL);
+    for holder in [investor, manager] {
+        bank.process(&instructions::force_distribute(&fund, &holder, &cranker)).unwrap();
+    }
+    let ix = instructions::close_fund(&fund, &manager, &manager, &admin, false, None, &spl_token::ID, &[]);
+    assert_fundr_error(bank.process(&ix), FundrError::MultisigRequired);
+    let ix = instructions::close_fund(&fund, &manager, &manager, &admin, false, Some(close), &spl_token::ID, &[]);
+    bank.process(&ix).unwrap();
+    assert!(bank.account(&fund).is_none());
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token::spl_token::{self, native_mint};
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use fundr::{FundrError, DEFAULT_MAX_PRICE_AGE, DEFAULT_SLIPPAGE_BPS, MAX_PRICE_AGE, MAX_SLIPPAGE_BPS, PRICE_SCALE};
+
+/// A fund holding a position in a token priced at 2 lamports per unit.
+/// Returns (admin, manager, fund, mint).
+fn priced_fund(bank: &mut TestBank) -> (Pubkey, Pubkey, Pubkey, Pubkey) {
+    let admin = create_config(bank);
+    let (manager, fund) = create_fund(bank, 10 * SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    bank.create_mint_at(&native_mint::ID, &spl_token::ID);
+    bank.process(&instructions::publish_price(&admin, &mint, 2 * PRICE_SCALE)).unwrap();
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &mint, 5_000)).unwrap();
+    (admin, manager, fund, mint)
+}
+
+#[test]
+fn only_the_admin_sets_prices_and_slippage() {
+    let mut bank = TestBank::new();
+    let (admin, manager, _, mint) = priced_fund(&mut bank);
+    let config = bank.config();
+    assert_eq!((config.slippage_bps, config.max_price_age), (DEFAULT_SLIPPAGE_BPS, DEFAULT_MAX_PRICE_AGE));
+    let feed = bank.price_feed(&mint);
+    assert_eq!((feed.mint, feed.price, feed.updated_at), (mint, 2 * PRICE_SCALE, bank.now()));
+
+    let result = bank.process(&instructions::publish_price(&manager, &mint, PRICE_SCALE));
+    assert_fundr_error(result, FundrError::Unauthorized);
+    let result = bank.process(&instructions::publish_price(&admin, &mint, 0));
+    assert_fundr_error(result, FundrError::InvalidPrice);
+
+    let result = bank.process(&instructions::set_oracle_params(&manager, 0, MAX_PRICE_AGE));
+    assert_fundr_error(result, FundrError::Unauthorized);
+    for (slippage_bps, max_price_age) in [(MAX_SLIPPAGE_BPS + 1, 60), (0, 0), (0, MAX_PRICE_AGE + 1)] {
+        let result = bank.process(&instructions::set_oracle_params(&admin, slippage_bps, max_price_age));
+        assert_fundr_error(result, FundrError::InvalidOracleParams);
+    }
+    bank.process(&instructions::set_oracle_params(&admin, MAX_SLIPPAGE_BPS, MAX_PRICE_AGE)).unwrap();
+    let config = bank.config();
+    assert_eq!((config.slippage_bps, config.max_price_age), (MAX_SLIPPAGE_BPS, MAX_PRICE_AGE));
+}
+
+#[test]
+fn rebalance_must_ask_for_fair_value_less_slippage() {
+    let mut bank = TestBank::new();
+    let (admin, manager, fund, mint) = priced_fund(&mut bank);
+
+    // SOL buys half as many units at 2 lamports each; 1% slippage leaves 0.495 SOL worth
+    let floor = preview::minimum_amount_out(&bank.config(), &bank.price_feed(&mint), &native_mint::ID, SOL, None).unwrap();
+    assert_eq!(floor, SOL / 2 * 99 / 100);
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &mint, floor - 1);
+    assert_fundr_error(bank.process(&ix), FundrError::MinimumOutputBelowFairValue);
+    bank.process(&instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &mint, floor)).unwrap();
+
+    // Selling is bounded the same way, in lamports
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &mint, SOL, &native_mint::ID, 2 * SOL * 99 / 100 - 1);
+    assert_fundr_error(bank.process(&ix), FundrError::MinimumOutputBelowFairValue);
+
+    // A wider protocol tolerance lets the same trade through
+    bank.process(&instructions::set_oracle_params(&admin, MAX_SLIPPAGE_BPS, DEFAULT_MAX_PRICE_AGE)).unwrap();
+    bank.process(&ix).unwrap();
+
+    // Liquidating during wind-down is still bounded
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &mint, SOL, &native_mint::ID, 1);
+    assert_fundr_error(bank.process(&ix), FundrError::MinimumOutputBelowFairValue);
+}
+
+#[test]
+fn fair_value_is_checked_after_transfer_fees() {
+    let mut bank = TestBank::new();
+    let (admin, manager, fund, _) = priced_fund(&mut bank);
+    let taxed = bank.create_mint_with_transfer_fee(100);
+    bank.process(&instructions::publish_price(&admin, &taxed, 2 * PRICE_SCALE)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &taxed, 5_000)).unwrap();
+
+    // Asking for exactly the floor leaves 1% less once the mint takes its fee
+    let floor = preview::minimum_amount_out(&bank.config(), &bank.price_feed(&taxed), &native_mint::ID, SOL, None).unwrap();
+    let ix = instructions::rebalance(&fund, &manager, &spl_token_2022::ID, &native_mint::ID, SOL, &taxed, floor);
+    assert_fundr_error(bank.process(&ix), FundrError::MinimumOutputBelowFairValue);
+
+    // so the preview asks for just enough more to cover it
+    let fee = bank.transfer_fee(&taxed);
+    let gross = preview::minimum_amount_out(&bank.config(), &bank.price_feed(&taxed), &native_mint::ID, SOL, Some(&fee)).unwrap();
+    let ix = instructions::rebalance(&fund, &manager, &spl_token_2022::ID, &native_mint::ID, SOL, &taxed, gross - 1);
+    assert_fundr_error(bank.process(&ix), FundrError::MinimumOutputBelowFairValue);
+    let ix = instructions::rebalance(&fund, &manager, &spl_token_2022::ID, &native_mint::ID, SOL, &taxed, gross);
+    bank.process(&ix).unwrap();
+}
+
+#[test]
+fn stale_or_mismatched_prices_are_rejected() {
+    let mut bank = TestBank::new();
+    let (admin, manager, fund, mint) = priced_fund(&mut bank);
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &mint, SOL / 2);
+
+    let now = bank.now();
+    bank.warp_to(now + DEFAULT_MAX_PRICE_AGE + 1);
+    assert_fundr_error(bank.process(&ix), FundrError::StalePrice);
+    bank.process(&instructions::publish_price(&admin, &mint, 2 * PRICE_SCALE)).unwrap();
+    bank.process(&ix).unwrap();
+
+    // A cheaper token's feed can't stand in for the traded one
+    let cheap = bank.create_mint(&spl_token::ID);
+    bank.process(&instructions::publish_price(&admin, &cheap, 1)).unwrap();
+    let mut mismatched = ix.clone();
+    mismatched.accounts[6].pubkey = pda::price_feed(&cheap).0;
+    assert_fundr_error(bank.process(&mismatched), FundrError::InvalidPriceFeed);
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::{ProgramError, Pubkey};
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use fundr::{FundrError, MAX_POSITIONS};
+
+#[test]
+fn initialize_portfolio_starts_empty() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+
+    let portfolio = bank.portfolio(&pda::portfolio(&fund).0);
+    assert_eq!(portfolio.fund, fund);
+    assert_eq!(portfolio.bump, pda::portfolio(&fund).1);
+    assert!(portfolio.positions().is_empty());
+
+    // The PDA can only be created once
+    assert!(bank.process(&instructions::initialize_portfolio(&fund, &manager)).is_err());
+}
+
+#[test]
+fn initialize_portfolio_rejects_other_signers() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let attacker = bank.wallet(SOL);
+
+    let result = bank.process(&instructions::initialize_portfolio(&fund, &attacker));
+
+    assert_fundr_error(result, FundrError::UnauthorizedManager);
+    assert!(bank.account(&pda::portfolio(&fund).0).is_none());
+}
+
+#[test]
+fn set_position_adds_and_updates_targets() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let classic = bank.create_mint(&spl_token::ID);
+    let token_2022 = bank.create_mint(&spl_token_2022::ID);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+
+    bank.process(&instructions::set_position(&fund, &manager, &classic, 4_000)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &token_2022, 3_000)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &classic, 7_000)).unwrap();
+
+    let portfolio = bank.portfolio(&pda::portfolio(&fund).0);
+    let targets: Vec<(Pubkey, u16)> = portfolio
+        .positions()
+        .iter()
+        .map(|position| (position.mint, position.target_weight_bps))
+        .collect();
+    assert_eq!(targets, [(classic, 7_000), (token_2022, 3_000)]);
+    assert_eq!(portfolio.total_target_bps(), 10_000);
+
+    // Targets above 100% in total are rejected, whether new or updated
+    let extra = bank.create_mint(&spl_token::ID);
+    let ix = instructions::set_position(&fund, &manager, &extra, 1);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAllocation);
+    let ix = instructions::set_position(&fund, &manager, &classic, 7_001);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAllocation);
+
+    let attacker = bank.wallet(SOL);
+    let ix = instructions::set_position(&fund, &attacker, &extra, 0);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+}
+
+#[test]
+fn portfolio_holds_up_to_max_positions() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+
+    let mints: Vec<Pubkey> = (0..MAX_POSITIONS).map(|_| bank.create_mint(&spl_token::ID)).collect();
+    for mint in &mints {
+        let weight = (10_000 / MAX_POSITIONS) as u16;
+        bank.process(&instructions::set_position(&fund, &manager, mint, weight)).unwrap();
+    }
+
+    let portfolio = bank.portfolio(&pda::portfolio(&fund).0);
+    assert_eq!(portfolio.positions().len(), MAX_POSITIONS);
+
+    let overflow = bank.create_mint(&spl_token::ID);
+    let ix = instructions::set_position(&fund, &manager, &overflow, 0);
+    assert_fundr_error(bank.process(&ix), FundrError::PortfolioFull);
+
+    // Every held position stays tradeable at full capacity
+    let last = mints[MAX_POSITIONS - 1];
+    publish_price(&mut bank, &admin, &last);
+    let ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &last, SOL);
+    bank.process(&ix).unwrap();
+}
+
+#[test]
+fn set_position_requires_an_active_fund() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+
+    let result = bank.process(&instructions::set_position(&fund, &manager, &mint, 1_000));
+
+    assert_fundr_error(result, FundrError::FundNotActive);
+}
+
+#[test]
+fn remove_position_moves_the_last_position_into_its_slot() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mints: Vec<Pubkey> = (0..3).map(|_| bank.create_mint(&spl_token::ID)).collect();
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    for mint in &mints {
+        bank.process(&instructions::set_position(&fund, &manager, mint, 1_000)).unwrap();
+    }
+
+    bank.process(&instructions::remove_position(&fund, &manager, &mints[0])).unwrap();
+
+    let portfolio = bank.portfolio(&pda::portfolio(&fund).0);
+    let held: Vec<Pubkey> = portfolio.positions().iter().map(|position| position.mint).collect();
+    assert_eq!(held, [mints[2], mints[1]]);
+    assert_eq!(portfolio.positions[2].mint, Pubkey::default());
+
+    let ix = instructions::remove_position(&fund, &manager, &mints[0]);
+    assert_fundr_error(bank.process(&ix), FundrError::PositionNotFound);
+
+    let attacker = bank.wallet(SOL);
+    let ix = instructions::remove_position(&fund, &attacker, &mints[1]);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+}
+
+#[test]
+fn portfolio_of_another_fund_is_rejected() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let (other_manager, other_fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    publish_price(&mut bank, &admin, &mint);
+    bank.process(&instructions::initialize_portfolio(&other_fund, &other_manager)).unwrap();
+    bank.process(&instructions::set_position(&other_fund, &other_manager, &mint, 1_000)).unwrap();
+
+    let mut ix = instructions::rebalance(&fund, &manager, &spl_token::ID, &native_mint::ID, SOL, &mint, SOL);
+    ix.accounts[3].pubkey = pda::portfolio(&other_fund).0;
+
+    let seeds: ProgramError = anchor_lang::error::Error::from(ErrorCode::ConstraintSeeds).into();
+    assert_eq!(bank.process(&ix), Err(seeds));
+}
+
+#[test]
+fn close_fund_returns_the_portfolio_rent() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let portfolio = pda::portfolio(&fund).0;
+    let cranker = bank.wallet(SOL);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    bank.process(&instructions::force_distribute(&fund, &manager, &cranker)).unwrap();
+
+    let expected = bank.lamports(&manager) + bank.lamports(&fund) + bank.lamports(&pda::vault(&fund).0)
+        - bank.fund(&fund).platform_fees
+        + bank.lamports(&portfolio);
+    bank.process(&instructions::close_fund(&fund, &manager, &manager, &admin, true, None, &spl_token::ID, &[])).unwrap();
+
+    assert_eq!(bank.lamports(&manager), expected);
+    assert!(bank.account(&portfolio).is_none());
+}
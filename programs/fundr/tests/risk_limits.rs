@@ -0,0 +1,123 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_spl::token::spl_token::{self, native_mint};
+use common::*;
+use fundr::{FundrError, ManagerAction, TURNOVER_WINDOW};
+
+/// A fund with a priced position to trade in and out of. Returns (admin, owner, fund, mint, nav).
+fn tradable_fund(bank: &mut TestBank) -> (Pubkey, Pubkey, Pubkey, Pubkey, u64) {
+    let admin = create_config(bank);
+    let (owner, fund) = create_fund(bank, 10 * SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    bank.create_mint_at(&native_mint::ID, &spl_token::ID);
+    publish_price(bank, &admin, &mint);
+    bank.process(&instructions::initialize_portfolio(&fund, &owner)).unwrap();
+    bank.process(&instructions::set_position(&fund, &owner, &mint, 5_000)).unwrap();
+    let nav = bank.fund(&fund).total_assets;
+    (admin, owner, fund, mint, nav)
+}
+
+fn buy(fund: &Pubkey, manager: &Pubkey, mint: &Pubkey, lamports: u64) -> Instruction {
+    instructions::rebalance(fund, manager, &spl_token::ID, &native_mint::ID, lamports, mint, lamports)
+}
+
+fn sell(fund: &Pubkey, manager: &Pubkey, mint: &Pubkey, lamports: u64) -> Instruction {
+    instructions::rebalance(fund, manager, &spl_token::ID, mint, lamports, &native_mint::ID, lamports)
+}
+
+#[test]
+fn set_risk_limits_is_owner_only_and_bounded() {
+    let mut bank = TestBank::new();
+    let (admin, owner, fund, mint, _) = tradable_fund(&mut bank);
+    let stranger = bank.wallet(SOL);
+
+    let result = bank.process(&instructions::set_risk_limits(&fund, &stranger, 1_000, 0, 0));
+    assert_fundr_error(result, FundrError::UnauthorizedManager);
+    let result = bank.process(&instructions::set_risk_limits(&fund, &owner, 10_001, 0, 0));
+    assert_fundr_error(result, FundrError::InvalidRiskLimits);
+    let result = bank.process(&instructions::set_risk_limits(&fund, &owner, 0, 0, 10_001));
+    assert_fundr_error(result, FundrError::InvalidRiskLimits);
+
+    // Turnover may exceed NAV over a day
+    bank.process(&instructions::set_risk_limits(&fund, &owner, 1_000, 30_000, 4_000)).unwrap();
+    let state = bank.fund(&fund);
+    assert_eq!((state.max_trade_bps, state.max_daily_turnover_bps, state.max_position_bps), (1_000, 30_000, 4_000));
+
+    // Every trade needs a SOL leg to be sized against NAV
+    let ix = instructions::rebalance(&fund, &owner, &spl_token::ID, &mint, 1, &mint, 1);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidSwapPair);
+    publish_price(&mut bank, &admin, &native_mint::ID);
+    let ix = instructions::rebalance(&fund, &owner, &spl_token::ID, &native_mint::ID, 1, &native_mint::ID, 1);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidSwapPair);
+}
+
+#[test]
+fn single_trades_are_capped_by_nav() {
+    let mut bank = TestBank::new();
+    let (_, owner, fund, mint, nav) = tradable_fund(&mut bank);
+    bank.process(&instructions::set_risk_limits(&fund, &owner, 1_000, 0, 0)).unwrap();
+
+    bank.process(&buy(&fund, &owner, &mint, nav / 10)).unwrap();
+    assert_fundr_error(bank.process(&buy(&fund, &owner, &mint, nav / 10 + 1)), FundrError::TradeExceedsNavLimit);
+    // Sells are sized by the SOL they must return
+    bank.process(&sell(&fund, &owner, &mint, nav / 10)).unwrap();
+    assert_fundr_error(bank.process(&sell(&fund, &owner, &mint, nav / 10 + 1)), FundrError::TradeExceedsNavLimit);
+
+    // Liquidating a winding-down fund is never held back
+    bank.process(&instructions::initiate_wind_down(&fund, &owner)).unwrap();
+    bank.process(&sell(&fund, &owner, &mint, nav)).unwrap();
+}
+
+#[test]
+fn daily_turnover_rolls_over() {
+    let mut bank = TestBank::new();
+    let (admin, owner, fund, mint, nav) = tradable_fund(&mut bank);
+    bank.process(&instructions::set_risk_limits(&fund, &owner, 0, 2_000, 0)).unwrap();
+
+    bank.process(&buy(&fund, &owner, &mint, nav / 10)).unwrap();
+    bank.process(&sell(&fund, &owner, &mint, nav / 10)).unwrap();
+    assert_eq!(bank.fund(&fund).turnover, 2 * (nav / 10));
+    assert_fundr_error(bank.process(&buy(&fund, &owner, &mint, 1)), FundrError::DailyTurnoverExceeded);
+
+    // Half a day later, half of the earlier trading still counts
+    let now = bank.now();
+    bank.warp_to(now + TURNOVER_WINDOW / 2);
+    publish_price(&mut bank, &admin, &mint);
+    assert_fundr_error(bank.process(&buy(&fund, &owner, &mint, nav / 10 + 1)), FundrError::DailyTurnoverExceeded);
+    bank.process(&buy(&fund, &owner, &mint, nav / 10)).unwrap();
+
+    let now = bank.now();
+    bank.warp_to(now + TURNOVER_WINDOW);
+    publish_price(&mut bank, &admin, &mint);
+    bank.process(&buy(&fund, &owner, &mint, nav / 5)).unwrap();
+}
+
+#[test]
+fn positions_are_capped_by_cost_basis() {
+    let mut bank = TestBank::new();
+    let (_, owner, fund, mint, nav) = tradable_fund(&mut bank);
+    bank.process(&instructions::create_signer_set(&fund, &owner, vec![owner], 1)).unwrap();
+
+    // A multisig fund changes its limits through an approved action
+    let result = bank.process(&instructions::set_risk_limits(&fund, &owner, 0, 0, 3_000));
+    assert_fundr_error(result, FundrError::MultisigRequired);
+    let action = ManagerAction::SetRiskLimits { max_trade_bps: 0, max_daily_turnover_bps: 0, max_position_bps: 3_000 };
+    bank.process(&instructions::propose_action(&fund, &owner, 0, action)).unwrap();
+    bank.process(&instructions::execute_action(&fund, 0, &action, &owner)).unwrap();
+    // and its owner only trades as the delegated trader
+    let action = ManagerAction::SetTrader { trader: owner, max_trade_amount: 0 };
+    bank.process(&instructions::propose_action(&fund, &owner, 1, action)).unwrap();
+    bank.process(&instructions::execute_action(&fund, 1, &action, &owner)).unwrap();
+
+    let cap = nav * 3 / 10;
+    bank.process(&buy(&fund, &owner, &mint, cap)).unwrap();
+    assert_eq!(bank.portfolio(&pda::portfolio(&fund).0).positions()[0].cost_basis, cap);
+    assert_fundr_error(bank.process(&buy(&fund, &owner, &mint, 1)), FundrError::PositionConcentrationExceeded);
+
+    // Selling frees up room in the position
+    bank.process(&sell(&fund, &owner, &mint, nav / 10)).unwrap();
+    assert_eq!(bank.portfolio(&pda::portfolio(&fund).0).positions()[0].cost_basis, cap - nav / 10);
+    bank.process(&buy(&fund, &owner, &mint, nav / 10)).unwrap();
+}
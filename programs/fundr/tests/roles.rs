@@ -0,0 +1,149 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token::spl_token::{self, native_mint};
+use common::*;
+use fundr::{FundrError, ManagerAction, ProposalAction};
+
+/// A fund with a position to trade into. Returns (owner, fund, mint).
+fn tradable_fund(bank: &mut TestBank) -> (Pubkey, Pubkey, Pubkey) {
+    let admin = create_config(bank);
+    let (owner, fund) = create_fund(bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    publish_price(bank, &admin, &mint);
+    bank.process(&instructions::initialize_portfolio(&fund, &owner)).unwrap();
+    bank.process(&instructions::set_position(&fund, &owner, &mint, 5_000)).unwrap();
+    (owner, fund, mint)
+}
+
+#[test]
+fn trader_rebalances_within_its_limit() {
+    let mut bank = TestBank::new();
+    let (owner, fund, mint) = tradable_fund(&mut bank);
+    let trader = bank.wallet(SOL);
+
+    let result = bank.process(&instructions::set_trader(&fund, &trader, &trader, SOL));
+    assert_fundr_error(result, FundrError::UnauthorizedManager);
+    bank.process(&instructions::set_trader(&fund, &owner, &trader, SOL)).unwrap();
+    let state = bank.fund(&fund);
+    assert_eq!((state.trader, state.max_trade_amount), (trader, SOL));
+
+    bank.process(&instructions::rebalance(&fund, &trader, &spl_token::ID, &native_mint::ID, SOL, &mint, SOL)).unwrap();
+    let ix = instructions::rebalance(&fund, &trader, &spl_token::ID, &native_mint::ID, SOL + 1, &mint, SOL + 1);
+    assert_fundr_error(bank.process(&ix), FundrError::TradeTooLarge);
+    // The cap only binds the delegate
+    bank.process(&instructions::rebalance(&fund, &owner, &spl_token::ID, &native_mint::ID, 10 * SOL, &mint, 10 * SOL)).unwrap();
+
+    // Trading is all the role allows
+    let result = bank.process(&instructions::set_position(&fund, &trader, &mint, 1_000));
+    assert_fundr_error(result, FundrError::UnauthorizedManager);
+    let token_account = bank.create_token_account(&spl_token::ID, &mint, &fund, 0);
+    let ix = instructions::close_token_account(&fund, &trader, &token_account, &spl_token::ID);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+
+    bank.process(&instructions::set_trader(&fund, &owner, &Pubkey::default(), 0)).unwrap();
+    let ix = instructions::rebalance(&fund, &trader, &spl_token::ID, &native_mint::ID, 1, &mint, 1);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+}
+
+#[test]
+fn operator_cleans_up_accounts() {
+    let mut bank = TestBank::new();
+    let (owner, fund, mint) = tradable_fund(&mut bank);
+    let operator = bank.wallet(SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+    bank.process(&instructions::withdraw(&fund, &investor, shares, 0)).unwrap();
+    // Accounts for tokens the portfolio no longer holds
+    let dropped = bank.create_mint(&spl_token::ID);
+    let first = bank.create_token_account(&spl_token::ID, &dropped, &fund, 0);
+    let second = bank.create_token_account(&spl_token::ID, &dropped, &fund, 0);
+
+    let ix = instructions::close_token_account(&fund, &operator, &first, &spl_token::ID);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+
+    bank.process(&instructions::set_operator(&fund, &owner, &operator)).unwrap();
+    assert_eq!(bank.fund(&fund).operator, operator);
+    bank.process(&ix).unwrap();
+    bank.process(&instructions::close_token_accounts(&fund, &operator, &spl_token::ID, &[second])).unwrap();
+    bank.process(&instructions::reclaim_rent(&fund, &operator, &investor)).unwrap();
+    assert!(bank.account(&first).is_none());
+    assert!(bank.account(&second).is_none());
+    assert!(bank.account(&pda::stake(&fund, &investor).0).is_none());
+
+    // Cleanup doesn't extend to trading
+    let ix = instructions::rebalance(&fund, &operator, &spl_token::ID, &native_mint::ID, 1, &mint, 1);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+
+    bank.process(&instructions::set_operator(&fund, &owner, &Pubkey::default())).unwrap();
+    let token_account = bank.create_token_account(&spl_token::ID, &dropped, &fund, 0);
+    let ix = instructions::close_token_account(&fund, &operator, &token_account, &spl_token::ID);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+}
+
+#[test]
+fn roles_of_a_multisig_fund_change_through_actions() {
+    let mut bank = TestBank::new();
+    let (owner, fund, mint) = tradable_fund(&mut bank);
+    let trader = bank.wallet(SOL);
+    bank.process(&instructions::create_signer_set(&fund, &owner, vec![owner], 1)).unwrap();
+
+    let result = bank.process(&instructions::set_trader(&fund, &owner, &trader, 0));
+    assert_fundr_error(result, FundrError::MultisigRequired);
+    let result = bank.process(&instructions::set_operator(&fund, &owner, &trader));
+    assert_fundr_error(result, FundrError::MultisigRequired);
+
+    // nor can the owner trade with its key alone
+    let ix = instructions::rebalance(&fund, &owner, &spl_token::ID, &native_mint::ID, SOL, &mint, SOL);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+
+    let action = ManagerAction::SetTrader { trader, max_trade_amount: 0 };
+    bank.process(&instructions::propose_action(&fund, &owner, 0, action)).unwrap();
+    bank.process(&instructions::execute_action(&fund, 0, &action, &owner)).unwrap();
+    bank.process(&instructions::rebalance(&fund, &trader, &spl_token::ID, &native_mint::ID, 100 * SOL, &mint, 100 * SOL)).unwrap();
+}
+
+/// Assert that `fund` has no delegated roles left and its old trader can't trade
+fn assert_roles_revoked(bank: &mut TestBank, fund: &Pubkey, trader: &Pubkey, mint: &Pubkey) {
+    let state = bank.fund(fund);
+    assert_eq!((state.trader, state.operator, state.max_trade_amount), (Pubkey::default(), Pubkey::default(), 0));
+    let ix = instructions::rebalance(fund, trader, &spl_token::ID, &native_mint::ID, 1, mint, 1);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+}
+
+#[test]
+fn set_authority_action_revokes_roles() {
+    let mut bank = TestBank::new();
+    let (owner, fund, mint) = tradable_fund(&mut bank);
+    let [trader, operator, successor] = [bank.wallet(SOL), bank.wallet(SOL), bank.wallet(SOL)];
+    bank.process(&instructions::set_trader(&fund, &owner, &trader, SOL)).unwrap();
+    bank.process(&instructions::set_operator(&fund, &owner, &operator)).unwrap();
+    bank.process(&instructions::create_signer_set(&fund, &owner, vec![owner], 1)).unwrap();
+
+    let action = ManagerAction::SetAuthority { new_authority: successor };
+    bank.process(&instructions::propose_action(&fund, &owner, 0, action)).unwrap();
+    bank.process(&instructions::execute_action(&fund, 0, &action, &owner)).unwrap();
+    assert_eq!(bank.fund(&fund).authority, successor);
+    assert_roles_revoked(&mut bank, &fund, &trader, &mint);
+}
+
+#[test]
+fn governance_replacement_revokes_roles() {
+    let mut bank = TestBank::new();
+    let (owner, fund, mint) = tradable_fund(&mut bank);
+    let [trader, operator, successor] = [bank.wallet(SOL), bank.wallet(SOL), bank.wallet(SOL)];
+    bank.process(&instructions::set_trader(&fund, &owner, &trader, SOL)).unwrap();
+    bank.process(&instructions::set_operator(&fund, &owner, &operator)).unwrap();
+    let investor = invest(&mut bank, &fund, 10 * SOL);
+    bank.warp_to(bank.now() + 1);
+
+    let id = bank.fund(&fund).proposal_count;
+    let action = ProposalAction::ReplaceAuthority { new_authority: successor };
+    bank.process(&instructions::create_proposal(&fund, &investor, id, action)).unwrap();
+    let proposal = pda::proposal(&fund, id).0;
+    bank.process(&instructions::cast_vote(&fund, &proposal, &investor, true)).unwrap();
+    bank.warp_to(bank.proposal(&proposal).voting_ends_at);
+    bank.process(&instructions::execute_proposal(&fund, &proposal, &investor, false)).unwrap();
+    assert_eq!(bank.fund(&fund).authority, successor);
+    assert_roles_revoked(&mut bank, &fund, &trader, &mint);
+}
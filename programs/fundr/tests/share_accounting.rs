@@ -0,0 +1,188 @@
+//! Property tests that drive random sequences of deposits, withdrawals, fee collections
+//! and NAV changes through the program and check the share accounting after every step.
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::rent::Rent;
+use common::*;
+use fundr::{Fund, MIN_FEE_CLAIM_DELAY, MIN_FEE_PERIOD, VIRTUAL_ASSETS, VIRTUAL_SHARES};
+use fundr_client::instructions::InitializeFundArgs;
+use proptest::prelude::*;
+
+const INVESTORS: usize = 4;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Deposit { investor: usize, lamports: u64 },
+    Withdraw { investor: usize, percent: u64 },
+    WithdrawExact { investor: usize, lamports: u64 },
+    CollectFees,
+    ClaimFees,
+    Gain { lamports: u64 },
+    Loss { percent: u64 },
+}
+
+/// Amounts from a few lamports (where rounding dominates) up to hundreds of SOL
+fn lamports() -> impl Strategy<Value = u64> {
+    prop_oneof![1u64..1_000, 1_000u64..SOL, SOL..500 * SOL]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (0..INVESTORS, lamports()).prop_map(|(investor, lamports)| Op::Deposit { investor, lamports }),
+        3 => (0..INVESTORS, 1u64..=100).prop_map(|(investor, percent)| Op::Withdraw { investor, percent }),
+        2 => (0..INVESTORS, lamports()).prop_map(|(investor, lamports)| Op::WithdrawExact { investor, lamports }),
+        1 => Just(Op::CollectFees),
+        1 => Just(Op::ClaimFees),
+        1 => lamports().prop_map(|lamports| Op::Gain { lamports }),
+        1 => (1u64..=50).prop_map(|percent| Op::Loss { percent }),
+    ]
+}
+
+struct Harness {
+    bank: TestBank,
+    manager: Pubkey,
+    fund: Pubkey,
+    investors: Vec<Pubkey>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let mut bank = TestBank::new();
+        create_config(&mut bank);
+        let (manager, fund) = create_fund_with(&mut bank, InitializeFundArgs { min_deposit: 1, ..fund_args(SOL) });
+        let investors = (0..INVESTORS).map(|_| bank.wallet(100_000 * SOL)).collect();
+        Self { bank, manager, fund, investors }
+    }
+
+    fn shares_of(&self, investor: &Pubkey) -> u64 {
+        let stake = pda::stake(&self.fund, investor).0;
+        self.bank.account(&stake).map_or(0, |_| self.bank.stake(&stake).shares)
+    }
+
+    /// Apply `op`. Instructions may legitimately fail (e.g. a deposit too small to mint
+    /// a share), in which case the bank keeps no changes.
+    fn apply(&mut self, op: &Op) {
+        let fund = self.fund;
+        let ix = match *op {
+            Op::Deposit { investor, lamports } => instructions::deposit(&fund, &self.investors[investor], lamports, 0),
+            Op::Withdraw { investor, percent } => {
+                let shares = (self.shares_of(&self.investors[investor]) as u128 * percent as u128 / 100) as u64;
+                instructions::withdraw(&fund, &self.investors[investor], shares, 0)
+            }
+            Op::WithdrawExact { investor, lamports } => {
+                instructions::withdraw_exact(&fund, &self.investors[investor], lamports, u64::MAX)
+            }
+            Op::CollectFees => {
+                self.bank.warp_to(self.bank.now() + MIN_FEE_PERIOD);
+                instructions::collect_fees(&fund, &self.manager)
+            }
+            Op::ClaimFees => {
+                self.bank.warp_to(self.bank.now() + MIN_FEE_CLAIM_DELAY);
+                instructions::claim_fees(&fund, &self.manager, None)
+            }
+            Op::Gain { lamports } => return simulate_gain(&mut self.bank, &fund, lamports),
+            Op::Loss { percent } => {
+                let loss = self.bank.fund(&fund).total_assets / 100 * percent;
+                return simulate_loss(&mut self.bank, &fund, loss);
+            }
+        };
+
+        let before = self.bank.fund(&fund);
+        if self.bank.process(&ix).is_err() {
+            return;
+        }
+        let after = self.bank.fund(&fund);
+
+        // Rounding on deposits and withdrawals must land on the fund's side, so the
+        // value of each remaining share never drops
+        if !matches!(op, Op::CollectFees) {
+            assert!(
+                share_price_cmp(&after, &before).is_ge(),
+                "{op:?} lowered the share price: {}/{} -> {}/{}",
+                before.total_assets,
+                before.total_shares,
+                after.total_assets,
+                after.total_shares
+            );
+        }
+    }
+
+    fn check_invariants(&self) {
+        let fund = self.bank.fund(&self.fund);
+        let holders = std::iter::once(&self.manager).chain(&self.investors);
+
+        // Every share is accounted for by exactly one stake
+        let shares: Vec<u64> = holders.map(|holder| self.shares_of(holder)).collect();
+        assert_eq!(shares.iter().sum::<u64>(), fund.total_shares);
+        assert_eq!(shares.iter().filter(|shares| **shares > 0).count() as u32, fund.investor_count);
+
+        // The vault holds at least the investors' assets plus retained platform fees and
+        // escrowed performance fees
+        let vault = self.bank.lamports(&pda::vault(&self.fund).0);
+        let owed = fund.total_assets as u128 + fund.platform_fees as u128 + fund.pending_fees as u128;
+        assert!(vault as u128 >= owed);
+        assert!(vault >= Rent::default().minimum_balance(0) || vault == 0);
+
+        // Redeeming every position at once never asks for more than the fund holds
+        let claims: u128 = shares
+            .iter()
+            .map(|shares| preview::withdraw(&fund, *shares).unwrap().gross as u128)
+            .sum();
+        assert!(claims <= fund.total_assets as u128);
+    }
+}
+
+/// Compare share prices `(assets + VA) / (shares + VS)` exactly, by cross-multiplying
+fn share_price_cmp(a: &Fund, b: &Fund) -> std::cmp::Ordering {
+    let lhs = (a.total_assets as u128 + VIRTUAL_ASSETS as u128) * (b.total_shares as u128 + VIRTUAL_SHARES as u128);
+    let rhs = (b.total_assets as u128 + VIRTUAL_ASSETS as u128) * (a.total_shares as u128 + VIRTUAL_SHARES as u128);
+    lhs.cmp(&rhs)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn share_accounting_holds_under_random_operations(ops in prop::collection::vec(op(), 1..48)) {
+        let mut harness = Harness::new();
+        harness.check_invariants();
+        for op in &ops {
+            harness.apply(op);
+            harness.check_invariants();
+        }
+    }
+
+    #[test]
+    fn round_trips_never_profit_the_investor(
+        seed_gain in 0u64..10 * SOL,
+        deposit in 1u64..100 * SOL,
+        exact in any::<bool>(),
+    ) {
+        let mut harness = Harness::new();
+        if seed_gain > 0 {
+            simulate_gain(&mut harness.bank, &harness.fund.clone(), seed_gain);
+        }
+        let investor = harness.investors[0];
+        let balance = harness.bank.lamports(&investor);
+
+        if harness.bank.process(&instructions::deposit(&harness.fund, &investor, deposit, 0)).is_err() {
+            return Ok(());
+        }
+        if exact {
+            let shares = harness.shares_of(&investor);
+            let half = preview::withdraw(&harness.bank.fund(&harness.fund), shares).unwrap().net / 2;
+            if half > 0 {
+                harness.bank.process(&instructions::withdraw_exact(&harness.fund, &investor, half, shares)).unwrap();
+            }
+        }
+        let shares = harness.shares_of(&investor);
+        harness.bank.process(&instructions::withdraw(&harness.fund, &investor, shares, 0)).unwrap();
+
+        // Stake rent is still held by the (now empty) stake account
+        let rent = harness.bank.lamports(&pda::stake(&harness.fund, &investor).0);
+        prop_assert!(harness.bank.lamports(&investor) + rent <= balance);
+        harness.check_invariants();
+    }
+}
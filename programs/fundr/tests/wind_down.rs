@@ -0,0 +1,214 @@
+mod common;
+
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token::{self, native_mint};
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use fundr::{FundStatus, FundrError};
+
+#[test]
+fn wind_down_stops_deposits() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+
+    let state = bank.fund(&fund);
+    assert_eq!(state.status, FundStatus::WindingDown);
+    assert_eq!(state.wind_down_started_at, bank.now());
+
+    let investor = bank.wallet(2 * SOL);
+    let result = bank.process(&instructions::deposit(&fund, &investor, SOL, 0));
+    assert_fundr_error(result, FundrError::FundNotActive);
+
+    let result = bank.process(&instructions::initiate_wind_down(&fund, &manager));
+    assert_fundr_error(result, FundrError::FundNotActive);
+}
+
+#[test]
+fn initiate_wind_down_rejects_other_signers() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let attacker = bank.wallet(SOL);
+
+    let result = bank.process(&instructions::initiate_wind_down(&fund, &attacker));
+
+    assert_fundr_error(result, FundrError::UnauthorizedManager);
+    assert_eq!(bank.fund(&fund).status, FundStatus::Active);
+}
+
+#[test]
+fn force_distribute_requires_wind_down() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, SOL);
+    let cranker = bank.wallet(SOL);
+
+    let result = bank.process(&instructions::force_distribute(&fund, &investor, &cranker));
+
+    assert_fundr_error(result, FundrError::FundNotWindingDown);
+}
+
+#[test]
+fn force_distribute_pays_out_without_the_withdrawal_fee() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, 2 * SOL);
+    let stake = pda::stake(&fund, &investor).0;
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    let shares = bank.stake(&stake).shares;
+    let expected = preview::withdraw(&bank.fund(&fund), shares).unwrap();
+    let balance = bank.lamports(&investor) + bank.lamports(&stake);
+    let cranker = bank.wallet(SOL);
+
+    bank.process(&instructions::force_distribute(&fund, &investor, &cranker)).unwrap();
+
+    assert_eq!(expected.fee, 0);
+    assert_eq!(bank.lamports(&investor), balance + expected.net);
+    assert!(bank.account(&stake).is_none());
+    assert_eq!(bank.fund(&fund).investor_count, 1);
+}
+
+#[test]
+fn close_fund_requires_wind_down_and_no_outstanding_shares() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+
+    let result = bank.process(&instructions::close_fund(&fund, &manager, &manager, &admin, false, None, &spl_token::ID, &[]));
+    assert_fundr_error(result, FundrError::FundNotWindingDown);
+
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    let result = bank.process(&instructions::close_fund(&fund, &manager, &manager, &admin, false, None, &spl_token::ID, &[]));
+    assert_fundr_error(result, FundrError::FundHasOutstandingShares);
+}
+
+#[test]
+fn close_fund_pays_the_platform_then_the_manager() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let investor = invest(&mut bank, &fund, 2 * SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    let token_account = bank.create_token_account(&spl_token::ID, &mint, &fund, 0);
+    let cranker = bank.wallet(SOL);
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    let result = bank.process(&instructions::close_stake(&fund, &investor));
+    assert_fundr_error(result, FundrError::AccountNotEmpty);
+    let shares = bank.stake(&pda::stake(&fund, &investor).0).shares;
+    bank.process(&instructions::withdraw(&fund, &investor, shares, 0)).unwrap();
+    bank.process(&instructions::force_distribute(&fund, &manager, &cranker)).unwrap();
+
+    let attacker = bank.wallet(SOL);
+    let ix = instructions::close_fund(&fund, &manager, &attacker, &admin, false, None, &spl_token::ID, &[token_account]);
+    assert_fundr_error(bank.process(&ix), FundrError::UnauthorizedManager);
+    let ix = instructions::close_fund(&fund, &manager, &manager, &attacker, false, None, &spl_token::ID, &[token_account]);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAccount);
+
+    let platform_fees = bank.fund(&fund).platform_fees;
+    let platform = bank.lamports(&admin) + platform_fees;
+    let expected = bank.lamports(&manager) + bank.lamports(&fund) + bank.lamports(&pda::vault(&fund).0) - platform_fees
+        + bank.lamports(&token_account);
+    assert!(platform_fees > 0);
+    bank.process(&instructions::close_fund(&fund, &manager, &manager, &admin, false, None, &spl_token::ID, &[token_account])).unwrap();
+
+    assert_eq!(bank.lamports(&admin), platform);
+    assert_eq!(bank.lamports(&manager), expected);
+    assert!(bank.account(&fund).is_none());
+    assert!(bank.account(&pda::vault(&fund).0).is_none());
+    assert!(bank.account(&token_account).is_none());
+
+    // Emptied stakes are closed by their owners without the fund
+    let rent = bank.lamports(&pda::stake(&fund, &investor).0);
+    let balance = bank.lamports(&investor);
+    bank.process(&instructions::close_stake(&fund, &investor)).unwrap();
+    assert_eq!(bank.lamports(&investor), balance + rent);
+}
+
+#[test]
+fn a_closed_fund_address_is_never_reused() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, 0);
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    bank.process(&instructions::close_fund(&fund, &manager, &manager, &admin, false, None, &spl_token::ID, &[])).unwrap();
+
+    // The same index would land on the closed fund's address
+    assert!(bank.process(&instructions::initialize_fund(&manager, 0, fund_args(SOL))).is_err());
+    assert_eq!(bank.manager_profile(&manager).fund_count, 1);
+    bank.process(&instructions::initialize_fund(&manager, 1, fund_args(SOL))).unwrap();
+
+    let next = pda::fund_at(&manager, 1).0;
+    assert_ne!(next, fund);
+    assert_eq!(bank.fund(&next).fund_index, 1);
+    assert_eq!(bank.manager_profile(&manager).fund_count, 2);
+    assert!(bank.account(&fund).is_none());
+}
+
+#[test]
+fn close_fund_rejects_token_accounts_holding_tokens() {
+    let mut bank = TestBank::new();
+    let admin = create_config(&mut bank);
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    let funded = bank.create_token_account(&spl_token::ID, &mint, &fund, 1);
+    let cranker = bank.wallet(SOL);
+    bank.process(&instructions::initiate_wind_down(&fund, &manager)).unwrap();
+    bank.process(&instructions::force_distribute(&fund, &manager, &cranker)).unwrap();
+
+    let result = bank.process(&instructions::close_fund(&fund, &manager, &manager, &admin, false, None, &spl_token::ID, &[funded]));
+
+    assert_fundr_error(result, FundrError::TokenAccountNotEmpty);
+}
+
+#[test]
+fn sync_assets_counts_vault_lamports_and_wrapped_sol() {
+    let mut bank = TestBank::new();
+    let (_, fund) = create_fund(&mut bank, SOL);
+    let before = bank.fund(&fund);
+    bank.create_token_account_at(&pda::wsol_account(&fund), &spl_token::ID, &native_mint::ID, &fund, SOL / 2);
+    bank.airdrop(&pda::vault(&fund).0, SOL / 4);
+
+    bank.process(&instructions::sync_assets(&fund, &[])).unwrap();
+
+    // The vault's rent reserve was never part of the fund's assets
+    assert_eq!(bank.fund(&fund).total_assets, before.total_assets + SOL / 4 + SOL / 2);
+}
+
+#[test]
+fn sync_assets_rejects_wrapped_sol_held_by_someone_else() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    bank.create_token_account_at(&pda::wsol_account(&fund), &spl_token::ID, &native_mint::ID, &manager, SOL);
+
+    let result = bank.process(&instructions::sync_assets(&fund, &[]));
+
+    assert_fundr_error(result, FundrError::InvalidAccount);
+}
+
+#[test]
+fn sync_assets_checks_the_fund_holds_no_position_tokens() {
+    let mut bank = TestBank::new();
+    let (manager, fund) = create_fund(&mut bank, SOL);
+    let mint = bank.create_mint(&spl_token::ID);
+    bank.process(&instructions::initialize_portfolio(&fund, &manager)).unwrap();
+    bank.process(&instructions::set_position(&fund, &manager, &mint, 5_000)).unwrap();
+    bank.airdrop(&pda::vault(&fund).0, SOL);
+    let position = [(mint, spl_token::ID)];
+
+    // Every position's token account must be shown, or its tokens would be written off
+    assert_fundr_error(bank.process(&instructions::sync_assets(&fund, &[])), FundrError::PositionsNotValued);
+    let ix = instructions::sync_assets(&fund, &[(mint, spl_token_2022::ID)]);
+    assert_fundr_error(bank.process(&ix), FundrError::InvalidAccount);
+
+    // A position the fund never bought into, or has sold out of, holds nothing
+    bank.process(&instructions::sync_assets(&fund, &position)).unwrap();
+    assert_eq!(bank.fund(&fund).total_assets, SOL / 100 * 99 + SOL);
+    let token_account = get_associated_token_address(&fund, &mint);
+    bank.create_token_account_at(&token_account, &spl_token::ID, &mint, &fund, 0);
+    bank.process(&instructions::sync_assets(&fund, &position)).unwrap();
+
+    bank.create_token_account_at(&token_account, &spl_token::ID, &mint, &fund, 1);
+    let result = bank.process(&instructions::sync_assets(&fund, &position));
+    assert_fundr_error(result, FundrError::PositionsNotValued);
+}